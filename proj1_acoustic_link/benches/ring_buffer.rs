@@ -0,0 +1,61 @@
+//! Compare the time spent in a simulated real-time audio callback
+//! when the samples are pushed into a `ConcurrentBuffer` or a lock-free ring buffer,
+//! while another thread keeps reading the buffer.
+//! Run with `cargo bench --bench ring_buffer`.
+use proj1_acoustic_link::{
+  block_buffer::{ring_buffer, ConcurrentBuffer},
+  traits::{InStream, OutStream, Sample, FP},
+  DefaultConfig,
+};
+use std::{
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  thread,
+  time::{Duration, Instant},
+};
+
+const BLOCK: usize = DefaultConfig::BUFFER_SIZE;
+const CALLBACKS: usize = 20000;
+
+// call `callback` repeatedly while `reader` keeps draining the buffer in another thread,
+// return the average and the worst latency of the callback
+fn run<I, O>(mut writer: O, mut reader: I) -> (Duration, Duration)
+where
+  I: InStream<FP, ()> + Send + 'static,
+  O: OutStream<FP, ()>,
+{
+  let stop = Arc::new(AtomicBool::new(false));
+  let stop_reader = stop.clone();
+  let handler = thread::spawn(move || {
+    let mut buf = vec![FP::ZERO; BLOCK];
+    while !stop_reader.load(Ordering::Relaxed) {
+      reader.read(&mut buf).unwrap();
+    }
+  });
+
+  let block = vec![FP::ZERO; BLOCK];
+  let mut total = Duration::ZERO;
+  let mut worst = Duration::ZERO;
+  for _ in 0..CALLBACKS {
+    let start = Instant::now();
+    writer.write(&block).unwrap();
+    let elapsed = start.elapsed();
+    total += elapsed;
+    worst = worst.max(elapsed);
+  }
+  stop.store(true, Ordering::Relaxed);
+  handler.join().unwrap();
+  (total / CALLBACKS as u32, worst)
+}
+
+fn main() {
+  let buffer = ConcurrentBuffer::new();
+  let (avg, worst) = run(buffer.clone(), buffer);
+  println!("ConcurrentBuffer: average {:?}, worst {:?} per callback", avg, worst);
+
+  let (producer, consumer) = ring_buffer(BLOCK * 64);
+  let (avg, worst) = run(producer, consumer);
+  println!("ring buffer: average {:?}, worst {:?} per callback", avg, worst);
+}
@@ -0,0 +1,213 @@
+use clap::{Parser, ValueEnum};
+#[cfg(not(feature = "nofloat"))]
+use proj1_acoustic_link::phy_packet::modem::{OfdmParams, OFDM};
+use proj1_acoustic_link::{
+  helper::CrcSeq,
+  phy_layer::CrcPhy,
+  phy_packet::{
+    frame_detect::CorrelationFraming,
+    modem::{DpskParams, FskParams, LineCode, LineCodeParams, PskParams, DPSK, FSK, PSK},
+    preambles::ChirpUpDown,
+    replay::{align, replay, ReplayFrame},
+    Modem, PhyPacket,
+  },
+  sample_stream::{ChannelMix, HoundInConfig, HoundInStream},
+  AudioConfig,
+};
+use std::{
+  fs,
+  io::{Error, Result},
+  path::PathBuf,
+};
+
+/// Decode a recorded session (a wav capture) offline with the full receive chain,
+/// print every frame detected, and report BER/PER against the packets actually sent.
+#[derive(Parser)]
+struct ReplayCli {
+  /// the wav capture, resampled to the sampling rate of the PHY layer if needed
+  capture: PathBuf,
+  /// modulation method of the frames
+  #[arg(long, value_enum, default_value_t = Method::LineCode)]
+  modem: Method,
+  /// integrity check of the decoded packets
+  #[arg(long, value_enum, default_value_t = Check::None)]
+  check: Check,
+  /// the bytes of the packets sent, one packet after another
+  #[arg(long)]
+  reference: Option<PathBuf>,
+  /// only decode the given channel of the capture, downmix all the channels if not given
+  #[arg(long)]
+  channel: Option<u16>,
+  /// sampling rate of the PHY layer
+  #[arg(long, default_value_t = AudioConfig::default().sample_rate)]
+  sample_rate: u32,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Method {
+  LineCode,
+  Psk,
+  Dpsk,
+  Fsk,
+  #[cfg(not(feature = "nofloat"))]
+  Ofdm,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Check {
+  None,
+  /// CRC16 of [`CrcPhy`]
+  Crc16,
+  /// CRC14 with sequence number of [`CrcSeq`]
+  CrcSeq,
+}
+
+/// number of bit errors between two packets, the missing/extra bytes are all wrong
+fn bit_errors(a: &[u8], b: &[u8]) -> usize {
+  a.iter()
+    .zip(b)
+    .map(|(x, y)| (x ^ y).count_ones() as usize)
+    .sum::<usize>()
+    + 8 * a.len().abs_diff(b.len())
+}
+
+fn hex(packet: &[u8]) -> String {
+  packet.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+/// decode the capture and print the report,
+/// `modem` builds the modem for the audio config,
+/// `crc_seq` unpacks a packet of the modem with [`CrcSeq`]
+fn run<MM: Modem + Send + 'static>(
+  cli: &ReplayCli,
+  config: &AudioConfig,
+  modem: fn(&AudioConfig) -> MM,
+  crc_seq: fn(&[u8]) -> Option<(PhyPacket, u8)>,
+) -> Result<()> {
+  let channel = match cli.channel {
+    Some(channel) => ChannelMix::Pick(channel),
+    None => ChannelMix::Downmix,
+  };
+  let stream_in = HoundInStream::open_with_config(
+    &cli.capture,
+    &HoundInConfig {
+      channel,
+      sample_rate: Some(config.sample_rate),
+    },
+  )
+  .map_err(Error::other)?;
+  let detector = CorrelationFraming::with_modem(ChirpUpDown::with_config(config), modem(config));
+  let modem = modem(config);
+  let packet_bytes = modem.bytes_per_packet();
+  let frames = replay(stream_in, detector, modem).map_err(Error::other)?;
+  let reference: Option<Vec<PhyPacket>> = match cli.reference.as_ref() {
+    Some(path) => Some(
+      fs::read(path)?
+        .chunks(packet_bytes)
+        .map(|chunk| chunk.to_vec())
+        .collect(),
+    ),
+    None => None,
+  };
+
+  // the frames are paired with the packets sent by their sequence numbers if checked with `CrcSeq`
+  let pairs = match reference.as_ref() {
+    Some(reference) => match cli.check {
+      Check::CrcSeq => align(&frames, reference, |packet| crc_seq(packet).map(|(_, seq)| seq)),
+      _ => align(&frames, reference, |_| None),
+    },
+    None => vec![None; frames.len()],
+  };
+
+  for (i, (ReplayFrame { offset, peak, packet }, pair)) in frames.iter().zip(&pairs).enumerate() {
+    let check = match cli.check {
+      Check::None => "",
+      Check::Crc16 if CrcPhy::verify(packet) => " crc ok",
+      Check::CrcSeq if crc_seq(packet).is_some() => " crc ok",
+      _ => " crc BAD",
+    };
+    let errors = match (reference.as_ref(), pair) {
+      (Some(reference), Some(j)) => format!(" packet #{} bit errors {}", j, bit_errors(packet, &reference[*j])),
+      (Some(_), None) => " unpaired".to_string(),
+      (None, _) => String::new(),
+    };
+    println!(
+      "#{} offset {} peak {:.2}{}{} {}",
+      i,
+      offset,
+      peak,
+      check,
+      errors,
+      hex(packet)
+    );
+  }
+
+  if let Some(reference) = reference {
+    let received: Vec<(&ReplayFrame, &PhyPacket)> = frames
+      .iter()
+      .zip(&pairs)
+      .filter_map(|(frame, pair)| pair.map(|j| (frame, &reference[j])))
+      .collect();
+    let missed = reference.len() - received.len();
+    // the BER is measured on the packets received, the packets missed are counted by the PER
+    let bits: usize = received.iter().map(|(_, sent)| sent.len() * 8).sum();
+    let errors: usize = received
+      .iter()
+      .map(|(frame, sent)| bit_errors(&frame.packet, sent))
+      .sum();
+    let bad_packets = received.iter().filter(|(frame, sent)| &frame.packet != *sent).count();
+    println!(
+      "{} frames detected, {} packets sent, {} missed, {} unpaired frames: BER {:.3e}, PER {:.3}",
+      frames.len(),
+      reference.len(),
+      missed,
+      frames.len() - received.len(),
+      errors as f64 / bits.max(1) as f64,
+      (bad_packets + missed) as f64 / reference.len().max(1) as f64
+    );
+  } else {
+    println!("{} frames detected", frames.len());
+  }
+  Ok(())
+}
+
+fn main() -> Result<()> {
+  let cli = ReplayCli::parse();
+  let config = AudioConfig {
+    sample_rate: cli.sample_rate,
+    ..Default::default()
+  };
+  match cli.modem {
+    Method::LineCode => run(
+      &cli,
+      &config,
+      LineCode::with_config,
+      CrcSeq::<{ LineCodeParams::DEFAULT.bytes_per_packet() }>::unpack,
+    ),
+    Method::Psk => run(
+      &cli,
+      &config,
+      PSK::with_config,
+      CrcSeq::<{ PskParams::DEFAULT.bytes_per_packet() }>::unpack,
+    ),
+    Method::Dpsk => run(
+      &cli,
+      &config,
+      DPSK::with_config,
+      CrcSeq::<{ DpskParams::DEFAULT.bytes_per_packet() }>::unpack,
+    ),
+    Method::Fsk => run(
+      &cli,
+      &config,
+      FSK::with_config,
+      CrcSeq::<{ FskParams::DEFAULT.bytes_per_packet() }>::unpack,
+    ),
+    #[cfg(not(feature = "nofloat"))]
+    Method::Ofdm => run(
+      &cli,
+      &config,
+      OFDM::with_config,
+      CrcSeq::<{ OfdmParams::DEFAULT.bytes_per_packet() }>::unpack,
+    ),
+  }
+}
@@ -1,5 +1,10 @@
 mod buffer;
 mod concurrent_buffer;
+mod ring_buffer;
 
 pub use buffer::Buffer;
 pub use concurrent_buffer::ConcurrentBuffer;
+pub use ring_buffer::{ring_buffer, RingConsumer, RingProducer};
+
+#[cfg(test)]
+mod tests;
@@ -79,7 +79,7 @@ impl<T: Clone> InStream<T, ()> for ConcurrentBuffer<T> {
     while n < dest.len() {
       let &(ref lock, _) = &*self.0;
       let mut buf = lock.lock();
-      if let Ok(m) = buf.read(dest) {
+      if let Ok(m) = buf.read(&mut dest[n..]) {
         n += m;
       }
       thread::yield_now();
@@ -0,0 +1,222 @@
+use crate::traits::{InStream, OutStream};
+use crossbeam::utils::CachePadded;
+use std::{
+  cell::UnsafeCell,
+  mem::MaybeUninit,
+  sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+  },
+  thread,
+  time::Duration,
+};
+
+/// the interval of polling when a blocking operation has to wait for the other side
+const POLL_INTERVAL: Duration = Duration::from_micros(100);
+
+// the storage shared by the producer and the consumer.
+// `head` and `tail` are monotonic (wrapping) counters of the elements popped and pushed,
+// the slot of a counter `i` is `i % capacity`.
+struct Shared<T> {
+  slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+  // owned by the consumer
+  head: CachePadded<AtomicUsize>,
+  // owned by the producer
+  tail: CachePadded<AtomicUsize>,
+  // the producer asks the consumer to discard the elements before `discard_until`
+  discard: AtomicBool,
+  discard_until: AtomicUsize,
+}
+
+// Safety: a slot is either written by the producer or read by the consumer, never both at the same time.
+// The producer only writes the slots in `[tail, head + capacity)`, the consumer only reads the slots in `[head, tail)`,
+// and the counters are published with release/acquire ordering after the slots are accessed.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+  fn capacity(&self) -> usize {
+    self.slots.len()
+  }
+}
+
+/// The writing end of a ring buffer, see [`ring_buffer`].
+pub struct RingProducer<T> {
+  shared: Arc<Shared<T>>,
+  tail: usize,
+}
+
+/// The reading end of a ring buffer, see [`ring_buffer`].
+pub struct RingConsumer<T> {
+  shared: Arc<Shared<T>>,
+  head: usize,
+}
+
+/// Create a preallocated single-producer/single-consumer ring buffer holding at most `capacity` elements.
+///
+/// Pushing/popping on either end is wait-free: no lock, no allocation, no system call.
+/// So the ends can be used in real-time audio callbacks.
+/// The blocking operations (`read_exact`/`write_exact`/`wait`) poll the other end.
+pub fn ring_buffer<T: Copy>(capacity: usize) -> (RingProducer<T>, RingConsumer<T>) {
+  assert!(capacity > 0, "ring buffer capacity must be positive");
+  let slots = (0..capacity).map(|_| UnsafeCell::new(MaybeUninit::uninit())).collect();
+  let shared = Arc::new(Shared {
+    slots,
+    head: CachePadded::new(AtomicUsize::new(0)),
+    tail: CachePadded::new(AtomicUsize::new(0)),
+    discard: AtomicBool::new(false),
+    discard_until: AtomicUsize::new(0),
+  });
+  let producer = RingProducer {
+    shared: shared.clone(),
+    tail: 0,
+  };
+  let consumer = RingConsumer { shared, head: 0 };
+  (producer, consumer)
+}
+
+impl<T: Copy> RingProducer<T> {
+  /// maximum number of elements in the buffer
+  pub fn capacity(&self) -> usize {
+    self.shared.capacity()
+  }
+
+  /// number of elements that can be pushed right now
+  pub fn free_len(&self) -> usize {
+    let head = self.shared.head.load(Ordering::Acquire);
+    self.capacity() - self.tail.wrapping_sub(head)
+  }
+
+  /// Push as many elements of `src` as possible, return the number of elements pushed.
+  pub fn push_slice(&mut self, src: &[T]) -> usize {
+    let n = src.len().min(self.free_len());
+    let cap = self.capacity();
+    src[..n].iter().enumerate().for_each(|(i, x)| {
+      let slot = &self.shared.slots[self.tail.wrapping_add(i) % cap];
+      // Safety: the slot is free, see `Shared`
+      unsafe { (*slot.get()).write(*x) };
+    });
+    self.tail = self.tail.wrapping_add(n);
+    self.shared.tail.store(self.tail, Ordering::Release);
+    n
+  }
+
+  /// Ask the consumer to discard all the elements pushed so far.
+  /// It takes effect on the next pop of the consumer.
+  pub fn clear(&mut self) {
+    self.shared.discard_until.store(self.tail, Ordering::Relaxed);
+    self.shared.discard.store(true, Ordering::Release);
+  }
+
+  /// whether the consumer has popped all the pushed elements
+  pub fn is_empty(&self) -> bool {
+    self.free_len() == self.capacity()
+  }
+
+  // the consumer has been dropped, no one will pop the elements
+  fn disconnected(&self) -> bool {
+    Arc::strong_count(&self.shared) == 1
+  }
+}
+
+impl<T: Copy> RingConsumer<T> {
+  /// maximum number of elements in the buffer
+  pub fn capacity(&self) -> usize {
+    self.shared.capacity()
+  }
+
+  /// number of elements that can be popped right now
+  pub fn len(&mut self) -> usize {
+    self.sync_discard();
+    self.shared.tail.load(Ordering::Acquire).wrapping_sub(self.head)
+  }
+
+  /// whether there is no element to pop right now
+  pub fn is_empty(&mut self) -> bool {
+    self.len() == 0
+  }
+
+  /// Pop as many elements as possible into `dest`, return the number of elements popped.
+  pub fn pop_slice(&mut self, dest: &mut [T]) -> usize {
+    let n = dest.len().min(self.len());
+    let cap = self.capacity();
+    dest[..n].iter_mut().enumerate().for_each(|(i, x)| {
+      let slot = &self.shared.slots[self.head.wrapping_add(i) % cap];
+      // Safety: the slot has been written by the producer, see `Shared`
+      *x = unsafe { (*slot.get()).assume_init() };
+    });
+    self.head = self.head.wrapping_add(n);
+    self.shared.head.store(self.head, Ordering::Release);
+    n
+  }
+
+  // skip the elements discarded by the producer
+  fn sync_discard(&mut self) {
+    if self.shared.discard.swap(false, Ordering::Acquire) {
+      let until = self.shared.discard_until.load(Ordering::Relaxed);
+      if until.wrapping_sub(self.head) <= self.capacity() {
+        self.head = until;
+        self.shared.head.store(self.head, Ordering::Release);
+      }
+    }
+  }
+
+  // the producer has been dropped, no more elements will come
+  fn disconnected(&self) -> bool {
+    Arc::strong_count(&self.shared) == 1
+  }
+}
+
+impl<T: Copy> InStream<T, ()> for RingConsumer<T> {
+  /// Pop as many as possible elements and return immediately with the number of elements read.
+  fn read(&mut self, buf: &mut [T]) -> Result<usize, ()> {
+    Ok(self.pop_slice(buf))
+  }
+
+  /// Read exactly `buf.len()` elements, block until all of them have been pushed by the producer.
+  /// Fail if the producer is dropped before that.
+  fn read_exact(&mut self, buf: &mut [T]) -> Result<(), ()> {
+    let mut n = 0;
+    while n < buf.len() {
+      let m = self.pop_slice(&mut buf[n..]);
+      n += m;
+      if m == 0 {
+        if self.disconnected() && self.is_empty() {
+          return Err(());
+        }
+        thread::sleep(POLL_INTERVAL);
+      }
+    }
+    Ok(())
+  }
+}
+
+impl<T: Copy> OutStream<T, ()> for RingProducer<T> {
+  /// Push as many as possible elements and return immediately with the number of elements written.
+  fn write(&mut self, buf: &[T]) -> Result<usize, ()> {
+    Ok(self.push_slice(buf))
+  }
+
+  /// Write exactly `buf.len()` elements, block until there is enough space in the buffer.
+  /// Fail if the consumer is dropped before that.
+  fn write_exact(&mut self, buf: &[T]) -> Result<(), ()> {
+    let mut n = 0;
+    while n < buf.len() {
+      let m = self.push_slice(&buf[n..]);
+      n += m;
+      if m == 0 {
+        if self.disconnected() {
+          return Err(());
+        }
+        thread::sleep(POLL_INTERVAL);
+      }
+    }
+    Ok(())
+  }
+
+  /// wait for the consumer to pop all the elements, or the consumer is dropped
+  fn wait(&mut self) {
+    while !self.is_empty() && !self.disconnected() {
+      thread::sleep(POLL_INTERVAL);
+    }
+  }
+}
@@ -0,0 +1,64 @@
+use super::{ring_buffer, ConcurrentBuffer};
+use crate::traits::{InStream, OutStream};
+use std::{thread, time::Duration};
+
+#[test]
+fn ring_wrap_around() {
+  let (mut tx, mut rx) = ring_buffer::<u32>(5);
+  let mut buf = [0; 5];
+  assert_eq!(tx.write(&[1, 2, 3]).unwrap(), 3);
+  assert_eq!(rx.read(&mut buf[..2]).unwrap(), 2);
+  assert_eq!(buf[..2], [1, 2]);
+  // only 4 slots are free, the rest is rejected
+  assert_eq!(tx.write(&[4, 5, 6, 7, 8]).unwrap(), 4);
+  assert_eq!(rx.read(&mut buf).unwrap(), 5);
+  assert_eq!(buf, [3, 4, 5, 6, 7]);
+  assert_eq!(rx.read(&mut buf).unwrap(), 0);
+}
+
+#[test]
+fn ring_clear() {
+  let (mut tx, mut rx) = ring_buffer::<u32>(8);
+  tx.write_exact(&[1, 2, 3]).unwrap();
+  tx.clear();
+  tx.write_exact(&[4]).unwrap();
+  let mut buf = [0; 8];
+  assert_eq!(rx.read(&mut buf).unwrap(), 1);
+  assert_eq!(buf[0], 4);
+}
+
+/// the blocking operations work across threads with a buffer smaller than the data
+#[test]
+fn ring_blocking() {
+  const LEN: u32 = 100000;
+  let (mut tx, mut rx) = ring_buffer::<u32>(1000);
+  let data: Vec<u32> = (0..LEN).collect();
+  let producer = thread::spawn(move || {
+    data.chunks(3000).for_each(|chunk| tx.write_exact(chunk).unwrap());
+    tx.wait();
+  });
+  let mut received = vec![0; LEN as usize];
+  rx.read_exact(&mut received).unwrap();
+  producer.join().unwrap();
+  assert!(received.into_iter().eq(0..LEN));
+  // the producer is gone
+  assert!(rx.read_exact(&mut [0]).is_err());
+}
+
+/// `read_exact` fills the destination in order when the samples arrive in several pieces
+#[test]
+fn concurrent_read_exact_pieces() {
+  let buffer = ConcurrentBuffer::<u32>::new();
+  let mut tx = buffer.clone();
+  let mut rx = buffer;
+  let producer = thread::spawn(move || {
+    for chunk in (0..100).collect::<Vec<_>>().chunks(10) {
+      tx.write_exact(chunk).unwrap();
+      thread::sleep(Duration::from_millis(1));
+    }
+  });
+  let mut received = vec![0; 100];
+  rx.read_exact(&mut received).unwrap();
+  producer.join().unwrap();
+  assert!(received.into_iter().eq(0..100));
+}
@@ -1,3 +1,4 @@
+use crate::phy_packet::params::{positive, ParamError};
 use cpal::StreamConfig;
 use hound::WavSpec;
 
@@ -37,3 +38,60 @@ impl Into<WavSpec> for DefaultConfig {
     }
   }
 }
+
+/// Audio stream configurations chosen at runtime.
+/// The default value is given by [`DefaultConfig`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AudioConfig {
+  /// number of samples per second
+  pub sample_rate: u32,
+  /// number of channels of the audio device
+  pub channels: u16,
+  /// number of samples in one block of the audio device callback
+  pub buffer_size: usize,
+  /// number of recent samples used to estimate the channel power
+  pub pwr_probe_wind: usize,
+}
+
+impl Default for AudioConfig {
+  fn default() -> Self {
+    Self {
+      sample_rate: DefaultConfig::SAMPLE_RATE,
+      channels: DefaultConfig::CHANNELS,
+      buffer_size: DefaultConfig::BUFFER_SIZE,
+      pwr_probe_wind: DefaultConfig::PWR_PROBE_WIND,
+    }
+  }
+}
+
+impl AudioConfig {
+  /// Check that the sample rate, the number of channels, the buffer size and the power probe window are positive,
+  /// a stream or a power probe can not be built on the config otherwise.
+  pub fn validate(&self) -> Result<(), ParamError> {
+    positive("sample rate", self.sample_rate as usize)?;
+    positive("channels", self.channels as usize)?;
+    positive("buffer size", self.buffer_size)?;
+    positive("power probe window", self.pwr_probe_wind)
+  }
+}
+
+impl From<&AudioConfig> for StreamConfig {
+  fn from(config: &AudioConfig) -> Self {
+    StreamConfig {
+      channels: config.channels,
+      sample_rate: cpal::SampleRate(config.sample_rate),
+      buffer_size: cpal::BufferSize::Fixed(config.buffer_size as u32),
+    }
+  }
+}
+
+impl From<&AudioConfig> for WavSpec {
+  fn from(config: &AudioConfig) -> Self {
+    WavSpec {
+      channels: config.channels,
+      sample_rate: config.sample_rate,
+      bits_per_sample: DefaultConfig::BITS_PER_SAMPE,
+      sample_format: hound::SampleFormat::Float,
+    }
+  }
+}
@@ -1,6 +1,7 @@
 mod bytes_bits;
 pub use bytes_bits::{
-  bits_to_bytes, bits_to_chars, bytes_to_bits, chars_to_bits, decode_4b5b, decode_nrzi, encode_4b5b, encode_nrzi,
+  bits_to_bytes, bits_to_chars, bytes_to_bits, chars_to_bits, decode_4b5b, decode_4b5b_soft, decode_nrzi,
+  decode_nrzi_soft, encode_4b5b, encode_nrzi,
 };
 
 mod paddings;
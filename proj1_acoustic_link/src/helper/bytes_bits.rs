@@ -91,6 +91,37 @@ pub fn decode_4b5b(bits: Bvec) -> Bvec {
   }
   out
 }
+/// Soft [`decode_4b5b`] of log-likelihood ratios, positive for a `0`:
+/// the max-log approximation over the 16 codes, the most likely code with the bit at `0` against the one at `1`.
+pub fn decode_4b5b_soft(llrs: &[f32]) -> Vec<f32> {
+  assert!(llrs.len().is_multiple_of(5));
+  llrs
+    .chunks_exact(5)
+    .flat_map(|group| {
+      // log-likelihood of each code, up to a constant
+      let likelihood: Vec<f32> = TBL
+        .iter()
+        .map(|code| {
+          let bit = |j: usize| (code >> (4 - j)) & 1;
+          group
+            .iter()
+            .enumerate()
+            .map(|(j, llr)| if bit(j) == 0 { llr / 2.0 } else { -llr / 2.0 })
+            .sum()
+        })
+        .collect();
+      (0..4).rev().map(move |k| {
+        let best = |bit: usize| {
+          (0..TBL.len())
+            .filter(|val| (val >> k) & 1 == bit)
+            .map(|val| likelihood[val])
+            .fold(f32::MIN, f32::max)
+        };
+        best(0) - best(1)
+      })
+    })
+    .collect()
+}
 
 pub fn encode_nrzi(bits: Bvec) -> Bvec {
   let mut out = Bvec::with_capacity(bits.len());
@@ -112,3 +143,17 @@ pub fn decode_nrzi(bits: Bvec) -> Bvec {
   }
   out
 }
+/// Soft [`decode_nrzi`] of log-likelihood ratios, positive for a `0`:
+/// a data bit is the exclusive or of two code bits, as reliable as the less reliable of them.
+pub fn decode_nrzi_soft(llrs: &[f32]) -> Vec<f32> {
+  // the line starts low, a sure `0`
+  let mut cur = f32::INFINITY;
+  llrs
+    .iter()
+    .map(|&llr| {
+      let out = cur.signum() * llr.signum() * cur.abs().min(llr.abs());
+      cur = llr;
+      out
+    })
+    .collect()
+}
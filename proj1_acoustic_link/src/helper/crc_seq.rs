@@ -25,7 +25,7 @@ const CRC_BITS: usize = 14;
 pub const SEQ_MOD: u8 = 1 << SEQ_BITS;
 
 /// CRC + Sequence Number helper.  
-/// `PACK_SIZE`: total number bytes in one packet.
+/// `PACK_SIZE`: maximum number of bytes in one packet.
 ///
 /// The last two bytes are preserved.
 /// - byte 1: sequence number and higher 6 bits of CRC-14
@@ -35,14 +35,14 @@ pub struct CrcSeq<const PACK_SIZE: usize>;
 impl<const PACK_SIZE: usize> CrcSeq<PACK_SIZE> {
   /// number of bytes in additional non-data section
   pub const NONDATA_SIZE: usize = 2;
-  /// encoding data bytes in a packet
+  /// maximum number of data bytes in a packet
   pub const DATA_SIZE: usize = PACK_SIZE - Self::NONDATA_SIZE;
 
   /// add sequence number and checksum to a chunk of data,
   /// return a packet with crc+seq.  
-  /// `data` must have exactly [`CrcSeq::DATA_SIZE`] bytes.
+  /// `data` must have at most [`CrcSeq::DATA_SIZE`] bytes.
   pub fn pack(data: &[u8], seq: u8) -> Vec<u8> {
-    assert_eq!(data.len(), Self::DATA_SIZE);
+    assert!(data.len() <= Self::DATA_SIZE);
     assert!(seq < SEQ_MOD);
     let mut packet = data.to_vec();
     packet.extend([0, 0]);
@@ -58,9 +58,12 @@ impl<const PACK_SIZE: usize> CrcSeq<PACK_SIZE> {
   }
 
   /// try to extract the data packet and sequence number,
-  /// if the data is corrupted, [`None`] is returned.
+  /// if the data is corrupted or the packet is too short, [`None`] is returned.
   pub fn unpack(packet: &[u8]) -> Option<(Vec<u8>, u8)> {
-    assert_eq!(packet.len(), PACK_SIZE);
+    assert!(packet.len() <= PACK_SIZE);
+    if packet.len() < Self::NONDATA_SIZE {
+      return None;
+    }
     let mut packet = packet.to_vec();
     // extract the sequence number field
     let seq = *ridx(&packet, 1) >> (CRC_BITS - 8);
@@ -77,7 +80,7 @@ impl<const PACK_SIZE: usize> CrcSeq<PACK_SIZE> {
     // check data integrity
     let verify_crc = CRC14.checksum(&packet);
     if verify_crc == chk {
-      Some((packet[..Self::DATA_SIZE].to_vec(), seq))
+      Some((packet[..packet.len() - Self::NONDATA_SIZE].to_vec(), seq))
     } else {
       None
     }
@@ -1,7 +1,10 @@
 use bitvec::prelude::*;
 use rand::{distributions::Standard, Rng, RngCore};
 
-use super::{add_padding, decode_4b5b, decode_nrzi, encode_4b5b, encode_nrzi, remove_padding, CrcSeq};
+use super::{
+  add_padding, decode_4b5b, decode_4b5b_soft, decode_nrzi, decode_nrzi_soft, encode_4b5b, encode_nrzi, remove_padding,
+  CrcSeq,
+};
 
 type CS = CrcSeq<9>;
 const TESTS: usize = 100;
@@ -55,6 +58,16 @@ fn crcseq_err() {
     assert_eq!(CS::unpack(&pack), None);
   }
 }
+#[test]
+fn crcseq_short() {
+  for len in 0..=CS::DATA_SIZE {
+    let data: Vec<u8> = rand::thread_rng().sample_iter(Standard).take(len).collect();
+    let pack = CS::pack(&data, 1);
+    assert_eq!(pack.len(), len + CS::NONDATA_SIZE);
+    assert_eq!(CS::unpack(&pack), Some((data, 1)));
+  }
+  assert_eq!(CS::unpack(&[0]), None);
+}
 
 #[test]
 fn fbfb() {
@@ -77,3 +90,17 @@ fn nrzi() {
     assert_eq!(dec, bits);
   }
 }
+
+/// the soft decoders of 4b5b and NRZI agree with the hard ones on sure bits
+#[test]
+fn soft_line_code() {
+  for _ in 0..TESTS {
+    let bytes: Vec<u8> = rand::thread_rng().sample_iter(Standard).take(4).collect();
+    let bits = bytes.view_bits();
+    let enc = encode_nrzi(encode_4b5b(bits.to_owned()));
+    let llrs: Vec<f32> = enc.iter().map(|bit| if *bit { -1.0 } else { 1.0 }).collect();
+    let dec = decode_4b5b_soft(&decode_nrzi_soft(&llrs));
+    let dec: Vec<bool> = dec.iter().map(|llr| *llr < 0.0).collect();
+    assert_eq!(dec, bits.iter().map(|bit| *bit).collect::<Vec<_>>());
+  }
+}
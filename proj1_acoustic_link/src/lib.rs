@@ -1,7 +1,7 @@
 /// common helper functions
 pub mod helper;
 
-/// defines [`traits::InStream`]/[`traits::OutStream`] and
+/// defines [`traits::InStream`]/[`traits::OutStream`]/[`traits::PowerProbe`] and
 /// [`traits::PacketSender`]/[`traits::PacketReceiver`] traits.
 pub mod traits;
 
@@ -16,9 +16,9 @@ pub mod phy_packet;
 /// where the stream data type is floating point 32bit PCM sample.
 pub mod sample_stream;
 
-/// blockwise buffer and its thread safe wrapper.
+/// blockwise buffer, its thread safe wrapper and a lock-free ring buffer for real-time audio callbacks.
 pub mod block_buffer;
 
 // Configurations for the audio stream
 mod default_config;
-pub use default_config::DefaultConfig;
+pub use default_config::{AudioConfig, DefaultConfig};
@@ -1,25 +1,42 @@
 /// PHY layer type traits: send+recv+probe
 mod traits;
-pub use traits::PhyLayer;
+pub use traits::{PhyLayer, PhyParams};
 
 /// the plain physics layer
 mod plain;
-pub use plain::PlainPHY;
+pub use plain::{ModemParams as PlainModemParams, PlainModem, PlainPHY};
 
-/// the plain physics layer
+/// the physics layer with CRC16 checksum: detect packet corrupt
 mod with_crc;
 pub use with_crc::{CrcPhy, CrcPhyRecvErr};
 
+/// the physics layer with forward error correction: correct packet corrupt
+mod with_fec;
+pub use with_fec::{CodeRate, Decision, FecParams, FecPhy};
+
 /// the atomic physics layer: detect packet lost/corrupt,
 /// no partial failure.
 mod atomic;
 pub use atomic::{AtomicPHY, AtomicPhyRecvErr};
 
+/// blocks of packets over the atomic physics layer with Reed-Solomon erasure coding:
+/// lost/corrupt packets are recovered, no acknowledgement.
+mod with_rs;
+pub use with_rs::{RsParams, RsPhy, RsPhyRecvErr};
+
 /// PHY layer with OFDM+PSK modulation for higher bit rate
 #[cfg(not(feature = "nofloat"))]
 mod ofdm;
 #[cfg(not(feature = "nofloat"))]
-pub use ofdm::HighBpsPHY;
+pub use ofdm::{CalibrationError, HighBpsPHY, LinkProfile};
+
+/// object-safe PHY layer interface, for PHY layers picked at runtime
+mod dyn_phy;
+pub use dyn_phy::{BoxedPhy, DynPhy, DynPhyErr};
+
+/// build a PHY layer picked at runtime, e.g. by a command line option
+mod builder;
+pub use builder::{BuildError, PhyBuilder, PhyKind};
 
 /// PHY layer implementation for mocking
 mod mocking;
@@ -27,3 +44,6 @@ pub use mocking::MockingPhy;
 
 /// the default PHY layer implementation is CRC PHY
 pub type DefaultPhy = CrcPhy;
+
+#[cfg(test)]
+mod tests;
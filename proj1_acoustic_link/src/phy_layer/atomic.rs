@@ -2,6 +2,7 @@ use super::{PhyLayer, PlainPHY};
 use crate::helper::{CrcSeq, SEQ_MOD};
 pub use crate::phy_packet::{Modem, PhyPacket, PreambleGen};
 pub use crate::traits::{PacketReceiver, PacketSender};
+use std::time::Duration;
 
 #[derive(Debug)]
 /// packet receive error
@@ -23,10 +24,12 @@ pub struct AtomicPHY {
   rx_seq: u8,
 }
 
-type CS = CrcSeq<{ PlainPHY::PACKET_BYTES }>;
+type CS = CrcSeq<{ <PlainPHY>::PACKET_BYTES }>;
 impl AtomicPHY {
-  /// number of data bytes in one packet
+  /// maximum number of data bytes in one packet
   pub const PACKET_BYTES: usize = CS::DATA_SIZE;
+  /// estimated RTT on the channel, the frames are those of the underlying layer
+  pub const ESTIMATED_RTT: Duration = <PlainPHY>::ESTIMATED_RTT;
 
   /// combine a sender and a receiver to get a physics layer object
   pub fn new(txrx: PlainPHY) -> Self {
@@ -36,11 +39,26 @@ impl AtomicPHY {
       rx_seq: 0,
     }
   }
+
+  /// maximum number of data bytes in one packet with the parameters of the underlying layer,
+  /// no more than [`Self::PACKET_BYTES`], see [`crate::phy_packet::params::MIN_PACKET_BYTES`]
+  pub fn packet_bytes(&self) -> usize {
+    self
+      .txrx
+      .packet_bytes()
+      .saturating_sub(CS::NONDATA_SIZE)
+      .min(Self::PACKET_BYTES)
+  }
+
+  /// Determine if the channel is free so that we can send a packet
+  pub fn channel_free(&self) -> bool {
+    self.txrx.channel_free()
+  }
 }
 
 impl PacketSender<PhyPacket, ()> for AtomicPHY {
   fn send(&mut self, packet: PhyPacket) -> Result<(), ()> {
-    assert_eq!(packet.len(), Self::PACKET_BYTES);
+    assert!(packet.len() <= self.packet_bytes());
     let packet = CS::pack(&packet, self.tx_seq);
     self.tx_seq = (self.tx_seq + 1) % SEQ_MOD;
     self.txrx.send(packet)
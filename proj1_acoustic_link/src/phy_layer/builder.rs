@@ -0,0 +1,232 @@
+use super::{AtomicPHY, BoxedPhy, CrcPhy, FecParams, FecPhy, PhyParams, PlainModemParams, PlainPHY};
+use crate::phy_packet::{
+  modem::{FskParams, FSK},
+  params::ParamError,
+};
+use crate::sample_stream::{
+  device::{DeviceError, DeviceSelector, Host},
+  CpalInStream, CpalOutStream,
+};
+use crate::traits::{InStream, OutStream, PowerProbe, FP};
+use crate::AudioConfig;
+use std::{fmt, str::FromStr};
+
+#[cfg(not(feature = "nofloat"))]
+use crate::phy_packet::modem::OfdmParams;
+
+/// The PHY layers [`PhyBuilder`] can build
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PhyKind {
+  /// [`PlainPHY`]: no integrity check
+  Plain,
+  /// [`CrcPhy`]: corrupted packets are dropped
+  #[default]
+  Crc,
+  /// [`AtomicPHY`]: corrupted packets are dropped, lost packets are detected
+  Atomic,
+  /// [`CrcPhy`] over [`FecPhy`]: corrupted packets are corrected, or dropped if too many bits are wrong
+  Fec,
+  /// [`CrcPhy`] over [`PlainPHY`] with [`FSK`]: slow but robust FSK for noisy rooms, corrupted packets are dropped
+  Fsk,
+  /// [`super::HighBpsPHY`]: OFDM for a higher bit rate, no integrity check
+  #[cfg(not(feature = "nofloat"))]
+  Ofdm,
+}
+
+impl PhyKind {
+  /// all the PHY layers available with the enabled features
+  pub const ALL: &'static [Self] = &[
+    Self::Plain,
+    Self::Crc,
+    Self::Atomic,
+    Self::Fec,
+    Self::Fsk,
+    #[cfg(not(feature = "nofloat"))]
+    Self::Ofdm,
+  ];
+
+  fn name(&self) -> &'static str {
+    match self {
+      Self::Plain => "plain",
+      Self::Crc => "crc",
+      Self::Atomic => "atomic",
+      Self::Fec => "fec",
+      Self::Fsk => "fsk",
+      #[cfg(not(feature = "nofloat"))]
+      Self::Ofdm => "ofdm",
+    }
+  }
+}
+
+impl FromStr for PhyKind {
+  type Err = String;
+
+  /// the lowercase name of the layer, e.g. `"crc"`
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Self::ALL.iter().copied().find(|kind| kind.name() == s).ok_or_else(|| {
+      let names: Vec<_> = Self::ALL.iter().map(Self::name).collect();
+      format!("unknown PHY layer \"{}\", expect one of {}", s, names.join(", "))
+    })
+  }
+}
+
+impl fmt::Display for PhyKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+/// Errors when building a PHY layer with [`PhyBuilder`]
+#[derive(Debug)]
+pub enum BuildError {
+  /// the audio config, or the parameters of the preamble or the modem for the audio config, are invalid
+  Param(ParamError),
+  /// the audio devices are not available
+  Device(DeviceError),
+}
+
+impl fmt::Display for BuildError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Param(e) => write!(f, "invalid PHY parameters: {}", e),
+      Self::Device(e) => write!(f, "{}", e),
+    }
+  }
+}
+
+impl std::error::Error for BuildError {}
+
+impl From<ParamError> for BuildError {
+  fn from(e: ParamError) -> Self {
+    Self::Param(e)
+  }
+}
+
+impl From<DeviceError> for BuildError {
+  fn from(e: DeviceError) -> Self {
+    Self::Device(e)
+  }
+}
+
+/// Assemble a PHY layer picked at runtime:
+/// the sample streams, the preamble, the frame detector, the modem and the integrity wrapper
+/// follow the [`PhyKind`], the audio config and the parameters of the builder.
+#[derive(Clone, Debug, Default)]
+pub struct PhyBuilder {
+  kind: PhyKind,
+  config: AudioConfig,
+  plain_params: PhyParams<PlainModemParams>,
+  fec_params: FecParams,
+  fsk_params: PhyParams<FskParams>,
+  #[cfg(not(feature = "nofloat"))]
+  ofdm_params: PhyParams<OfdmParams>,
+}
+
+impl PhyBuilder {
+  /// build the given PHY layer with the default audio config and parameters
+  pub fn new(kind: PhyKind) -> Self {
+    Self {
+      kind,
+      ..Default::default()
+    }
+  }
+
+  pub fn kind(&self) -> PhyKind {
+    self.kind
+  }
+
+  /// the audio config of the streams, the preamble and the modem
+  pub fn config(mut self, config: &AudioConfig) -> Self {
+    self.config = *config;
+    self
+  }
+
+  /// parameters of the layers built on [`PlainPHY`]
+  pub fn plain_params(mut self, params: PhyParams<PlainModemParams>) -> Self {
+    self.plain_params = params;
+    self
+  }
+
+  /// parameters of the forward error correction of [`FecPhy`]
+  pub fn fec_params(mut self, params: FecParams) -> Self {
+    self.fec_params = params;
+    self
+  }
+
+  /// parameters of [`PlainPHY`] with [`FSK`]
+  pub fn fsk_params(mut self, params: PhyParams<FskParams>) -> Self {
+    self.fsk_params = params;
+    self
+  }
+
+  /// parameters of [`super::HighBpsPHY`]
+  #[cfg(not(feature = "nofloat"))]
+  pub fn ofdm_params(mut self, params: PhyParams<OfdmParams>) -> Self {
+    self.ofdm_params = params;
+    self
+  }
+
+  /// build the PHY layer on the default audio devices
+  pub fn build(&self) -> Result<BoxedPhy, BuildError> {
+    self.config.validate()?;
+    let (stream_in, power_probe) = CpalInStream::with_config_probe(&self.config);
+    self.build_with_streams(stream_in, CpalOutStream::with_config(&self.config), power_probe)
+  }
+
+  /// Build the PHY layer on the selected input/output devices of a host.
+  /// The power probe shares the input stream.
+  pub fn build_on_devices(
+    &self,
+    host: &Host,
+    input: &DeviceSelector,
+    output: &DeviceSelector,
+  ) -> Result<BoxedPhy, BuildError> {
+    self.config.validate()?;
+    let (stream_in, power_probe) = CpalInStream::on_device_with_probe(host, input, &self.config)?;
+    self.build_with_streams(
+      stream_in,
+      CpalOutStream::on_device(host, output, &self.config)?,
+      power_probe,
+    )
+  }
+
+  /// Build the PHY layer on the given sample streams and power probe.
+  pub fn build_with_streams<I, O, P>(&self, stream_in: I, stream_out: O, power_probe: P) -> Result<BoxedPhy, BuildError>
+  where
+    I: InStream<FP, ()> + Send + 'static,
+    O: OutStream<FP, ()> + Send + 'static,
+    P: PowerProbe + Send + 'static,
+  {
+    self.config.validate()?;
+    let plain = |stream_in, stream_out, power_probe| {
+      PlainPHY::with_streams_params(stream_in, stream_out, power_probe, &self.config, &self.plain_params)
+    };
+    Ok(match self.kind {
+      PhyKind::Plain => Box::new(plain(stream_in, stream_out, power_probe)?),
+      PhyKind::Crc => Box::new(CrcPhy::new(plain(stream_in, stream_out, power_probe)?)),
+      PhyKind::Atomic => Box::new(AtomicPHY::new(plain(stream_in, stream_out, power_probe)?)),
+      PhyKind::Fec => Box::new(CrcPhy::new(FecPhy::with_params(
+        plain(stream_in, stream_out, power_probe)?,
+        self.fec_params,
+      ))),
+      PhyKind::Fsk => Box::new(CrcPhy::new(PlainPHY::<FSK>::with_streams_params(
+        stream_in,
+        stream_out,
+        power_probe,
+        &self.config,
+        &self.fsk_params,
+      )?)),
+      #[cfg(not(feature = "nofloat"))]
+      PhyKind::Ofdm => Box::new(super::HighBpsPHY::with_streams_params(
+        stream_in,
+        stream_out,
+        power_probe,
+        &self.config,
+        &self.ofdm_params,
+      )?),
+    })
+  }
+}
+
+#[cfg(all(test, feature = "wired"))]
+mod tests;
@@ -0,0 +1,72 @@
+use super::{BuildError, PhyBuilder, PhyKind};
+use crate::phy_layer::{BoxedPhy, CrcPhy, PhyLayer};
+use crate::phy_packet::params::ParamError;
+use crate::sample_stream::VirtualAir;
+use crate::traits::{PacketReceiver, PacketSender};
+use crate::AudioConfig;
+use rand::{distributions::Standard, Rng};
+use std::{thread, time::Duration};
+
+const RECV_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// a packet of a length every kind of PHY layer can carry
+fn random_packet(phy: &BoxedPhy) -> Vec<u8> {
+  let len = phy.packet_bytes().min(<CrcPhy>::PACKET_BYTES);
+  rand::thread_rng().sample_iter(Standard).take(len).collect()
+}
+
+fn builder_nodes(air: &VirtualAir, kind: PhyKind) -> Vec<BoxedPhy> {
+  (0..air.nodes())
+    .map(|id| {
+      let (stream_in, stream_out, power_probe) = air.node(id);
+      PhyBuilder::new(kind)
+        .build_with_streams(stream_in, stream_out, power_probe)
+        .unwrap()
+    })
+    .collect()
+}
+
+/// every kind of PHY layer built at runtime carries packets of its own capacity
+#[test]
+fn builder_kinds() {
+  for &kind in PhyKind::ALL {
+    let air = VirtualAir::new(2);
+    let mut phys = builder_nodes(&air, kind);
+    let packet = random_packet(&phys[0]);
+    phys[0].send(packet.clone()).unwrap();
+    assert_eq!(phys[1].recv_timeout(RECV_TIMEOUT).unwrap(), packet, "{} PHY", kind);
+  }
+
+  // the audio config is checked before anything is built on it
+  let air = VirtualAir::new(1);
+  let (stream_in, stream_out, power_probe) = air.node(0);
+  let config = AudioConfig {
+    sample_rate: 0,
+    ..Default::default()
+  };
+  let built = PhyBuilder::default()
+    .config(&config)
+    .build_with_streams(stream_in, stream_out, power_probe);
+  assert!(matches!(built, Err(BuildError::Param(ParamError::NotPositive(_)))));
+}
+
+/// every kind of PHY layer built at runtime senses the carrier of the others
+#[test]
+fn builder_carrier_sense() {
+  for &kind in PhyKind::ALL {
+    let air = VirtualAir::new(2);
+    let mut phys = builder_nodes(&air, kind);
+    assert!(phys[1].is_channel_free(), "{} PHY", kind);
+
+    let mut sender = phys.remove(0);
+    let packet = random_packet(&sender);
+    let handler = thread::spawn(move || sender.send(packet).unwrap());
+    let mut busy = false;
+    while !handler.is_finished() {
+      busy |= !phys[0].is_channel_free();
+      thread::sleep(Duration::from_millis(1));
+    }
+    handler.join().unwrap();
+    assert!(busy, "{} PHY", kind);
+  }
+}
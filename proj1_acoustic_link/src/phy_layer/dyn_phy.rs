@@ -0,0 +1,121 @@
+use super::{AtomicPHY, PhyLayer};
+pub use crate::phy_packet::PhyPacket;
+pub use crate::traits::{PacketReceiver, PacketSender};
+use std::time::Duration;
+
+/// Error of a PHY layer behind [`DynPhy`]:
+/// the error types of the layers differ, so they are kept as their debug output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DynPhyErr {
+  /// failed to send the packet
+  Send(String),
+  /// no packet received, or the packet received is dropped, e.g. corrupted
+  Recv(String),
+}
+
+/// The object-safe counterpart of [`PhyLayer`]:
+/// the packet capacity and the RTT are queried at runtime,
+/// so that upper layers can work on a PHY layer picked at runtime, see [`BoxedPhy`] and [`super::PhyBuilder`].
+///
+/// The methods are named apart from [`PacketSender`]/[`PacketReceiver`],
+/// a [`BoxedPhy`] sends/receives packets with those traits.
+pub trait DynPhy: Send {
+  /// send a packet of at most [`DynPhy::packet_bytes`] bytes, return until send finished or error
+  fn send_packet(&mut self, packet: PhyPacket) -> Result<(), DynPhyErr>;
+  /// receive a packet, return immediately
+  fn recv_packet(&mut self) -> Result<PhyPacket, DynPhyErr>;
+  /// receive a packet, retry until error or timeout
+  fn recv_packet_timeout(&mut self, timeout: Duration) -> Result<PhyPacket, DynPhyErr>;
+  /// check if some packets can be received without extracting the packet
+  fn packet_ready(&mut self) -> bool;
+
+  /// maximum number of bytes in one packet
+  fn packet_bytes(&self) -> usize;
+  /// estimated RTT on the channel
+  fn estimated_rtt(&self) -> Duration;
+  /// Determine if the channel is free so that we can send a packet
+  fn is_channel_free(&self) -> bool;
+}
+
+/// A PHY layer picked at runtime
+pub type BoxedPhy = Box<dyn DynPhy>;
+
+fn send_err<E: std::fmt::Debug>(e: E) -> DynPhyErr {
+  DynPhyErr::Send(format!("{:?}", e))
+}
+
+fn recv_err<E: std::fmt::Debug>(e: E) -> DynPhyErr {
+  DynPhyErr::Recv(format!("{:?}", e))
+}
+
+impl PacketSender<PhyPacket, DynPhyErr> for BoxedPhy {
+  fn send(&mut self, packet: PhyPacket) -> Result<(), DynPhyErr> {
+    self.send_packet(packet)
+  }
+}
+
+impl PacketReceiver<PhyPacket, DynPhyErr> for BoxedPhy {
+  fn recv(&mut self) -> Result<PhyPacket, DynPhyErr> {
+    self.recv_packet()
+  }
+
+  fn recv_timeout(&mut self, timeout: Duration) -> Result<PhyPacket, DynPhyErr> {
+    self.recv_packet_timeout(timeout)
+  }
+
+  fn recv_peek(&mut self) -> bool {
+    self.packet_ready()
+  }
+}
+
+impl<P: PhyLayer + Send> DynPhy for P {
+  fn send_packet(&mut self, packet: PhyPacket) -> Result<(), DynPhyErr> {
+    self.send(packet).map_err(send_err)
+  }
+  fn recv_packet(&mut self) -> Result<PhyPacket, DynPhyErr> {
+    self.recv().map_err(recv_err)
+  }
+  fn recv_packet_timeout(&mut self, timeout: Duration) -> Result<PhyPacket, DynPhyErr> {
+    self.recv_timeout(timeout).map_err(recv_err)
+  }
+  fn packet_ready(&mut self) -> bool {
+    self.recv_peek()
+  }
+
+  fn packet_bytes(&self) -> usize {
+    PhyLayer::packet_bytes(self)
+  }
+  fn estimated_rtt(&self) -> Duration {
+    P::ESTIMATED_RTT
+  }
+  fn is_channel_free(&self) -> bool {
+    self.channel_free()
+  }
+}
+
+/// The number of packets skipped between two packets received is dropped,
+/// upper layers over [`DynPhy`] keep their own sequence numbers.
+impl DynPhy for AtomicPHY {
+  fn send_packet(&mut self, packet: PhyPacket) -> Result<(), DynPhyErr> {
+    self.send(packet).map_err(send_err)
+  }
+  fn recv_packet(&mut self) -> Result<PhyPacket, DynPhyErr> {
+    self.recv().map(|(packet, _)| packet).map_err(recv_err)
+  }
+  fn recv_packet_timeout(&mut self, timeout: Duration) -> Result<PhyPacket, DynPhyErr> {
+    self.recv_timeout(timeout).map(|(packet, _)| packet).map_err(recv_err)
+  }
+  fn packet_ready(&mut self) -> bool {
+    self.recv_peek()
+  }
+
+  fn packet_bytes(&self) -> usize {
+    AtomicPHY::packet_bytes(self)
+  }
+  fn estimated_rtt(&self) -> Duration {
+    AtomicPHY::ESTIMATED_RTT
+  }
+  fn is_channel_free(&self) -> bool {
+    self.channel_free()
+  }
+}
@@ -1,32 +1,191 @@
+use super::{PhyLayer, PhyParams};
+use crate::phy_packet::{modem::BitLoading, params::ParamError, preambles::PreambleParams, SoftBits};
 pub use crate::phy_packet::{Modem, PhyPacket, PreambleGen};
+use crate::sample_stream::{CaptureDir, CaptureLog, CpalInStream, CpalOutStream};
+use crate::traits::FP;
 pub use crate::traits::{PacketReceiver, PacketSender};
+use crate::AudioConfig;
+use std::{io, time::Duration};
 
 use config::*;
 
 /// a physics layer peer object.
-/// use OFDM+BPSK for modulation.
+/// use OFDM for modulation: by default QPSK on every data subcarrier with pilot subcarriers tracking the drifts,
+/// or the constellation of each subcarrier picked by a bit-loading table, see [`HighBpsPHY::with_profile`].
 /// similar to [`super::PlainPHY`], no correctness guarantee for transmission.
+/// With a [`PreambleParams::RepeatedHalves`] preamble the frames are detected by [`SchmidlCox`],
+/// whose coarse frequency offset estimate is corrected by the modem, otherwise by [`CorrelationFraming`].
 pub struct HighBpsPHY {
   tx: Tx,
   rx: Rx,
+  power_probe: PowerProbe,
 }
 
 impl HighBpsPHY {
-  /// number of bytes in one packet
-  pub const PACKET_BYTES: usize = ModemMethod::BYTES_PER_PACKET;
-  /// number of samples in one packet
-  pub const PACKET_SAMPLES: usize = Tx::SAMPLES_PER_PACKET;
+  /// maximum number of bytes in one packet with the default parameters
+  pub const PACKET_BYTES: usize = ModemParams::DEFAULT.bytes_per_packet();
+
+  /// maximum number of bytes in one packet with the parameters of the layer
+  pub fn packet_bytes(&self) -> usize {
+    self.tx.packet_bytes()
+  }
+
+  /// number of samples of the frame sending a packet of `bytes` bytes
+  pub fn frame_samples(&self, bytes: usize) -> usize {
+    self.tx.frame_samples(bytes)
+  }
 
   /// combine a sender and a receiver to get a physics layer object
-  pub fn new(tx: Tx, rx: Rx) -> Self {
-    Self { tx, rx }
+  pub fn new(tx: Tx, rx: Rx, power_probe: PowerProbe) -> Self {
+    Self { tx, rx, power_probe }
+  }
+
+  /// build a physics layer object on the default audio devices with the given audio config
+  pub fn with_config(config: &AudioConfig) -> Self {
+    let (stream_in, power_probe) = CpalInStream::with_config_probe(config);
+    Self::with_streams_config(stream_in, CpalOutStream::with_config(config), power_probe, config)
+  }
+
+  /// build a physics layer object on the given sample streams and power probe with the given audio config
+  pub fn with_streams_config<I, O, P>(stream_in: I, stream_out: O, power_probe: P, config: &AudioConfig) -> Self
+  where
+    I: crate::traits::InStream<FP, ()> + Send + 'static,
+    O: crate::traits::OutStream<FP, ()> + Send + 'static,
+    P: crate::traits::PowerProbe + Send + 'static,
+  {
+    Self::with_streams_params(stream_in, stream_out, power_probe, config, &Default::default())
+      .expect("invalid default PHY parameters for the audio config")
+  }
+
+  /// build a physics layer object on the given sample streams and power probe,
+  /// the preamble and the modem are built with the given parameters at the sampling rate of the audio config.
+  pub fn with_streams_params<I, O, P>(
+    stream_in: I,
+    stream_out: O,
+    power_probe: P,
+    config: &AudioConfig,
+    params: &PhyParams<ModemParams>,
+  ) -> Result<Self, ParamError>
+  where
+    I: crate::traits::InStream<FP, ()> + Send + 'static,
+    O: crate::traits::OutStream<FP, ()> + Send + 'static,
+    P: crate::traits::PowerProbe + Send + 'static,
+  {
+    let loading = BitLoading::uniform(params.modem.subcarriers, params.modem.constellation);
+    Self::with_boxed_streams(
+      Box::new(stream_in),
+      Box::new(stream_out),
+      Box::new(power_probe),
+      config,
+      params,
+      &loading,
+      None,
+    )
+  }
+
+  /// build a physics layer object on the default audio devices with the given parameters
+  pub fn with_params(config: &AudioConfig, params: &PhyParams<ModemParams>) -> Result<Self, ParamError> {
+    let (stream_in, power_probe) = CpalInStream::with_config_probe(config);
+    Self::with_streams_params(
+      stream_in,
+      CpalOutStream::with_config(config),
+      power_probe,
+      config,
+      params,
+    )
+  }
+
+  /// Build a physics layer object on the given sample streams and power probe,
+  /// and capture the samples sent/received with the packet boundaries into a directory, see [`CaptureDir`].
+  pub fn with_streams_capture<I, O, P>(
+    stream_in: I,
+    stream_out: O,
+    power_probe: P,
+    config: &AudioConfig,
+    capture: &CaptureDir,
+  ) -> io::Result<Self>
+  where
+    I: crate::traits::InStream<FP, ()> + Send + 'static,
+    O: crate::traits::OutStream<FP, ()> + Send + 'static,
+    P: crate::traits::PowerProbe + Send + 'static,
+  {
+    let params = PhyParams::<ModemParams>::default();
+    Self::with_boxed_streams(
+      Box::new(capture.tee_in(stream_in, config)?),
+      Box::new(capture.tee_out(stream_out, config)?),
+      Box::new(power_probe),
+      config,
+      &params,
+      &BitLoading::uniform(params.modem.subcarriers, params.modem.constellation),
+      Some((capture.rx_log()?, capture.tx_log()?)),
+    )
+    .map_err(io::Error::other)
+  }
+
+  /// build a physics layer object on the default audio devices, capturing into a directory
+  pub fn with_capture(config: &AudioConfig, capture: &CaptureDir) -> io::Result<Self> {
+    let (stream_in, power_probe) = CpalInStream::with_config_probe(config);
+    Self::with_streams_capture(
+      stream_in,
+      CpalOutStream::with_config(config),
+      power_probe,
+      config,
+      capture,
+    )
+  }
+
+  fn with_boxed_streams(
+    stream_in: InStream,
+    stream_out: OutStream,
+    power_probe: PowerProbe,
+    config: &AudioConfig,
+    params: &PhyParams<ModemParams>,
+    loading: &BitLoading,
+    logs: Option<(CaptureLog, CaptureLog)>,
+  ) -> Result<Self, ParamError> {
+    config.validate()?;
+    let preamble = || Preamble::with_params(&params.preamble, config);
+    let modem = || ModemMethod::with_loading(&params.modem, loading.clone(), config);
+    let (rx_log, tx_log) = logs.unzip();
+    let tx = Tx::with_capture(stream_out, preamble()?, modem()?, tx_log);
+    // a training symbol of two identical halves gives a coarse frequency offset to the modem
+    let detector: FrameDetector = match params.preamble {
+      PreambleParams::RepeatedHalves(_) => Box::new(SchmidlCox::with_modem(preamble()?, modem()?)),
+      _ => Box::new(CorrelationFraming::with_modem(preamble()?, modem()?)),
+    };
+    let rx = Rx::with_capture(stream_in, modem()?, detector, config, rx_log);
+    Ok(Self::new(tx, rx, power_probe))
+  }
+}
+
+impl PhyLayer for HighBpsPHY {
+  type SendErr = ();
+  type RecvErr = ();
+  const PACKET_BYTES: usize = HighBpsPHY::PACKET_BYTES;
+  const ESTIMATED_RTT: Duration = ESTIMATED_RTT;
+
+  fn channel_free(&self) -> bool {
+    self.power_probe.power() < REST_POWER
+  }
+
+  fn packet_bytes(&self) -> usize {
+    HighBpsPHY::packet_bytes(self)
+  }
+
+  /// the soft bits of the modem, see [`Modem::demodulate_soft`]
+  fn recv_soft(&mut self) -> Result<SoftBits, ()> {
+    self.rx.recv_soft().ok_or(())
+  }
+
+  fn recv_soft_timeout(&mut self, timeout: Duration) -> Result<SoftBits, ()> {
+    self.rx.recv_soft_timeout(timeout).ok_or(())
   }
 }
 
 impl PacketSender<PhyPacket, ()> for HighBpsPHY {
-  /// send a packet, return until send finished or error
+  /// send a packet of at most [`HighBpsPHY::packet_bytes`] bytes, return until send finished or error
   fn send(&mut self, packet: PhyPacket) -> Result<(), ()> {
-    assert_eq!(packet.len(), Self::PACKET_BYTES);
+    assert!(packet.len() <= self.packet_bytes());
     self.tx.send(packet)
   }
 }
@@ -47,14 +206,15 @@ impl PacketReceiver<PhyPacket, ()> for HighBpsPHY {
 
 impl Default for HighBpsPHY {
   fn default() -> Self {
-    let tx = Tx::new(OutStream::default(), ModemMethod::default());
-    let rx = Rx::new(
-      InStream::default(),
-      ModemMethod::default(),
-      FrameDetector::new::<{ ModemMethod::SAMPLES_PER_PACKET }>(Preamble::new()),
-    );
-    Self::new(tx, rx)
+    Self::with_config(&AudioConfig::default())
   }
 }
 
 mod config;
+
+/// measure a link and save the bit-loading table agreed on
+mod calibration;
+pub use calibration::{CalibrationError, LinkProfile};
+
+#[cfg(all(test, feature = "wired"))]
+mod tests;
@@ -0,0 +1,376 @@
+use super::{config::*, HighBpsPHY};
+use crate::phy_layer::{CrcPhy, PhyParams};
+use crate::phy_packet::{
+  header::LengthHeader,
+  modem::{BitLoading, Subcarriers},
+  params::ParamError,
+  preambles::{CarrierParams, ChirpParams, MSequenceParams, PreambleParams, RepeatedHalvesParams, ZadoffChuParams},
+};
+use crate::sample_stream::{CpalInStream, CpalOutStream};
+use crate::traits::{PacketReceiver, PacketSender, FP};
+use crate::AudioConfig;
+use std::{
+  fmt, fs, io,
+  path::Path,
+  str::FromStr,
+  time::{Duration, Instant},
+};
+
+/// The bit-loading table of a calibrated link with the parameters of the PHY layers on it,
+/// saved to disk so that the link is set up again without probing.
+///
+/// The text format has one setting per line, `#` starts a comment:
+/// ```text
+/// preamble <lowest frequency> <highest frequency> <samples>
+/// preamble msequence <degree> <taps> <carrier frequency> <samples per chip>
+/// preamble barker13 <carrier frequency> <samples per chip>
+/// preamble zadoff-chu <length> <root> <carrier frequency> <samples per chip>
+/// preamble repeated <half length> <first bin> <bins>
+/// ofdm <FFT size> <cyclic prefix> <symbols per packet>
+/// pilots <index>...
+/// subcarrier <index> <bits per point>
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkProfile {
+  /// parameters of the preamble and the modem,
+  /// the subcarriers and the constellation of the modem are superseded by the table
+  pub params: PhyParams<ModemParams>,
+  /// the constellation of each subcarrier
+  pub loading: BitLoading,
+}
+
+impl LinkProfile {
+  /// Combine the parameters of the layers with a table,
+  /// the number of symbols of a packet is reduced so that a packet fits in the length header.
+  pub fn new(mut params: PhyParams<ModemParams>, loading: BitLoading) -> Self {
+    let max_symbols = LengthHeader::MAX_LEN * 8 / loading.bits_per_symbol().max(1);
+    params.modem.encode_symbols = params.modem.encode_symbols.min(max_symbols);
+    params.modem.subcarriers = loading.subcarriers();
+    Self { params, loading }
+  }
+
+  /// maximum number of bytes in one packet
+  pub fn packet_bytes(&self) -> usize {
+    self.loading.bits_per_symbol() * self.params.modem.encode_symbols / 8
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+    fs::write(path, self.to_string())
+  }
+
+  pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+    fs::read_to_string(path)?
+      .parse()
+      .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+  }
+}
+
+impl fmt::Display for LinkProfile {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.params.preamble {
+      PreambleParams::Chirp(ChirpParams { fa, fb, n }) => writeln!(f, "preamble {} {} {}", fa, fb, n)?,
+      PreambleParams::MSequence(MSequenceParams { degree, taps, carrier }) => writeln!(
+        f,
+        "preamble msequence {} {} {} {}",
+        degree, taps, carrier.freq, carrier.samples_per_chip
+      )?,
+      PreambleParams::Barker13(carrier) => {
+        writeln!(f, "preamble barker13 {} {}", carrier.freq, carrier.samples_per_chip)?
+      }
+      PreambleParams::ZadoffChu(ZadoffChuParams { len, root, carrier }) => writeln!(
+        f,
+        "preamble zadoff-chu {} {} {} {}",
+        len, root, carrier.freq, carrier.samples_per_chip
+      )?,
+      PreambleParams::RepeatedHalves(RepeatedHalvesParams {
+        half_len,
+        first_bin,
+        bins,
+      }) => writeln!(f, "preamble repeated {} {} {}", half_len, first_bin, bins)?,
+    }
+    let ModemParams {
+      n, m, encode_symbols, ..
+    } = self.params.modem;
+    writeln!(f, "ofdm {} {} {}", n, m, encode_symbols)?;
+    write!(f, "pilots")?;
+    for k in self.params.modem.pilots.iter() {
+      write!(f, " {}", k)?;
+    }
+    writeln!(f)?;
+    for (k, constellation) in self.loading.carriers() {
+      writeln!(f, "subcarrier {} {}", k, constellation.bits())?;
+    }
+    Ok(())
+  }
+}
+
+impl FromStr for LinkProfile {
+  type Err = String;
+
+  /// parse the text format of [`LinkProfile`], the error tells the line at fault
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut preamble = None;
+    let mut modem = None;
+    let mut pilots = Subcarriers::EMPTY;
+    let mut table = Vec::new();
+    for (number, line) in s.lines().enumerate() {
+      let line = line.split('#').next().unwrap_or_default();
+      let fields: Vec<_> = line.split_whitespace().collect();
+      let invalid = || format!("line {}: invalid setting \"{}\"", number + 1, line.trim());
+      let parse = |field: &str| field.parse().map_err(|_| invalid());
+      let carrier = |freq: &str, samples_per_chip: &str| {
+        Ok::<_, String>(CarrierParams {
+          freq: freq.parse().map_err(|_| invalid())?,
+          samples_per_chip: parse(samples_per_chip)?,
+        })
+      };
+      match fields.as_slice() {
+        [] => {}
+        ["preamble", "msequence", degree, taps, freq, samples_per_chip] => {
+          preamble = Some(PreambleParams::MSequence(MSequenceParams {
+            degree: degree.parse().map_err(|_| invalid())?,
+            taps: taps.parse().map_err(|_| invalid())?,
+            carrier: carrier(freq, samples_per_chip)?,
+          }))
+        }
+        ["preamble", "barker13", freq, samples_per_chip] => {
+          preamble = Some(PreambleParams::Barker13(carrier(freq, samples_per_chip)?))
+        }
+        ["preamble", "zadoff-chu", len, root, freq, samples_per_chip] => {
+          preamble = Some(PreambleParams::ZadoffChu(ZadoffChuParams {
+            len: parse(len)?,
+            root: parse(root)?,
+            carrier: carrier(freq, samples_per_chip)?,
+          }))
+        }
+        ["preamble", "repeated", half_len, first_bin, bins] => {
+          preamble = Some(PreambleParams::RepeatedHalves(RepeatedHalvesParams {
+            half_len: parse(half_len)?,
+            first_bin: parse(first_bin)?,
+            bins: parse(bins)?,
+          }))
+        }
+        ["preamble", fa, fb, n] => {
+          preamble = Some(PreambleParams::Chirp(ChirpParams {
+            fa: fa.parse().map_err(|_| invalid())?,
+            fb: fb.parse().map_err(|_| invalid())?,
+            n: parse(n)?,
+          }))
+        }
+        ["ofdm", n, m, encode_symbols] => {
+          modem = Some(ModemParams {
+            n: parse(n)?,
+            m: parse(m)?,
+            encode_symbols: parse(encode_symbols)?,
+            ..ModemParams::DEFAULT
+          })
+        }
+        ["pilots", indices @ ..] => {
+          for k in indices {
+            let k: usize = parse(k)?;
+            if k >= Subcarriers::MAX {
+              return Err(invalid());
+            }
+            pilots = pilots.with(k);
+          }
+        }
+        ["subcarrier", k, bits] => table.extend([
+          k.parse::<u8>().map_err(|_| invalid())?,
+          bits.parse::<u8>().map_err(|_| invalid())?,
+        ]),
+        _ => return Err(invalid()),
+      }
+    }
+    let preamble = preamble.ok_or("missing preamble setting")?;
+    let modem = modem.ok_or("missing ofdm setting")?;
+    let loading = BitLoading::from_bytes(&table).ok_or("invalid subcarrier table")?;
+    let modem = ModemParams { pilots, ..modem };
+    // clamp the number of symbols like a profile made by a calibration
+    Ok(Self::new(PhyParams { preamble, modem }, loading))
+  }
+}
+
+/// Errors of a link calibration with [`HighBpsPHY::calibrate_send`]/[`HighBpsPHY::calibrate_recv`]
+#[derive(Debug)]
+pub enum CalibrationError {
+  /// no probe or no table received before the timeout
+  Timeout,
+  /// no subcarrier is clean enough to carry data
+  NoSubcarrier,
+  /// the table does not fit in one packet of the layer
+  TableTooLong,
+  /// failed to send a probe or the table
+  Send,
+}
+
+impl fmt::Display for CalibrationError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Timeout => write!(f, "link calibration timed out"),
+      Self::NoSubcarrier => write!(f, "no subcarrier is clean enough to carry data"),
+      Self::TableTooLong => write!(f, "the bit-loading table does not fit in one packet"),
+      Self::Send => write!(f, "failed to send a calibration packet"),
+    }
+  }
+}
+
+impl std::error::Error for CalibrationError {}
+
+/// Link calibration: the initiator sends probe packets of known content,
+/// the responder measures the SNR of each subcarrier against the points sent in them,
+/// then replies with the bit-loading table, protected by a CRC16 checksum.
+/// The same table is used both ways, the channel being about the same in the two directions.
+///
+/// Probe with many subcarriers of a robust constellation, e.g. [`ModemParams::SOUNDING`],
+/// then build the layers from the [`LinkProfile`] of the table.
+impl HighBpsPHY {
+  /// the first byte of a table reply
+  const TABLE_TAG: u8 = b'L';
+  /// the first byte of a probe, followed by its index and the bytes generated from the index
+  const PROBE_TAG: u8 = b'P';
+  /// number of times the table is sent, in case a reply is lost
+  const TABLE_REPLIES: usize = 3;
+
+  /// Initiate a calibration: send `probes` probe packets, then wait for the table until `timeout`.
+  /// The replies are not all consumed, the layer is meant to be dropped for one built from the table.
+  pub fn calibrate_send(&mut self, probes: usize, timeout: Duration) -> Result<BitLoading, CalibrationError> {
+    for index in 0..probes {
+      let probe = Self::probe(index as u8, self.packet_bytes());
+      self.send(probe).map_err(|_| CalibrationError::Send)?;
+    }
+    let deadline = Instant::now() + timeout;
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+      let Ok(packet) = self.recv_timeout(left) else {
+        continue;
+      };
+      if let Some(loading) = Self::parse_table(&packet) {
+        return Ok(loading);
+      }
+    }
+    Err(CalibrationError::Timeout)
+  }
+
+  /// Respond to a calibration: receive up to `probes` probe packets until `timeout`, the other packets are skipped,
+  /// pick the constellation of each subcarrier with a `margin_db` SNR margin, see [`BitLoading::from_snr`],
+  /// and reply with the table.
+  /// The SNR of a subcarrier is the power of the points sent over the power of their errors, summed over the probes.
+  pub fn calibrate_recv(
+    &mut self,
+    probes: usize,
+    margin_db: f32,
+    timeout: Duration,
+  ) -> Result<BitLoading, CalibrationError> {
+    let deadline = Instant::now() + timeout;
+    let mut power: Vec<(usize, f32, f32)> = Vec::new();
+    let mut received = 0;
+    while received < probes {
+      let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+        break;
+      };
+      let Ok(packet) = self.recv_timeout(left) else {
+        continue;
+      };
+      let Some(sent) = Self::probe_sent(&packet) else {
+        continue;
+      };
+      let error = self.rx.modem().last_error(&sent);
+      power.resize(error.len(), (0, 0.0, 0.0));
+      for ((k, signal_sum, error_sum), (carrier, signal, error)) in power.iter_mut().zip(error) {
+        *k = carrier;
+        *signal_sum += signal;
+        *error_sum += error;
+      }
+      received += 1;
+    }
+    if received == 0 {
+      return Err(CalibrationError::Timeout);
+    }
+
+    let snr: Vec<_> = power
+      .iter()
+      .map(|(k, signal, error)| (*k, 10.0 * (signal / error.max(f32::MIN_POSITIVE)).log10()))
+      .collect();
+    let loading = BitLoading::from_snr(&snr, margin_db);
+    if loading.carriers().is_empty() {
+      return Err(CalibrationError::NoSubcarrier);
+    }
+    let mut reply = vec![Self::TABLE_TAG];
+    reply.extend(loading.to_bytes());
+    reply.extend(CrcPhy::checksum(&reply));
+    if reply.len() > self.packet_bytes() {
+      return Err(CalibrationError::TableTooLong);
+    }
+    for _ in 0..Self::TABLE_REPLIES {
+      self.send(reply.clone()).map_err(|_| CalibrationError::Send)?;
+    }
+    Ok(loading)
+  }
+
+  /// a probe of `len` bytes: the tag, the index, then pseudo-random bytes generated from the index
+  fn probe(index: u8, len: usize) -> Vec<u8> {
+    let mut state = 0x9e37_79b9_u32 ^ index as u32;
+    let bytes = std::iter::repeat_with(move || {
+      // xorshift32
+      state ^= state << 13;
+      state ^= state >> 17;
+      state ^= state << 5;
+      state as u8
+    });
+    [Self::PROBE_TAG, index].into_iter().chain(bytes).take(len).collect()
+  }
+
+  /// The probe sent as the packet received, if the packet is a probe:
+  /// the packet has the tag of a probe, and at most a quarter of its bits differ from the probe of its index.
+  fn probe_sent(packet: &[u8]) -> Option<Vec<u8>> {
+    let (&tag, rest) = packet.split_first()?;
+    (tag == Self::PROBE_TAG).then_some(())?;
+    let probe = Self::probe(*rest.first()?, packet.len());
+    let errors: u32 = probe.iter().zip(packet).map(|(x, y)| (x ^ y).count_ones()).sum();
+    (errors as usize <= packet.len() * 8 / 4).then_some(probe)
+  }
+
+  fn parse_table(packet: &[u8]) -> Option<BitLoading> {
+    if !CrcPhy::verify(packet) {
+      return None;
+    }
+    let (tag, table) = packet[..packet.len() - CrcPhy::CRC_BYTES].split_first()?;
+    (*tag == Self::TABLE_TAG).then_some(())?;
+    BitLoading::from_bytes(table)
+  }
+
+  /// build a physics layer object on the given sample streams and power probe with a calibrated profile
+  pub fn with_streams_profile<I, O, P>(
+    stream_in: I,
+    stream_out: O,
+    power_probe: P,
+    config: &AudioConfig,
+    profile: &LinkProfile,
+  ) -> Result<Self, ParamError>
+  where
+    I: crate::traits::InStream<FP, ()> + Send + 'static,
+    O: crate::traits::OutStream<FP, ()> + Send + 'static,
+    P: crate::traits::PowerProbe + Send + 'static,
+  {
+    Self::with_boxed_streams(
+      Box::new(stream_in),
+      Box::new(stream_out),
+      Box::new(power_probe),
+      config,
+      &profile.params,
+      &profile.loading,
+      None,
+    )
+  }
+
+  /// build a physics layer object on the default audio devices with a calibrated profile
+  pub fn with_profile(config: &AudioConfig, profile: &LinkProfile) -> Result<Self, ParamError> {
+    let (stream_in, power_probe) = CpalInStream::with_config_probe(config);
+    Self::with_streams_profile(
+      stream_in,
+      CpalOutStream::with_config(config),
+      power_probe,
+      config,
+      profile,
+    )
+  }
+}
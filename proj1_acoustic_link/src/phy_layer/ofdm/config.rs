@@ -1,11 +1,29 @@
 pub use crate::phy_packet::{
-  frame_detect::CorrelationFraming as FrameDetector, modem::OFDM as ModemMethod, preambles::ChirpUpDown as Preamble,
-  txrx::PhyReceiver, txrx::PhySender,
+  frame_detect::{CorrelationFraming, SchmidlCox},
+  modem::OfdmParams as ModemParams,
+  modem::OFDM as ModemMethod,
+  preambles::AnyPreamble as Preamble,
+  txrx::PhyReceiver,
+  txrx::PhySender,
 };
 
-pub use crate::sample_stream::{CpalInStream as InStream, CpalOutStream as OutStream};
+pub(super) use crate::phy_layer::plain::REST_POWER;
+use crate::traits::FP;
+use std::time::Duration;
+
+/// sample input stream type, the receiver can work on any sample stream
+pub type InStream = Box<dyn crate::traits::InStream<FP, ()> + Send>;
+/// sample output stream type, the sender can work on any sample stream
+pub type OutStream = Box<dyn crate::traits::OutStream<FP, ()> + Send>;
+/// channel power probe type
+pub type PowerProbe = Box<dyn crate::traits::PowerProbe + Send>;
+/// frame detector type, picked by the preamble of the parameters
+pub type FrameDetector = Box<dyn crate::phy_packet::FrameDetector + Send>;
 
 // physice packet sender type
 pub type Tx = PhySender<Preamble, ModemMethod, OutStream, ()>;
 // physice packet receiver type
-pub type Rx = PhyReceiver<Preamble, ModemMethod, FrameDetector<Preamble>, InStream, ()>;
+pub type Rx = PhyReceiver<Preamble, ModemMethod, FrameDetector, InStream, ()>;
+
+/// a frame of the default parameters lasts about 60ms
+pub const ESTIMATED_RTT: Duration = Duration::from_millis(150);
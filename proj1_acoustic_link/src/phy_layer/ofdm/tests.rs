@@ -0,0 +1,82 @@
+use super::{HighBpsPHY, LinkProfile};
+use crate::phy_layer::PhyParams;
+use crate::phy_packet::{header::LengthHeader, modem::OfdmParams, preambles::PreambleParams};
+use crate::sample_stream::VirtualAir;
+use crate::traits::{PacketReceiver, PacketSender};
+use rand::{distributions::Standard, Rng};
+use std::{thread, time::Duration};
+
+/// two nodes measure their link, agree on a bit-loading table and talk with the saved profile
+#[test]
+fn calibration() {
+  const PROBES: usize = 4;
+  let air = VirtualAir::new(2);
+  let config = Default::default();
+  let params = PhyParams {
+    modem: OfdmParams::SOUNDING,
+    ..Default::default()
+  };
+  let mut sounding: Vec<_> = (0..2)
+    .map(|id| {
+      let (stream_in, stream_out, power_probe) = air.node(id);
+      HighBpsPHY::with_streams_params(stream_in, stream_out, power_probe, &config, &params).unwrap()
+    })
+    .collect();
+  let mut responder = sounding.pop().unwrap();
+  let responder = thread::spawn(move || responder.calibrate_recv(PROBES, 3.0, Duration::from_secs(2)));
+  // other traffic on the channel is not taken as a probe
+  let packet = vec![0; sounding[0].packet_bytes()];
+  sounding[0].send(packet).unwrap();
+  let sent = sounding[0].calibrate_send(PROBES, Duration::from_secs(3)).unwrap();
+  let received = responder.join().unwrap().unwrap();
+  assert_eq!(sent, received);
+  // the virtual air is noiseless
+  assert_eq!(sent.subcarriers(), OfdmParams::SOUNDING.subcarriers);
+  drop(sounding);
+
+  let profile = LinkProfile::new(params, sent);
+  let path = std::env::temp_dir().join(format!("{}_virtual_air_profile.txt", std::process::id()));
+  profile.save(&path).unwrap();
+  let profile = LinkProfile::load(&path).unwrap();
+  assert_eq!(profile.loading, received);
+  assert!(profile.packet_bytes() > HighBpsPHY::PACKET_BYTES);
+  // the preamble of every family is kept in the profile
+  for preamble in [
+    PreambleParams::MSequence(Default::default()),
+    PreambleParams::Barker13(Default::default()),
+    PreambleParams::for_node(2),
+    PreambleParams::RepeatedHalves(Default::default()),
+  ] {
+    let other = LinkProfile::new(
+      PhyParams {
+        preamble,
+        ..profile.params
+      },
+      received.clone(),
+    );
+    assert_eq!(other.to_string().parse::<LinkProfile>().unwrap(), other);
+  }
+  // a profile edited by hand gets the packets clamped into the length header too
+  let edited = profile.to_string().replace(
+    &format!(
+      "ofdm {} {} {}",
+      profile.params.modem.n, profile.params.modem.m, profile.params.modem.encode_symbols
+    ),
+    &format!("ofdm {} {} 100000", profile.params.modem.n, profile.params.modem.m),
+  );
+  let edited: LinkProfile = edited.parse().unwrap();
+  assert!(edited.packet_bytes() <= LengthHeader::MAX_LEN);
+
+  let mut phys: Vec<_> = (0..2)
+    .map(|id| {
+      let (stream_in, stream_out, power_probe) = air.node(id);
+      HighBpsPHY::with_streams_profile(stream_in, stream_out, power_probe, &config, &profile).unwrap()
+    })
+    .collect();
+  let packet: Vec<u8> = rand::thread_rng()
+    .sample_iter(Standard)
+    .take(profile.packet_bytes())
+    .collect();
+  phys[0].send(packet.clone()).unwrap();
+  assert_eq!(phys[1].recv_timeout(Duration::from_millis(500)).unwrap(), packet);
+}
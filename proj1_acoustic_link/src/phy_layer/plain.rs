@@ -1,47 +1,272 @@
-use std::time::Duration;
+use std::{io, time::Duration};
 
-use super::PhyLayer;
+use super::{PhyLayer, PhyParams};
+use crate::phy_packet::params::ParamError;
+use crate::phy_packet::SoftBits;
 pub use crate::phy_packet::{Modem, PhyPacket, PreambleGen};
+use crate::sample_stream::{
+  device::{input_device, output_device, DeviceError, DeviceSelector, Host},
+  CaptureDir, CaptureLog, CpalInStream, CpalOutStream,
+};
+use crate::traits::FP;
 pub use crate::traits::{PacketReceiver, PacketSender};
+use crate::AudioConfig;
+pub(super) use config::REST_POWER;
 use config::*;
+pub use config::{ModemParams, PlainModem};
 
 /// a physics layer peer object.
 /// send/recv packets with no latency/correctness guarantee.
-pub struct PlainPHY {
-  tx: Tx,
-  rx: Rx,
+/// The modem is picked by `M`, e.g. `PlainPHY<FSK>` is slow, but robust to echoes, noise and phase drift
+/// in a noisy room.
+pub struct PlainPHY<M: PlainModem = ModemMethod> {
+  tx: Tx<M>,
+  rx: Rx<M>,
   power_probe: PowerProbe,
 }
 
-impl PlainPHY {
-  /// number of samples in one packet
-  pub const PACKET_SAMPLES: usize = Tx::SAMPLES_PER_PACKET;
+impl<M: PlainModem> PlainPHY<M> {
+  /// maximum number of bytes in one packet with the parameters of the layer,
+  /// [`PhyLayer::PACKET_BYTES`] with the default parameters
+  pub fn packet_bytes(&self) -> usize {
+    self.tx.packet_bytes()
+  }
+
+  /// number of samples of the frame sending a packet of `bytes` bytes
+  pub fn frame_samples(&self, bytes: usize) -> usize {
+    self.tx.frame_samples(bytes)
+  }
 
   /// combine a sender and a receiver to get a physics layer object
-  pub fn new(tx: Tx, rx: Rx, power_probe: PowerProbe) -> Self {
+  pub fn new(tx: Tx<M>, rx: Rx<M>, power_probe: PowerProbe) -> Self {
     Self { tx, rx, power_probe }
   }
+
+  /// build a physics layer object on the given sample streams and power probe
+  pub fn with_streams<I, O, P>(stream_in: I, stream_out: O, power_probe: P) -> Self
+  where
+    I: crate::traits::InStream<FP, ()> + Send + 'static,
+    O: crate::traits::OutStream<FP, ()> + Send + 'static,
+    P: crate::traits::PowerProbe + Send + 'static,
+  {
+    Self::with_streams_config(stream_in, stream_out, power_probe, &AudioConfig::default())
+  }
+
+  /// build a physics layer object on the given sample streams and power probe,
+  /// the preamble, the modem and the receiver worker follow the audio config.
+  pub fn with_streams_config<I, O, P>(stream_in: I, stream_out: O, power_probe: P, config: &AudioConfig) -> Self
+  where
+    I: crate::traits::InStream<FP, ()> + Send + 'static,
+    O: crate::traits::OutStream<FP, ()> + Send + 'static,
+    P: crate::traits::PowerProbe + Send + 'static,
+  {
+    Self::with_streams_params(stream_in, stream_out, power_probe, config, &Default::default())
+      .expect("invalid default PHY parameters for the audio config")
+  }
+
+  /// Build a physics layer object on the given sample streams and power probe,
+  /// the preamble and the modem are built with the given parameters at the sampling rate of the audio config.
+  /// Upper layers relying on [`PhyLayer::PACKET_BYTES`] need parameters carrying at least as many bytes.
+  pub fn with_streams_params<I, O, P>(
+    stream_in: I,
+    stream_out: O,
+    power_probe: P,
+    config: &AudioConfig,
+    params: &PhyParams<M::Params>,
+  ) -> Result<Self, ParamError>
+  where
+    I: crate::traits::InStream<FP, ()> + Send + 'static,
+    O: crate::traits::OutStream<FP, ()> + Send + 'static,
+    P: crate::traits::PowerProbe + Send + 'static,
+  {
+    Self::with_boxed_streams(
+      Box::new(stream_in),
+      Box::new(stream_out),
+      Box::new(power_probe),
+      config,
+      params,
+      None,
+    )
+  }
+
+  /// Build a physics layer object on the given sample streams and power probe,
+  /// and capture the samples sent/received with the packet boundaries into a directory, see [`CaptureDir`].
+  pub fn with_streams_capture<I, O, P>(
+    stream_in: I,
+    stream_out: O,
+    power_probe: P,
+    config: &AudioConfig,
+    capture: &CaptureDir,
+  ) -> io::Result<Self>
+  where
+    I: crate::traits::InStream<FP, ()> + Send + 'static,
+    O: crate::traits::OutStream<FP, ()> + Send + 'static,
+    P: crate::traits::PowerProbe + Send + 'static,
+  {
+    Self::with_boxed_streams(
+      Box::new(capture.tee_in(stream_in, config)?),
+      Box::new(capture.tee_out(stream_out, config)?),
+      Box::new(power_probe),
+      config,
+      &Default::default(),
+      Some((capture.rx_log()?, capture.tx_log()?)),
+    )
+    .map_err(io::Error::other)
+  }
+
+  fn with_boxed_streams(
+    stream_in: InStream,
+    stream_out: OutStream,
+    power_probe: PowerProbe,
+    config: &AudioConfig,
+    params: &PhyParams<M::Params>,
+    logs: Option<(CaptureLog, CaptureLog)>,
+  ) -> Result<Self, ParamError> {
+    config.validate()?;
+    let preamble = || Preamble::with_params(&params.preamble, config);
+    let modem = || M::with_params(&params.modem, config);
+    let (rx_log, tx_log) = logs.unzip();
+    let tx = Tx::with_capture(stream_out, preamble()?, modem()?, tx_log);
+    let rx = Rx::with_capture(
+      stream_in,
+      modem()?,
+      FrameDetector::with_modem(preamble()?, modem()?),
+      config,
+      rx_log,
+    );
+    Ok(Self::new(tx, rx, power_probe))
+  }
+
+  /// build a physics layer object on the default audio devices with the given audio config
+  pub fn with_config(config: &AudioConfig) -> Self {
+    let (stream_in, power_probe) = CpalInStream::with_config_probe(config);
+    Self::with_streams_config(stream_in, CpalOutStream::with_config(config), power_probe, config)
+  }
+
+  /// build a physics layer object on the default audio devices with the given parameters
+  pub fn with_params(config: &AudioConfig, params: &PhyParams<M::Params>) -> Result<Self, ParamError> {
+    let (stream_in, power_probe) = CpalInStream::with_config_probe(config);
+    Self::with_streams_params(
+      stream_in,
+      CpalOutStream::with_config(config),
+      power_probe,
+      config,
+      params,
+    )
+  }
+
+  /// build a physics layer object on the default audio devices, capturing into a directory
+  pub fn with_capture(config: &AudioConfig, capture: &CaptureDir) -> io::Result<Self> {
+    let (stream_in, power_probe) = CpalInStream::with_config_probe(config);
+    Self::with_streams_capture(
+      stream_in,
+      CpalOutStream::with_config(config),
+      power_probe,
+      config,
+      capture,
+    )
+  }
+
+  /// Build a physics layer object on the selected input/output devices of a host.
+  /// The power probe shares the input stream.
+  pub fn on_devices(
+    host: &Host,
+    input: &DeviceSelector,
+    output: &DeviceSelector,
+    config: &AudioConfig,
+  ) -> Result<Self, DeviceError> {
+    let (stream_in, power_probe) = CpalInStream::on_device_with_probe(host, input, config)?;
+    Ok(Self::with_streams_config(
+      stream_in,
+      CpalOutStream::on_device(host, output, config)?,
+      power_probe,
+      config,
+    ))
+  }
+
+  /// Build one physics layer object for each channel of the selected input/output devices of a host,
+  /// e.g. two independent links on the left/right channels of a stereo sound card.
+  /// The power probe of each object only listens on its own channel.
+  pub fn on_channels(
+    host: &Host,
+    input: &DeviceSelector,
+    output: &DeviceSelector,
+    config: &AudioConfig,
+  ) -> Result<Vec<Self>, DeviceError> {
+    let channels: Vec<_> = (0..config.channels as usize).collect();
+    Self::with_channels(host, input, output, &channels, config)
+  }
+
+  /// Build a physics layer object on one channel of the selected input/output devices of a host,
+  /// the other channels of the devices are left silent.
+  pub fn on_channel(
+    host: &Host,
+    input: &DeviceSelector,
+    output: &DeviceSelector,
+    channel: usize,
+    config: &AudioConfig,
+  ) -> Result<Self, DeviceError> {
+    if channel >= config.channels as usize {
+      return Err(DeviceError::NoChannel(channel));
+    }
+    Ok(Self::with_channels(host, input, output, &[channel], config)?.remove(0))
+  }
+
+  // one physics layer object for each of the given channels,
+  // the input stream and the power probe of a channel share the same cpal stream
+  fn with_channels(
+    host: &Host,
+    input: &DeviceSelector,
+    output: &DeviceSelector,
+    channels: &[usize],
+    config: &AudioConfig,
+  ) -> Result<Vec<Self>, DeviceError> {
+    let stream_in = CpalInStream::split_channels(input_device(host, input)?, config, channels)?;
+    let stream_out = CpalOutStream::split_channels(output_device(host, output)?, config, channels)?;
+    Ok(
+      stream_in
+        .into_iter()
+        .zip(stream_out)
+        .map(|((stream_in, power_probe), stream_out)| {
+          Self::with_streams_config(stream_in, stream_out, power_probe, config)
+        })
+        .collect(),
+    )
+  }
 }
 
-impl PhyLayer for PlainPHY {
+impl<M: PlainModem> PhyLayer for PlainPHY<M> {
   type SendErr = ();
   type RecvErr = ();
-  const PACKET_BYTES: usize = ModemMethod::BYTES_PER_PACKET;
-  const ESTIMATED_RTT: Duration = ESTIMATED_RTT;
+  const PACKET_BYTES: usize = M::PACKET_BYTES;
+  const ESTIMATED_RTT: Duration = M::ESTIMATED_RTT;
 
   fn channel_free(&self) -> bool {
     self.power_probe.power() < REST_POWER
   }
+
+  fn packet_bytes(&self) -> usize {
+    PlainPHY::packet_bytes(self)
+  }
+
+  /// the soft bits of the modem, see [`Modem::demodulate_soft`]
+  fn recv_soft(&mut self) -> Result<SoftBits, ()> {
+    self.rx.recv_soft().ok_or(())
+  }
+
+  fn recv_soft_timeout(&mut self, timeout: Duration) -> Result<SoftBits, ()> {
+    self.rx.recv_soft_timeout(timeout).ok_or(())
+  }
 }
 
-impl PacketSender<PhyPacket, ()> for PlainPHY {
-  /// send a packet, return until send finished or error
+impl<M: PlainModem> PacketSender<PhyPacket, ()> for PlainPHY<M> {
+  /// send a packet of at most [`PlainPHY::packet_bytes`] bytes, return until send finished or error
   fn send(&mut self, packet: PhyPacket) -> Result<(), ()> {
-    assert_eq!(packet.len(), Self::PACKET_BYTES);
+    assert!(packet.len() <= self.packet_bytes());
     self.tx.send(packet)
   }
 }
-impl PacketReceiver<PhyPacket, ()> for PlainPHY {
+impl<M: PlainModem> PacketReceiver<PhyPacket, ()> for PlainPHY<M> {
   /// receive a packet, return received a packet or error
   fn recv(&mut self) -> Result<PhyPacket, ()> {
     self.rx.recv()
@@ -56,16 +281,10 @@ impl PacketReceiver<PhyPacket, ()> for PlainPHY {
   }
 }
 
-impl Default for PlainPHY {
+impl<M: PlainModem> Default for PlainPHY<M> {
+  /// PHY layer on the default audio input/output device
   fn default() -> Self {
-    let tx = Tx::new(OutStream::default(), ModemMethod::default());
-    let rx = Rx::new(
-      InStream::default(),
-      ModemMethod::default(),
-      FrameDetector::new::<{ ModemMethod::SAMPLES_PER_PACKET }>(Preamble::new()),
-    );
-    let power_probe = PowerProbe::default();
-    Self::new(tx, rx, power_probe)
+    Self::with_config(&AudioConfig::default())
   }
 }
 
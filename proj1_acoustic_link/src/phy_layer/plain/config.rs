@@ -1,21 +1,85 @@
 #[cfg(feature = "wired")]
-pub use crate::phy_packet::modem::LineCode as ModemMethod;
+pub use crate::phy_packet::modem::{LineCode as ModemMethod, LineCodeParams as ModemParams};
 #[cfg(not(feature = "wired"))]
-pub use crate::phy_packet::modem::PSK as ModemMethod;
+pub use crate::phy_packet::modem::{PskParams as ModemParams, PSK as ModemMethod};
 
+use crate::phy_packet::modem::{DpskParams, FskParams, LineCode, LineCodeParams, PskParams, DPSK, FSK, PSK};
 pub use crate::phy_packet::{
-  frame_detect::CorrelationFraming as FrameDetector, preambles::ChirpUpDown as Preamble, txrx::PhyReceiver,
+  frame_detect::CorrelationFraming as FrameDetector, preambles::AnyPreamble as Preamble, txrx::PhyReceiver,
   txrx::PhySender,
 };
+use crate::phy_packet::{params::ParamError, Modem};
+use crate::traits::FP;
+use crate::AudioConfig;
 use std::time::Duration;
 
-pub use crate::sample_stream::{CpalInStream as InStream, CpalOutStream as OutStream, CpalPowerProbe as PowerProbe};
+/// sample input stream type, the receiver can work on any sample stream
+pub type InStream = Box<dyn crate::traits::InStream<FP, ()> + Send>;
+/// sample output stream type, the sender can work on any sample stream
+pub type OutStream = Box<dyn crate::traits::OutStream<FP, ()> + Send>;
+/// channel power probe type
+pub type PowerProbe = Box<dyn crate::traits::PowerProbe + Send>;
 
 /// physice packet sender type
-pub type Tx = PhySender<Preamble, ModemMethod, OutStream, ()>;
+pub type Tx<M = ModemMethod> = PhySender<Preamble, M, OutStream, ()>;
 /// physice packet receiver type
-pub type Rx = PhyReceiver<Preamble, ModemMethod, FrameDetector<Preamble>, InStream, ()>;
+pub type Rx<M = ModemMethod> = PhyReceiver<Preamble, M, FrameDetector<Preamble>, InStream, ()>;
 
-/// the channel is considered free when the power is smaller than [`REST_POWER`]
-pub const REST_POWER: f32 = if cfg!(feature = "wired") { 1e-5 } else { 1e9 };
-pub const ESTIMATED_RTT: Duration = Duration::from_millis(150);
+/// The channel is considered free when the power is smaller than [`REST_POWER`].
+/// Over the air, the noise of a quiet room stays below the power of a tone at about 1/20 of the full scale.
+pub const REST_POWER: f32 = if cfg!(feature = "wired") { 1e-5 } else { 1e-3 };
+
+/// A modem a [`super::PlainPHY`] can be built on, with its runtime parameters
+pub trait PlainModem: Modem + Send + 'static {
+  /// runtime parameters of the modem
+  type Params: Clone + Default;
+  /// maximum number of bytes in one packet with the default parameters
+  const PACKET_BYTES: usize;
+  /// estimated RTT of a frame of the default parameters
+  const ESTIMATED_RTT: Duration;
+
+  /// build the modem with the given parameters at the sampling rate of the audio config
+  fn with_params(params: &Self::Params, config: &AudioConfig) -> Result<Self, ParamError>;
+}
+
+impl PlainModem for LineCode {
+  type Params = LineCodeParams;
+  const PACKET_BYTES: usize = LineCodeParams::DEFAULT.bytes_per_packet();
+  const ESTIMATED_RTT: Duration = Duration::from_millis(150);
+
+  fn with_params(params: &LineCodeParams, config: &AudioConfig) -> Result<Self, ParamError> {
+    LineCode::with_params(params, config)
+  }
+}
+
+impl PlainModem for PSK {
+  type Params = PskParams;
+  const PACKET_BYTES: usize = PskParams::DEFAULT.bytes_per_packet();
+  const ESTIMATED_RTT: Duration = Duration::from_millis(150);
+
+  fn with_params(params: &PskParams, config: &AudioConfig) -> Result<Self, ParamError> {
+    PSK::with_params(params, config)
+  }
+}
+
+impl PlainModem for DPSK {
+  type Params = DpskParams;
+  const PACKET_BYTES: usize = DpskParams::DEFAULT.bytes_per_packet();
+  /// a frame of the default parameters lasts about 170ms
+  const ESTIMATED_RTT: Duration = Duration::from_millis(400);
+
+  fn with_params(params: &DpskParams, config: &AudioConfig) -> Result<Self, ParamError> {
+    DPSK::with_params(params, config)
+  }
+}
+
+impl PlainModem for FSK {
+  type Params = FskParams;
+  const PACKET_BYTES: usize = FskParams::DEFAULT.bytes_per_packet();
+  /// a frame of the default parameters lasts about 350ms
+  const ESTIMATED_RTT: Duration = Duration::from_millis(800);
+
+  fn with_params(params: &FskParams, config: &AudioConfig) -> Result<Self, ParamError> {
+    FSK::with_params(params, config)
+  }
+}
@@ -0,0 +1,179 @@
+use rand::{distributions::Standard, Rng};
+use std::{collections::VecDeque, time::Duration};
+
+use super::{CodeRate, CrcPhy, CrcPhyRecvErr, Decision, FecParams, FecPhy, PhyLayer};
+use crate::phy_packet::{
+  soft::{from_hard, hard_decision},
+  PhyPacket, SoftBits,
+};
+use crate::traits::{PacketReceiver, PacketSender};
+
+const RATES: [CodeRate; 3] = [CodeRate::Half, CodeRate::TwoThirds, CodeRate::ThreeQuarters];
+
+/// A PHY layer receiving the packets it sends, through a channel turning the bytes sent into soft bits
+struct Loopback {
+  packets: VecDeque<PhyPacket>,
+  channel: fn(&[u8]) -> SoftBits,
+}
+
+impl Loopback {
+  fn new(channel: fn(&[u8]) -> SoftBits) -> Self {
+    Self {
+      packets: VecDeque::new(),
+      channel,
+    }
+  }
+}
+
+impl PhyLayer for Loopback {
+  type SendErr = ();
+  type RecvErr = ();
+  const PACKET_BYTES: usize = 100;
+  const ESTIMATED_RTT: Duration = Duration::ZERO;
+
+  fn channel_free(&self) -> bool {
+    true
+  }
+
+  fn recv_soft(&mut self) -> Result<SoftBits, ()> {
+    let packet = self.packets.pop_front().ok_or(())?;
+    Ok((self.channel)(&packet))
+  }
+
+  fn recv_soft_timeout(&mut self, _: Duration) -> Result<SoftBits, ()> {
+    self.recv_soft()
+  }
+}
+
+impl PacketSender<PhyPacket, ()> for Loopback {
+  fn send(&mut self, packet: PhyPacket) -> Result<(), ()> {
+    assert!(packet.len() <= Self::PACKET_BYTES);
+    self.packets.push_back(packet);
+    Ok(())
+  }
+}
+
+impl PacketReceiver<PhyPacket, ()> for Loopback {
+  fn recv(&mut self) -> Result<PhyPacket, ()> {
+    self.recv_soft().map(|soft| hard_decision(&soft))
+  }
+
+  fn recv_timeout(&mut self, _: Duration) -> Result<PhyPacket, ()> {
+    self.recv()
+  }
+
+  fn recv_peek(&mut self) -> bool {
+    !self.packets.is_empty()
+  }
+}
+
+fn random_packet(len: usize) -> PhyPacket {
+  rand::thread_rng().sample_iter(Standard).take(len).collect()
+}
+
+fn fec(rate: CodeRate, decision: Decision, channel: fn(&[u8]) -> SoftBits) -> FecPhy<Loopback> {
+  FecPhy::with_params(Loopback::new(channel), FecParams { rate, decision })
+}
+
+/// a burst of wrong bits in the middle of the packet, always detected by the CRC16
+fn burst(bytes: &[u8]) -> SoftBits {
+  let mut soft = from_hard(bytes);
+  let start = soft.len() / 2;
+  soft[start..start + 16].iter_mut().for_each(|llr| *llr = -*llr);
+  soft
+}
+
+/// every fourth bit wrong, but less reliable than the others
+fn weak_errors(bytes: &[u8]) -> SoftBits {
+  let mut soft = from_hard(bytes);
+  soft.iter_mut().step_by(4).for_each(|llr| *llr *= -0.2);
+  soft
+}
+
+/// packets of every length up to the maximum are received as sent, in coded packets of the expected length
+#[test]
+fn fec_ideal() {
+  for rate in RATES {
+    let mut phy = fec(rate, Decision::Soft, from_hard);
+    assert_eq!(phy.packet_bytes(), rate.data_bytes(Loopback::PACKET_BYTES));
+    for len in 0..=phy.packet_bytes() {
+      let packet = random_packet(len);
+      assert_eq!(phy.encode(&packet).len(), rate.coded_bytes(len));
+      phy.send(packet.clone()).unwrap();
+      assert_eq!(phy.recv().unwrap(), packet, "{:?}", rate);
+    }
+  }
+  assert_eq!(
+    FecPhy::<Loopback>::PACKET_BYTES,
+    CodeRate::Half.data_bytes(Loopback::PACKET_BYTES)
+  );
+}
+
+/// a packet corrupted by a burst of errors is corrected under the CRC layer
+#[test]
+fn fec_corrects_burst() {
+  let packet = random_packet(<CrcPhy<Loopback>>::PACKET_BYTES);
+  let mut phy = CrcPhy::new(Loopback::new(burst));
+  phy.send(packet.clone()).unwrap();
+  assert!(matches!(phy.recv(), Err(CrcPhyRecvErr::Corrupt)));
+
+  for rate in RATES {
+    let mut phy = CrcPhy::new(fec(rate, Decision::Hard, burst));
+    let packet = random_packet(phy.packet_bytes());
+    phy.send(packet.clone()).unwrap();
+    assert_eq!(phy.recv().unwrap(), packet, "{:?}", rate);
+  }
+}
+
+/// the soft decisions correct errors on the unreliable bits beyond the reach of the hard decisions
+#[test]
+fn fec_soft_decision() {
+  let mut soft = fec(CodeRate::Half, Decision::Soft, weak_errors);
+  let mut hard = fec(CodeRate::Half, Decision::Hard, weak_errors);
+  let packet = random_packet(soft.packet_bytes());
+  soft.send(packet.clone()).unwrap();
+  hard.send(packet.clone()).unwrap();
+  assert_eq!(soft.recv().unwrap(), packet);
+  assert_ne!(hard.recv().unwrap(), packet);
+}
+
+/// blocks sent in packets with parity packets are received whole,
+/// a block is recovered from as many of its packets as its data packets
+#[test]
+#[cfg(feature = "wired")]
+fn rs_blocks() {
+  use super::{AtomicPHY, PlainPHY, RsParams, RsPhy, RsPhyRecvErr};
+  use crate::sample_stream::VirtualAir;
+
+  let air = VirtualAir::new(2);
+  let params = RsParams {
+    data_packets: 3,
+    parity_packets: 2,
+  };
+  let mut phys: Vec<_> = (0..2)
+    .map(|id| {
+      let (stream_in, stream_out, power_probe) = air.node(id);
+      let phy = AtomicPHY::new(<PlainPHY>::with_streams(stream_in, stream_out, power_probe));
+      RsPhy::with_params(phy, &params).unwrap()
+    })
+    .collect();
+  let block = random_packet(phys[0].block_bytes());
+
+  for len in [block.len(), 10] {
+    phys[0].send_block(&block[..len]).unwrap();
+  }
+  for len in [block.len(), 10] {
+    assert_eq!(phys[1].recv_block(Duration::from_millis(500)).unwrap(), block[..len]);
+  }
+  assert_eq!(
+    phys[1].recv_block(Duration::from_millis(500)),
+    Err(RsPhyRecvErr::NoBlock)
+  );
+
+  let mut packets: Vec<_> = phys[0].encode(&block).into_iter().map(Some).collect();
+  packets[0] = None;
+  packets[3] = None;
+  assert_eq!(phys[1].decode(packets.clone()).unwrap(), block);
+  packets[1] = None;
+  assert_eq!(phys[1].decode(packets), Err(RsPhyRecvErr::Unrecoverable(2)));
+}
@@ -1,22 +1,51 @@
 use std::fmt::Debug;
 use std::time::Duration;
 
-pub use crate::phy_packet::PhyPacket;
+use crate::phy_packet::preambles::PreambleParams;
+use crate::phy_packet::soft::from_hard;
+pub use crate::phy_packet::{PhyPacket, SoftBits};
 pub use crate::traits::{PacketReceiver, PacketSender};
 
 /// The PHY layer service provider trait:
-/// Sending/receiving packets of fixed bytes with no correctness or delivery guarantee
+/// Sending/receiving packets of at most [`PhyLayer::PACKET_BYTES`] bytes with no correctness or delivery guarantee
 ///
 /// - `SendErr`: the error type that may occur when sending a packet
 /// - `RecvErr`: the error type that may occur when receiving a packet
 pub trait PhyLayer: PacketSender<PhyPacket, Self::SendErr> + PacketReceiver<PhyPacket, Self::RecvErr> {
   type SendErr: Debug;
   type RecvErr: Debug;
-  /// number of bytes in one packet
+  /// maximum number of bytes in one packet, with the default parameters if the layer is parameterised
   const PACKET_BYTES: usize;
   /// estimated RTT on the channel
   const ESTIMATED_RTT: Duration;
 
   /// Determine if the channel is free so that we can send a packet
   fn channel_free(&self) -> bool;
+
+  /// maximum number of bytes in one packet with the runtime parameters of the layer,
+  /// [`PhyLayer::PACKET_BYTES`] by default
+  fn packet_bytes(&self) -> usize {
+    Self::PACKET_BYTES
+  }
+
+  /// Receive a packet as soft bits, see [`SoftBits`], return immediately.
+  /// The layers without soft information give the hard bits of [`PacketReceiver::recv`].
+  fn recv_soft(&mut self) -> Result<SoftBits, Self::RecvErr> {
+    self.recv().map(|packet| from_hard(&packet))
+  }
+
+  /// receive a packet as soft bits, retry until error or timeout, see [`PhyLayer::recv_soft`]
+  fn recv_soft_timeout(&mut self, timeout: Duration) -> Result<SoftBits, Self::RecvErr> {
+    self.recv_timeout(timeout).map(|packet| from_hard(&packet))
+  }
+}
+
+/// Runtime parameters of a PHY layer built on a preamble and a modem with parameters `M`,
+/// e.g. to tune the link for a new cable or room, or to pick the preamble of a node, without recompiling.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PhyParams<M> {
+  /// parameters of the preamble
+  pub preamble: PreambleParams,
+  /// parameters of the modem
+  pub modem: M,
 }
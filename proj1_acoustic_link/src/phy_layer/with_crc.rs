@@ -16,8 +16,36 @@ pub enum CrcPhyRecvErr {
 
 /// A PHY layer implementation with CRC16 checksum protecting each packet
 /// packet corruption can be detected
+///
+/// The underlying layer is [`PlainPHY`] by default,
+/// e.g. [`super::FecPhy`] to correct the packets before they are checked.
 #[derive(Default)]
-pub struct CrcPhy(PlainPHY);
+pub struct CrcPhy<P = PlainPHY>(P);
+
+impl<P: PhyLayer> CrcPhy<P> {
+  /// combine a sender and a receiver to get a physics layer object
+  pub fn new(txrx: P) -> Self {
+    Self(txrx)
+  }
+
+  /// maximum number of data bytes in one packet with the parameters of the underlying layer,
+  /// [`PhyLayer::PACKET_BYTES`] with the default parameters.
+  /// The modems hold at least [`crate::phy_packet::params::MIN_PACKET_BYTES`] bytes, which leaves room for some data,
+  /// zero if the packets of the underlying layer can not even hold the checksum.
+  pub fn packet_bytes(&self) -> usize {
+    self.0.packet_bytes().saturating_sub(CrcPhy::CRC_BYTES)
+  }
+
+  /// verify data integrity with crc16
+  fn on_packet_arrived(&mut self, packet: PhyPacket) -> Result<PhyPacket, CrcPhyRecvErr> {
+    if let Some(packet_data) = CrcPhy::crc_remove(packet) {
+      Ok(packet_data)
+    } else {
+      println!("[CRC PHY] a packet is corrupted");
+      Err(CrcPhyRecvErr::Corrupt)
+    }
+  }
+}
 
 impl CrcPhy {
   /// number of bytes reserved for CRC checksum
@@ -25,63 +53,64 @@ impl CrcPhy {
   /// the crc16 checksum algorithm
   pub const CRC16: Crc<u16> = Crc::<u16>::new(&CRC_16_USB);
 
-  /// combine a sender and a receiver to get a physics layer object
-  pub fn new(txrx: PlainPHY) -> Self {
-    Self(txrx)
+  /// Whether the last [`Self::CRC_BYTES`] bytes of a packet are the checksum of the rest,
+  /// e.g. to check a packet decoded offline.
+  pub fn verify(packet: &[u8]) -> bool {
+    if packet.len() < Self::CRC_BYTES {
+      return false;
+    }
+    let (data, checksum) = packet.split_at(packet.len() - Self::CRC_BYTES);
+    checksum == Self::checksum(data)
   }
 
-  fn crc_append(mut packet: PhyPacket) -> PhyPacket {
-    let crc = Self::CRC16.checksum(&packet);
+  /// the checksum appended to the data of a packet
+  pub fn checksum(data: &[u8]) -> [u8; 2] {
+    let crc = Self::CRC16.checksum(data);
     let cs_low = (crc & 0x00FF) as u8;
     let cs_high = (crc & 0xFF00) as u8;
-    packet.extend([cs_low, cs_high]);
+    [cs_low, cs_high]
+  }
+
+  fn crc_append(mut packet: PhyPacket) -> PhyPacket {
+    let checksum = Self::checksum(&packet);
+    packet.extend(checksum);
     packet
   }
   fn crc_remove(packet: PhyPacket) -> Option<PhyPacket> {
-    let (data, checksum) = packet.split_at(Self::PACKET_BYTES);
-    let crc = Self::CRC16.checksum(data);
-    let cs_low = (crc & 0x00FF) as u8;
-    let cs_high = (crc & 0xFF00) as u8;
-    if checksum == [cs_low, cs_high] {
-      Some(PhyPacket::from(data))
+    if Self::verify(&packet) {
+      Some(PhyPacket::from(&packet[..packet.len() - Self::CRC_BYTES]))
     } else {
       None
     }
   }
-
-  /// verify data integrity with crc16
-  fn on_packet_arrived(&mut self, packet: PhyPacket) -> Result<PhyPacket, CrcPhyRecvErr> {
-    if let Some(packet_data) = Self::crc_remove(packet) {
-      Ok(packet_data)
-    } else {
-      println!("[CRC PHY] a packet is corrupted");
-      Err(CrcPhyRecvErr::Corrupt)
-    }
-  }
 }
 
-impl PhyLayer for CrcPhy {
-  type SendErr = ();
+impl<P: PhyLayer> PhyLayer for CrcPhy<P> {
+  type SendErr = P::SendErr;
   type RecvErr = CrcPhyRecvErr;
 
-  /// number of data bytes in one packet, 2 bytes used for CRC16
-  const PACKET_BYTES: usize = PlainPHY::PACKET_BYTES - Self::CRC_BYTES;
-  const ESTIMATED_RTT: Duration = PlainPHY::ESTIMATED_RTT;
+  /// maximum number of data bytes in one packet, 2 bytes used for CRC16
+  const PACKET_BYTES: usize = P::PACKET_BYTES - CrcPhy::CRC_BYTES;
+  const ESTIMATED_RTT: Duration = P::ESTIMATED_RTT;
 
   fn channel_free(&self) -> bool {
     self.0.channel_free()
   }
+
+  fn packet_bytes(&self) -> usize {
+    CrcPhy::packet_bytes(self)
+  }
 }
 
-impl PacketSender<PhyPacket, ()> for CrcPhy {
-  fn send(&mut self, packet: PhyPacket) -> Result<(), ()> {
-    assert_eq!(packet.len(), Self::PACKET_BYTES);
-    let packet = Self::crc_append(packet);
+impl<P: PhyLayer> PacketSender<PhyPacket, P::SendErr> for CrcPhy<P> {
+  fn send(&mut self, packet: PhyPacket) -> Result<(), P::SendErr> {
+    assert!(packet.len() <= self.packet_bytes());
+    let packet = CrcPhy::crc_append(packet);
     self.0.send(packet)
   }
 }
 
-impl PacketReceiver<PhyPacket, CrcPhyRecvErr> for CrcPhy {
+impl<P: PhyLayer> PacketReceiver<PhyPacket, CrcPhyRecvErr> for CrcPhy<P> {
   /// Receive a packet immediately.  
   /// Success: the received packet
   /// Failed: [`RecvError`] type: no packet, packet corrupt, packet lost
@@ -0,0 +1,279 @@
+use super::{PhyLayer, PlainPHY};
+use crate::helper::{bits_to_bytes, bytes_to_bits};
+use crate::phy_packet::soft::from_hard;
+pub use crate::phy_packet::{PhyPacket, SoftBits};
+pub use crate::traits::{PacketReceiver, PacketSender};
+use std::time::Duration;
+
+/// constraint length of the convolutional code, the input bit and the `K - 1` bits before it
+const CONSTRAINT_LENGTH: usize = 7;
+/// number of states of the encoder, its last `K - 1` input bits
+const STATES: usize = 1 << (CONSTRAINT_LENGTH - 1);
+/// generator polynomials of the rate 1/2 mother code, the usual K=7 code (133, 171 in octal)
+const GENERATORS: [usize; 2] = [0o133, 0o171];
+/// zero bits appended to the data to bring the encoder back to the state `0`
+const TAIL_BITS: usize = CONSTRAINT_LENGTH - 1;
+/// number of columns of the block interleaver, the distance of two neighbour bits once interleaved
+/// is the number of rows
+const INTERLEAVER_COLUMNS: usize = 16;
+
+/// Rate of the convolutional code of [`FecPhy`]: the rate 1/2 mother code punctured to a higher rate.
+/// A higher rate carries more data in a packet but corrects fewer errors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CodeRate {
+  /// the mother code, two coded bits for a data bit
+  #[default]
+  Half,
+  /// three coded bits out of the four of two data bits
+  TwoThirds,
+  /// four coded bits out of the six of three data bits
+  ThreeQuarters,
+}
+
+impl CodeRate {
+  /// the coded bits of a puncturing period which are sent, the two outputs of the encoder alternating
+  const fn pattern(&self) -> &'static [bool] {
+    match self {
+      Self::Half => &[true, true],
+      Self::TwoThirds => &[true, true, true, false],
+      Self::ThreeQuarters => &[true, true, true, false, false, true],
+    }
+  }
+
+  /// number of coded bits sent for `bits` bits into the encoder
+  const fn coded_bits(&self, bits: usize) -> usize {
+    const fn sent(pattern: &[bool], len: usize) -> usize {
+      let (mut n, mut i) = (0, 0);
+      while i < len {
+        n += pattern[i] as usize;
+        i += 1;
+      }
+      n
+    }
+    let pattern = self.pattern();
+    let period = pattern.len();
+    2 * bits / period * sent(pattern, period) + sent(pattern, 2 * bits % period)
+  }
+
+  /// number of bytes of the coded packet of `bytes` data bytes, with the tail bits
+  pub const fn coded_bytes(&self, bytes: usize) -> usize {
+    self.coded_bits(8 * bytes + TAIL_BITS).div_ceil(8)
+  }
+
+  /// maximum number of data bytes whose coded packet fits in `bytes` bytes
+  pub const fn data_bytes(&self, bytes: usize) -> usize {
+    let mut data = 0;
+    while self.coded_bytes(data + 1) <= bytes {
+      data += 1;
+    }
+    data
+  }
+
+  /// whether each coded bit of the mother code of `bits` input bits is sent
+  fn punctured(&self, bits: usize) -> impl Iterator<Item = bool> {
+    self.pattern().iter().copied().cycle().take(2 * bits)
+  }
+}
+
+/// How [`FecPhy`] reads the coded bits received
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Decision {
+  /// the soft bits of the underlying layer, see [`PhyLayer::recv_soft`]:
+  /// the bits the modem is unsure of weigh less in the decoding
+  #[default]
+  Soft,
+  /// the bytes of the underlying layer, all the bits weigh the same
+  Hard,
+}
+
+/// Parameters of [`FecPhy`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FecParams {
+  /// rate of the convolutional code
+  pub rate: CodeRate,
+  /// soft or hard decision decoding
+  pub decision: Decision,
+}
+
+/// A PHY layer wrapper with forward error correction:
+/// a convolutional code, punctured to the rate of [`FecParams`], followed by a block interleaver.
+/// The receiver deinterleaves the coded bits and finds the most likely data with the Viterbi algorithm,
+/// so that a packet is corrected unless too many of its bits are wrong.
+///
+/// The packets are not checked, put the layer under [`super::CrcPhy`] to drop the packets left corrupted.
+/// The interleaver spreads a burst of errors on the channel over the whole packet,
+/// as the code corrects scattered errors far better than neighbour ones.
+pub struct FecPhy<P = PlainPHY> {
+  phy: P,
+  params: FecParams,
+}
+
+impl<P: PhyLayer> FecPhy<P> {
+  /// protect the packets of the underlying layer with the default parameters
+  pub fn new(phy: P) -> Self {
+    Self::with_params(phy, FecParams::default())
+  }
+
+  pub fn with_params(phy: P, params: FecParams) -> Self {
+    Self { phy, params }
+  }
+
+  pub fn params(&self) -> &FecParams {
+    &self.params
+  }
+
+  /// maximum number of data bytes in one packet with the parameters of the layer and of the underlying layer,
+  /// [`PhyLayer::PACKET_BYTES`] with the default parameters
+  pub fn packet_bytes(&self) -> usize {
+    self.params.rate.data_bytes(self.phy.packet_bytes())
+  }
+
+  /// the coded packet of the data: encoded with the tail bits, punctured, interleaved and padded to whole bytes
+  pub fn encode(&self, data: &[u8]) -> PhyPacket {
+    let mut state = 0;
+    let coded: Vec<_> = bytes_to_bits(data)
+      .into_iter()
+      .chain([0; TAIL_BITS])
+      .flat_map(|bit| {
+        let register = (state << 1) | bit as usize;
+        state = register % STATES;
+        GENERATORS.map(|generator| parity(register & generator))
+      })
+      .zip(self.params.rate.punctured(8 * data.len() + TAIL_BITS))
+      .filter_map(|(bit, sent)| sent.then_some(bit))
+      .collect();
+
+    let mut bits: Vec<_> = interleaver(coded.len()).into_iter().map(|i| coded[i]).collect();
+    bits.resize(bits.len().next_multiple_of(8), 0);
+    bits_to_bytes(&bits)
+  }
+
+  /// The data of the soft bits of a coded packet, see [`FecPhy::encode`].
+  /// The data is as long as the coded packet can carry, the padding bits are ignored.
+  pub fn decode(&self, soft: &[f32]) -> PhyPacket {
+    let rate = self.params.rate;
+    let bytes = rate.data_bytes(soft.len() / 8);
+    let bits = 8 * bytes + TAIL_BITS;
+
+    // the punctured bits are erased: they favour neither 0 nor 1
+    let mut coded = vec![0.0; rate.coded_bits(bits)];
+    for (i, llr) in interleaver(coded.len()).into_iter().zip(soft) {
+      coded[i] = *llr;
+    }
+    let mut coded = coded.into_iter();
+    let mother: Vec<_> = rate
+      .punctured(bits)
+      .map(|sent| if sent { coded.next().unwrap() } else { 0.0 })
+      .collect();
+
+    let mut data = viterbi(&mother);
+    data.truncate(8 * bytes);
+    bits_to_bytes(&data)
+  }
+
+  // receive the coded packet as soft or hard bits following the parameters
+  fn recv_coded(&mut self, timeout: Option<Duration>) -> Result<SoftBits, P::RecvErr> {
+    match (self.params.decision, timeout) {
+      (Decision::Soft, None) => self.phy.recv_soft(),
+      (Decision::Soft, Some(timeout)) => self.phy.recv_soft_timeout(timeout),
+      (Decision::Hard, None) => self.phy.recv().map(|packet| from_hard(&packet)),
+      (Decision::Hard, Some(timeout)) => self.phy.recv_timeout(timeout).map(|packet| from_hard(&packet)),
+    }
+  }
+}
+
+fn parity(x: usize) -> u8 {
+  (x.count_ones() % 2) as u8
+}
+
+/// The block interleaver of `len` bits: written row by row into [`INTERLEAVER_COLUMNS`] columns,
+/// read column by column, the last row may be short.
+/// The `i`-th bit sent is the bit `order[i]` before interleaving.
+fn interleaver(len: usize) -> Vec<usize> {
+  let mut order: Vec<_> = (0..len).collect();
+  order.sort_by_key(|i| (i % INTERLEAVER_COLUMNS, i / INTERLEAVER_COLUMNS));
+  order
+}
+
+/// The Viterbi algorithm on the soft bits of the mother code, two for each input bit, `0` for the erased ones:
+/// the input bits of the path through the trellis whose coded bits correlate the most with the soft bits.
+/// The path starts and ends at the state `0`, the tail bits are returned too.
+fn viterbi(soft: &[f32]) -> Vec<u8> {
+  let mut metrics = [f32::NEG_INFINITY; STATES];
+  metrics[0] = 0.0;
+  // for each step, the states whose survivor comes from the predecessor with the oldest bit at 1
+  let mut decisions: Vec<u64> = Vec::with_capacity(soft.len() / 2);
+
+  for llrs in soft.chunks_exact(2) {
+    let mut next = [f32::NEG_INFINITY; STATES];
+    let mut decision = 0u64;
+    for (state, metric) in next.iter_mut().enumerate() {
+      // the state is the register without its oldest bit, the input bit is its lowest bit
+      for oldest in 0..2 {
+        let previous = (state >> 1) | (oldest << (CONSTRAINT_LENGTH - 2));
+        let register = (previous << 1) | (state & 1);
+        let branch: f32 = GENERATORS
+          .iter()
+          .zip(llrs)
+          .map(|(generator, llr)| if parity(register & generator) == 0 { *llr } else { -llr })
+          .sum();
+        if metrics[previous] + branch > *metric {
+          *metric = metrics[previous] + branch;
+          if oldest == 1 {
+            decision |= 1 << state;
+          }
+        }
+      }
+    }
+    metrics = next;
+    decisions.push(decision);
+  }
+
+  let mut state = 0;
+  let mut bits = vec![0; decisions.len()];
+  for (bit, decision) in bits.iter_mut().zip(decisions).rev() {
+    *bit = (state & 1) as u8;
+    let oldest = ((decision >> state) & 1) as usize;
+    state = (state >> 1) | (oldest << (CONSTRAINT_LENGTH - 2));
+  }
+  bits
+}
+
+impl<P: PhyLayer> PhyLayer for FecPhy<P> {
+  type SendErr = P::SendErr;
+  type RecvErr = P::RecvErr;
+
+  /// maximum number of data bytes in one packet at the default code rate
+  const PACKET_BYTES: usize = CodeRate::Half.data_bytes(P::PACKET_BYTES);
+  const ESTIMATED_RTT: Duration = P::ESTIMATED_RTT;
+
+  fn channel_free(&self) -> bool {
+    self.phy.channel_free()
+  }
+
+  fn packet_bytes(&self) -> usize {
+    FecPhy::packet_bytes(self)
+  }
+}
+
+impl<P: PhyLayer> PacketSender<PhyPacket, P::SendErr> for FecPhy<P> {
+  fn send(&mut self, packet: PhyPacket) -> Result<(), P::SendErr> {
+    assert!(packet.len() <= self.packet_bytes());
+    let packet = self.encode(&packet);
+    self.phy.send(packet)
+  }
+}
+
+impl<P: PhyLayer> PacketReceiver<PhyPacket, P::RecvErr> for FecPhy<P> {
+  fn recv(&mut self) -> Result<PhyPacket, P::RecvErr> {
+    self.recv_coded(None).map(|soft| self.decode(&soft))
+  }
+
+  fn recv_timeout(&mut self, timeout: Duration) -> Result<PhyPacket, P::RecvErr> {
+    self.recv_coded(Some(timeout)).map(|soft| self.decode(&soft))
+  }
+
+  fn recv_peek(&mut self) -> bool {
+    self.phy.recv_peek()
+  }
+}
@@ -0,0 +1,197 @@
+use super::{AtomicPHY, AtomicPhyRecvErr};
+use crate::phy_packet::params::{positive, ParamError};
+pub use crate::phy_packet::PhyPacket;
+pub use crate::traits::{PacketReceiver, PacketSender};
+use reed_solomon_erasure::{galois_8::Field, ReedSolomon};
+use std::time::Duration;
+
+/// number of bytes ahead of the data of a block for its length
+const LENGTH_BYTES: usize = 2;
+
+/// Parameters of [`RsPhy`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RsParams {
+  /// number of packets carrying the data of a block
+  pub data_packets: usize,
+  /// number of parity packets of a block, the number of packets a block can lose
+  pub parity_packets: usize,
+}
+
+impl RsParams {
+  /// 10 data packets protected by 4 parity packets
+  pub const DEFAULT: Self = Self {
+    data_packets: 10,
+    parity_packets: 4,
+  };
+
+  /// number of packets of a block
+  pub const fn packets(&self) -> usize {
+    self.data_packets + self.parity_packets
+  }
+
+  pub fn validate(&self) -> Result<(), ParamError> {
+    positive("data packets", self.data_packets)?;
+    positive("parity packets", self.parity_packets)?;
+    if self.packets() > 256 {
+      return Err(ParamError::Invalid("packets per block", "must be at most 256"));
+    }
+    Ok(())
+  }
+}
+
+impl Default for RsParams {
+  fn default() -> Self {
+    Self::DEFAULT
+  }
+}
+
+/// Reed-Solomon receive error type
+#[derive(Debug, PartialEq, Eq)]
+pub enum RsPhyRecvErr {
+  /// no packet of a block received before timeout
+  NoBlock,
+  /// more packets of the block lost than parity packets, with the number of packets received
+  Unrecoverable(usize),
+}
+
+/// A block layer over [`AtomicPHY`] for one-way bulk transfer without acknowledgement:
+/// the data of a block is split into packets protected by Reed-Solomon parity packets,
+/// the block is recovered from any [`RsParams::data_packets`] of its packets.
+///
+/// The sequence numbers of [`AtomicPHY`] tell the position of a packet in the stream of packets sent,
+/// the packets lost or corrupted are erased.
+/// Up to [`crate::helper::SEQ_MOD`]` - 1` packets lost in a row are counted,
+/// a longer run of losses shifts the positions of the packets after it.
+/// The blocks follow each other: the receiver starts with the link, before the first block is sent.
+pub struct RsPhy {
+  phy: AtomicPHY,
+  params: RsParams,
+  rs: ReedSolomon<Field>,
+  // the position of the next packet expected in the stream of packets sent
+  next: usize,
+  // the first packet received of the next block, kept for the next block received
+  pending: Option<(usize, PhyPacket)>,
+}
+
+impl RsPhy {
+  /// protect the blocks with the default parameters
+  pub fn new(phy: AtomicPHY) -> Self {
+    Self::with_params(phy, &RsParams::DEFAULT).expect("invalid default Reed-Solomon parameters")
+  }
+
+  pub fn with_params(phy: AtomicPHY, params: &RsParams) -> Result<Self, ParamError> {
+    params.validate()?;
+    let block_bytes = params.data_packets * phy.packet_bytes();
+    if block_bytes - LENGTH_BYTES > u16::MAX as usize {
+      return Err(ParamError::Invalid(
+        "data packets",
+        "a block can not be longer than its length allows",
+      ));
+    }
+    Ok(Self {
+      phy,
+      params: *params,
+      rs: ReedSolomon::new(params.data_packets, params.parity_packets).unwrap(),
+      next: 0,
+      pending: None,
+    })
+  }
+
+  pub fn params(&self) -> &RsParams {
+    &self.params
+  }
+
+  /// maximum number of data bytes in a block
+  pub fn block_bytes(&self) -> usize {
+    self.params.data_packets * self.phy.packet_bytes() - LENGTH_BYTES
+  }
+
+  /// The packets of a block of at most [`RsPhy::block_bytes`] bytes,
+  /// the data packets then the parity packets, all of [`AtomicPHY::packet_bytes`] bytes.
+  pub fn encode(&self, block: &[u8]) -> Vec<PhyPacket> {
+    assert!(block.len() <= self.block_bytes());
+    let packet_bytes = self.phy.packet_bytes();
+    let mut data = (block.len() as u16).to_le_bytes().to_vec();
+    data.extend(block);
+    data.resize(self.params.data_packets * packet_bytes, 0);
+
+    let mut packets: Vec<_> = data.chunks_exact(packet_bytes).map(PhyPacket::from).collect();
+    packets.resize(self.params.packets(), vec![0; packet_bytes]);
+    self.rs.encode(&mut packets).unwrap();
+    packets
+  }
+
+  /// The block of the packets received, `None` for the packets lost, see [`RsPhy::encode`].
+  /// The packets of an unexpected length are lost too.
+  pub fn decode(&self, mut packets: Vec<Option<PhyPacket>>) -> Result<Vec<u8>, RsPhyRecvErr> {
+    assert_eq!(packets.len(), self.params.packets());
+    let packet_bytes = self.phy.packet_bytes();
+    packets
+      .iter_mut()
+      .filter(|packet| packet.as_ref().is_some_and(|packet| packet.len() != packet_bytes))
+      .for_each(|packet| *packet = None);
+    let received = packets.iter().flatten().count();
+    if received < self.params.data_packets {
+      return Err(RsPhyRecvErr::Unrecoverable(received));
+    }
+
+    self.rs.reconstruct_data(&mut packets).unwrap();
+    let data: Vec<_> = packets
+      .into_iter()
+      .take(self.params.data_packets)
+      .flat_map(Option::unwrap)
+      .collect();
+    let len = u16::from_le_bytes([data[0], data[1]]) as usize;
+    match data.get(LENGTH_BYTES..LENGTH_BYTES + len) {
+      Some(block) => Ok(block.to_vec()),
+      None => Err(RsPhyRecvErr::Unrecoverable(received)),
+    }
+  }
+
+  /// send the packets of a block of at most [`RsPhy::block_bytes`] bytes, return until send finished or error
+  #[allow(clippy::result_unit_err)]
+  pub fn send_block(&mut self, block: &[u8]) -> Result<(), ()> {
+    for packet in self.encode(block) {
+      self.phy.send(packet)?;
+    }
+    Ok(())
+  }
+
+  /// Receive the packets of a block, each packet waited for until timeout.
+  /// The block ends with its last packet, the first packet of a later block or a timeout,
+  /// it is recovered if enough of its packets are received.
+  pub fn recv_block(&mut self, timeout: Duration) -> Result<Vec<u8>, RsPhyRecvErr> {
+    let packets = self.params.packets();
+    let mut block = None;
+    let mut received = vec![None; packets];
+    while let Some((position, packet)) = self.pending.take().or_else(|| self.recv_packet(timeout)) {
+      if *block.get_or_insert(position / packets) != position / packets {
+        self.pending = Some((position, packet));
+        break;
+      }
+      received[position % packets] = Some(packet);
+      if position % packets == packets - 1 {
+        break;
+      }
+    }
+    match block {
+      Some(_) => self.decode(received),
+      None => Err(RsPhyRecvErr::NoBlock),
+    }
+  }
+
+  // receive a packet with its position in the stream, the packets lost or corrupted are skipped
+  fn recv_packet(&mut self, timeout: Duration) -> Option<(usize, PhyPacket)> {
+    loop {
+      match self.phy.recv_timeout(timeout) {
+        Ok((packet, skips)) => {
+          let position = self.next + skips as usize;
+          self.next = position + 1;
+          return Some((position, packet));
+        }
+        Err(AtomicPhyRecvErr::NoPacketAvaiable) => return None,
+        Err(AtomicPhyRecvErr::Lost | AtomicPhyRecvErr::Corrupt) => continue,
+      }
+    }
+  }
+}
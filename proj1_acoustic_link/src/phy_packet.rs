@@ -1,21 +1,30 @@
 /// Frame: a audio signal.
-/// A frame consists of a preamble section and a payload section.
+/// A frame consists of a preamble section, a length header section and a payload section.
 ///
 /// Packet: a chunk of bytes.
 /// A packet is sent/received with no data integrity guarantee
 
 /// define the types and traits related to physisc layer packet
 pub mod traits;
-pub use traits::{FrameDetector, FramePayload, Modem, PhyPacket, PreambleGen};
+pub use traits::{FrameDetector, FramePayload, Modem, PhyPacket, PreambleGen, SoftBits};
 
 /// implementors of [`FrameDetector`]: audio stream framing algorithms.
 pub mod frame_detect;
+/// the length header of a frame, see [`header::LengthHeader`].
+pub mod header;
 /// implementors of [`Modem`]: modulation methods.
 pub mod modem;
+/// runtime parameters of the modems and the preambles, see [`params::ParamError`].
+pub mod params;
 /// implementors of [`PreambleGen`]: preamble sequences.
 pub mod preambles;
+/// hard decisions and link quality from the soft bits of [`Modem::demodulate_soft`], see [`SoftBits`].
+pub mod soft;
 
 /// Bytes packet (packet type [`PhyPacket`]) transmission on audio PCM sample streams.  
 /// A sender can be built on a stream with a [`PreambleGen`] and a [`Modem`].  
 /// A receiver can be built on a stream with a [`PreambleGen`], a [`FrameDetector`] and a [`Modem`].  
 pub mod txrx;
+
+/// Run the receive chain offline on a recorded session, e.g. a wav capture, see [`replay::replay`].
+pub mod replay;
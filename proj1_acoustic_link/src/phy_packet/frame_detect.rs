@@ -1,4 +1,4 @@
-use super::{FrameDetector, FramePayload, PreambleGen};
+use super::{header::LengthHeader, FrameDetector, FramePayload, Modem, PreambleGen};
 use crate::helper::dot_product;
 use crate::traits::{Sample, FP};
 use std::collections::VecDeque;
@@ -64,35 +64,137 @@ impl PreambleWindow {
     self.square_sum = FP::ZERO;
   }
 
+  // average power of the samples in the window
+  pub fn power(&self) -> f32 {
+    if self.buffer.is_empty() {
+      0.0
+    } else {
+      self.square_sum.into_f32().max(0.0) / self.buffer.len() as f32
+    }
+  }
+
   pub fn enough_power(&self) -> bool {
     self.smooth_power > FP::ZERO && self.square_sum > FP::ZERO
   }
 }
 
+// The noise floor of the correlation for the CFAR threshold: the mean square of the correlation output
+// lagging behind by a guard interval, so that the rising correlation of a preamble does not raise its own threshold.
+struct NoiseFloor {
+  // squared correlations within the guard interval, not yet in the mean
+  guard: VecDeque<f32>,
+  guard_len: usize,
+  mean_square: f32,
+  // number of correlations in the mean
+  count: usize,
+}
+
+impl NoiseFloor {
+  pub fn with_guard(guard_len: usize) -> NoiseFloor {
+    Self {
+      guard: VecDeque::with_capacity(guard_len + 1),
+      guard_len,
+      mean_square: 0.0,
+      count: 0,
+    }
+  }
+
+  // Push a correlation, the one leaving the guard interval is averaged over the last `window` correlations:
+  // a running mean at first, an exponential moving average once there are enough of them.
+  pub fn update(&mut self, corr: f32, window: usize) {
+    self.guard.push_back(corr * corr);
+    if self.guard.len() > self.guard_len {
+      let square = self.guard.pop_front().unwrap();
+      self.count += 1;
+      self.mean_square += (square - self.mean_square) / self.count.min(window) as f32;
+    }
+  }
+
+  // the root mean square of the correlation, `None` until it is averaged over at least `training` correlations
+  pub fn rms(&self, training: usize) -> Option<f32> {
+    (self.count >= training).then(|| self.mean_square.sqrt())
+  }
+
+  // Drop the correlations within the guard interval, which belong to the preamble just detected.
+  // The noise floor is kept for the next frame.
+  pub fn clear_guard(&mut self) {
+    self.guard.clear();
+  }
+}
+
+// Decode the payload length in bytes and in samples from the samples of the length header,
+// `None` if the header is invalid.
+type HeaderDecoder = Box<dyn FnMut(&[FP]) -> Option<(usize, usize)> + Send>;
+
 struct Payload {
   buffer: Vec<FP>,
+  // number of samples of the header and the payload, unknown before the header is decoded
   size: usize,
+  // number of samples of the length header and its decoder, `None` for fixed length payloads
+  header: Option<(usize, HeaderDecoder)>,
+  // number of bytes decoded from the last header, kept until the next header
+  bytes: Option<usize>,
 }
 
 impl Payload {
-  pub fn new<const SIZE: usize>() -> Self {
+  pub fn new(size: usize) -> Self {
+    Self {
+      buffer: Vec::with_capacity(size),
+      size,
+      header: None,
+      bytes: None,
+    }
+  }
+  pub fn with_header(header_len: usize, decoder: HeaderDecoder) -> Self {
     Self {
-      buffer: Vec::with_capacity(SIZE),
-      size: SIZE,
+      buffer: Vec::with_capacity(header_len),
+      size: usize::MAX,
+      header: Some((header_len, decoder)),
+      bytes: None,
     }
   }
-  pub fn extend(&mut self, samples: Vec<FP>) {
-    assert!(samples.len() <= self.size);
-    self.buffer.extend(samples.iter())
+  pub fn header_len(&self) -> usize {
+    self.header.as_ref().map_or(0, |(len, _)| *len)
   }
-  pub fn update(&mut self, sample: FP) -> Option<Vec<FP>> {
+  // Push the samples into the payload, the samples after a complete payload are dropped.
+  pub fn extend(&mut self, samples: Vec<FP>) -> Result<Option<FramePayload>, ()> {
+    for sample in samples {
+      if let Some(frame) = self.update(sample)? {
+        return Ok(Some(frame));
+      }
+    }
+    Ok(None)
+  }
+  // Push one sample into the payload, return the payload without the header once complete,
+  // or an error if the header is invalid and the frame is dropped.
+  pub fn update(&mut self, sample: FP) -> Result<Option<FramePayload>, ()> {
     self.buffer.push(sample);
+    if let Some((header_len, decoder)) = self.header.as_mut() {
+      if self.buffer.len() == *header_len {
+        match decoder(&self.buffer) {
+          Some((bytes, len)) => {
+            self.size = *header_len + len;
+            self.bytes = Some(bytes);
+          }
+          None => {
+            self.clear();
+            return Err(());
+          }
+        }
+      }
+    }
     if self.buffer.len() == self.size {
-      let frame = self.buffer.clone();
-      self.buffer.clear();
-      Some(frame)
+      let frame = self.buffer[self.header_len()..].to_vec();
+      self.clear();
+      Ok(Some(frame))
     } else {
-      None
+      Ok(None)
+    }
+  }
+  fn clear(&mut self) {
+    self.buffer.clear();
+    if self.header.is_some() {
+      self.size = usize::MAX;
     }
   }
 }
@@ -104,50 +206,104 @@ pub struct CorrelationFraming<PG: PreambleGen> {
   frame_payload: Payload,
   corr_peak_index: usize,
   corr_peak_value: FP,
+  // correlation peak of the last frame detected
+  last_peak: FP,
+  // number of samples received after the end of the last frame detected
+  last_lag: usize,
+  noise_floor: NoiseFloor,
+  // number of preambles detected, and of those dropped for an invalid header
+  detections: usize,
+  false_alarms: usize,
 }
 
 impl<PG> CorrelationFraming<PG>
 where
   PG: PreambleGen,
 {
-  /// For preamble detection,
-  /// the ratio of the correlation power to the received signal average power
-  /// must be greater than this threshold.
-  pub const CORR_MIN: f32 = 3.0;
+  /// The lowest threshold of the correlation to detect a preamble,
+  /// used alone until the noise floor is known, and on a silent channel.
+  /// The correlation peak of a preamble is its energy times the gain of the channel:
+  /// the frames of a quiet link peak well below 3.0, the fixed threshold the CFAR threshold replaces.
+  pub const CORR_MIN: f32 = 0.2;
   /// The falling edge can be detected about 70 samples after the correlation peak appears.
   pub const AFTER_PEAK_SAMPLES: usize = 200;
-  /// The minimum power required in detect window when detecting preamble
-  /// Used to skip irrelevant patterns with extremely low power.
-  /// **TODO** Currently only enabled for wired connection, find a proper value for wireless case
-  /// **TODO** Find a proper value and proper volume configuration
-  pub const POWER_MIN: f32 = if cfg!(feature = "wired") { 0.05 / 64.0 } else { 0.0 };
+  /// For preamble detection, the correlation must be greater than this many times
+  /// the root mean square of the correlation in the noise before the preamble (constant false alarm rate).
+  pub const CFAR_SCALE: f32 = 6.0;
+  /// Number of correlations the noise floor is averaged over, about 40ms at 48kHz.
+  /// The noise floor follows the changes of volume and background noise at this pace.
+  pub const CFAR_WINDOW: usize = 2048;
 
-  /// Create the CorrelationFraming detector with given preamble generator and layload length
+  /// Create the CorrelationFraming detector with given preamble generator and payload length
+  /// for frames without a length header.
   pub fn new<const PAYLOAD_LEN: usize>(preamble_gen: PG) -> CorrelationFraming<PG>
   where
     PG: PreambleGen,
   {
+    Self::with_payload_len(preamble_gen, PAYLOAD_LEN)
+  }
+
+  /// Create the CorrelationFraming detector with given preamble generator and payload length.
+  /// Same as [`CorrelationFraming::new`], but the payload length is given at runtime.
+  pub fn with_payload_len(preamble_gen: PG, payload_len: usize) -> CorrelationFraming<PG> {
     Self {
-      detect_window: PreambleWindow::with_capacity(PG::PREAMBLE_LEN + Self::AFTER_PEAK_SAMPLES),
+      detect_window: PreambleWindow::with_capacity(preamble_gen.preamble_len() + Self::AFTER_PEAK_SAMPLES),
       state: FramingState::DetectPreambleStart,
-      frame_payload: Payload::new::<PAYLOAD_LEN>(),
+      frame_payload: Payload::new(payload_len),
       corr_peak_index: 0,
       corr_peak_value: FP::ZERO,
+      last_peak: FP::ZERO,
+      last_lag: 0,
+      noise_floor: NoiseFloor::with_guard(preamble_gen.preamble_len()),
+      detections: 0,
+      false_alarms: 0,
       preamble_gen,
     }
   }
 
+  /// Create the CorrelationFraming detector for the frames sent by [`super::txrx::PhySender`]:
+  /// the length header is demodulated by the given modem once received,
+  /// the frame is dropped if the length is invalid.
+  /// The payloads found do not include the header.
+  pub fn with_modem<MM>(preamble_gen: PG, mut modem: MM) -> CorrelationFraming<PG>
+  where
+    MM: Modem + Send + 'static,
+  {
+    let max_len = modem.bytes_per_packet();
+    assert!(max_len <= LengthHeader::MAX_LEN);
+    let header_len = modem.samples_for(LengthHeader::BYTES);
+    let decoder = Box::new(move |samples: &[FP]| {
+      let len = LengthHeader::decode(&modem.demodulate(samples)[..LengthHeader::BYTES]);
+      (len <= max_len).then(|| (len, modem.samples_for(len)))
+    });
+    Self {
+      frame_payload: Payload::with_header(header_len, decoder),
+      ..Self::with_payload_len(preamble_gen, 0)
+    }
+  }
+
+  /// the preamble to detect
+  pub fn preamble(&self) -> &PG {
+    &self.preamble_gen
+  }
+
+  /// number of samples of the length header after the preamble, 0 if there is no header
+  pub fn header_samples(&self) -> usize {
+    self.frame_payload.header_len()
+  }
+
   // detect the start of preamble. After the checks passed, it will enter detect rising edge state.
   fn detect_preamble_start(&mut self, sample: FP) -> FramingState {
     self.detect_window.update(sample);
     // Not enough samples
-    if self.detect_window.len() < PG::PREAMBLE_LEN || !self.detect_window.enough_power() {
+    if self.detect_window.len() < self.preamble_gen.preamble_len() || !self.detect_window.enough_power() {
       return FramingState::DetectPreambleStart;
     }
-    // To check if is the beginning of the preamble
+    // To check if is the beginning of the preamble, against the noise floor before this sample
     let corr = self.corr();
-    let pwr = self.detect_window.smooth_power;
-    if corr.into_f32() > Self::CORR_MIN && pwr.into_f32() > Self::POWER_MIN {
+    let threshold = self.threshold();
+    self.noise_floor.update(corr.into_f32(), Self::CFAR_WINDOW);
+    if corr.into_f32() > threshold {
       self.corr_peak_value = corr;
       self.corr_peak_index = self.detect_window.tail_index;
       FramingState::DetectRisingEdge
@@ -158,7 +314,8 @@ where
   }
 
   // Try to find the peak of the preamble. If it starts falling, it will enter detect falling edge.
-  fn detect_rising_edge(&mut self, sample: FP) -> FramingState {
+  // The payload may be complete with the samples after the peak if it is short enough.
+  fn detect_rising_edge(&mut self, sample: FP) -> (FramingState, Option<FramePayload>) {
     self.detect_window.update(sample);
     // Get the data.
     let corr = self.corr();
@@ -166,37 +323,81 @@ where
       self.corr_peak_value = corr;
       self.corr_peak_index = self.detect_window.tail_index;
     } else if self.detect_window.tail_index - self.corr_peak_index > Self::AFTER_PEAK_SAMPLES {
-      self
-        .frame_payload
-        .extend(self.detect_window.extract_samples_to_end(self.corr_peak_index));
+      self.last_peak = self.corr_peak_value;
+      let samples = self.detect_window.extract_samples_to_end(self.corr_peak_index);
+      let received = samples.len();
       self.reset_detection_state();
-      return FramingState::WaitPayload;
+      self.detections += 1;
+      return match self.frame_payload.extend(samples) {
+        Ok(None) => (FramingState::WaitPayload, None),
+        Ok(Some(payload)) => {
+          // a short frame is complete before the detector is sure about the peak
+          self.last_lag = received - self.frame_payload.header_len() - payload.len();
+          (FramingState::DetectPreambleStart, Some(payload))
+        }
+        Err(()) => {
+          self.false_alarms += 1;
+          (FramingState::DetectPreambleStart, None)
+        }
+      };
     }
-    FramingState::DetectRisingEdge
+    (FramingState::DetectRisingEdge, None)
   }
 
   fn wait_payload(&mut self, sample: FP) -> (FramingState, Option<FramePayload>) {
     // append the newly found sample into the payload window
-    if let Some(payload) = self.frame_payload.update(sample) {
-      (FramingState::DetectPreambleStart, Some(payload))
-    } else {
-      (FramingState::WaitPayload, None)
+    match self.frame_payload.update(sample) {
+      // wait until we have enough example
+      Ok(None) => (FramingState::WaitPayload, None),
+      Ok(payload) => {
+        self.last_lag = 0;
+        (FramingState::DetectPreambleStart, payload)
+      }
+      // invalid header, drop the frame
+      Err(()) => {
+        self.false_alarms += 1;
+        (FramingState::DetectPreambleStart, None)
+      }
     }
-    // wait until we have enough example
   }
 
   // Calculate the relations between preamble and samples. Return the ratio of correlation power to the received signal average power and cosine similarity.
   fn corr(&self) -> FP {
     let r = self.detect_window.len();
     dot_product(
-      self.detect_window.buffer.range(r - PG::PREAMBLE_LEN..),
+      self.detect_window.buffer.range(r - self.preamble_gen.preamble_len()..),
       self.preamble_gen.iter(),
     )
   }
 
+  /// The correlation peak of the preamble of the last frame detected,
+  /// e.g. to tell how strong the frame is above the detection threshold [`Self::threshold`].
+  pub fn last_peak(&self) -> FP {
+    self.last_peak
+  }
+
+  /// The current threshold of the correlation to detect a preamble:
+  /// [`Self::CFAR_SCALE`] times the root mean square of the correlation in the noise, at least [`Self::CORR_MIN`].
+  /// Until the correlation is averaged over a preamble length, the noise is taken as white
+  /// and the root mean square of its correlation is estimated from the power of the samples in the window.
+  pub fn threshold(&self) -> f32 {
+    let rms = self
+      .noise_floor
+      .rms(self.preamble_gen.preamble_len())
+      .unwrap_or_else(|| self.preamble_gen.norm().into_f32() * self.detect_window.power().sqrt());
+    (Self::CFAR_SCALE * rms).max(Self::CORR_MIN)
+  }
+
+  /// Number of samples received after the end of the last frame detected,
+  /// non-zero if the frame is so short that it ends before the falling edge of the correlation.
+  pub fn last_lag(&self) -> usize {
+    self.last_lag
+  }
+
   // reset the fields relatated to preable detection.
   fn reset_detection_state(&mut self) {
     self.detect_window.clear();
+    self.noise_floor.clear_guard();
     self.corr_peak_value = FP::ZERO;
     self.corr_peak_index = 0;
   }
@@ -218,12 +419,32 @@ where
         None
       }
       FramingState::DetectRisingEdge => {
-        self.state = self.detect_rising_edge(sample);
-        None
+        let (state, frame) = self.detect_rising_edge(sample);
+        self.state = state;
+        frame
       }
     }
   }
+
+  fn payload_bytes(&self) -> Option<usize> {
+    self.frame_payload.bytes
+  }
+
+  fn detections(&self) -> usize {
+    self.detections
+  }
+
+  /// The preambles detected whose length header is invalid.
+  /// Always 0 for the frames without a header, whose false alarms are only caught by the layers above.
+  fn false_alarms(&self) -> usize {
+    self.false_alarms
+  }
 }
 
+#[cfg(not(feature = "nofloat"))]
+mod schmidl_cox;
+#[cfg(not(feature = "nofloat"))]
+pub use schmidl_cox::SchmidlCox;
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,358 @@
+use super::{FrameDetector, FramePayload, LengthHeader, Modem, Payload, PreambleGen};
+use crate::traits::{Sample, FP};
+use std::collections::VecDeque;
+use std::f32::consts::TAU;
+use std::sync::{
+  atomic::{AtomicU32, Ordering},
+  Arc,
+};
+type Complex = rustfft::num_complex::Complex32;
+type Complex64 = rustfft::num_complex::Complex64;
+
+/// number of taps on each side of the centre of the Hilbert transformer
+const HILBERT_HALF: usize = 15;
+
+// State of SchmidlCox
+enum FramingState {
+  DetectPreamble,
+  // the timing metric is above the threshold: the best start of the preamble so far,
+  // with its timing metric and its delayed autocorrelation
+  DetectPeak { start: usize, metric: f64, corr: Complex64 },
+  WaitPayload,
+}
+
+/// Frame detection on a training symbol of two identical halves (Schmidl & Cox),
+/// e.g. [`crate::phy_packet::preambles::RepeatedHalves`], for [`crate::phy_packet::modem::OFDM`]:
+///
+/// - the received samples are turned into an analytic signal by a Hilbert transformer
+/// - the delayed autocorrelation `P(d)` of the two halves of `L` samples from `d`, and the energy `E(d)` of the
+///   `2L` samples, are updated in constant time per sample, without correlating against the preamble
+/// - the timing metric `|P(d)|² / E(d)²` is within `[0, 1]` whatever the amplitude of the signal,
+///   a frame starts around its peak above [`SchmidlCox::METRIC_MIN`]
+/// - the peak is a plateau in noise: the timing is refined once per frame around the peak
+///   by the correlation with the preamble, which also rejects the periodic signals like tones (as Minn does)
+/// - a frequency offset turns the second half against the first by `2π·offset·L`:
+///   the phase of `P(d)` is a coarse estimate of the offset within `±1 / (2L)` cycles per sample,
+///   see [`FrameDetector::frequency_offset`]
+pub struct SchmidlCox<PG: PreambleGen> {
+  state: FramingState,
+  preamble_gen: PG,
+  // number of samples of a half of the preamble
+  half: usize,
+  // the analytic preamble, to refine the timing
+  reference: Vec<Complex>,
+  // the recent samples, the last one has the index `received - 1`
+  samples: VecDeque<f32>,
+  // the recent analytic samples, the last one has the index `received - 1 - HILBERT_HALF`
+  analytic: VecDeque<Complex>,
+  // number of samples received, counting the zeros ahead of the stream for the Hilbert transformer
+  received: usize,
+  corr: Complex64,
+  energy: f64,
+  frame_payload: Payload,
+  // timing metric of the last frame detected
+  last_metric: f32,
+  // frequency offset of the last frame detected, shared with the header decoder
+  last_offset: Option<f32>,
+  header_offset: Option<Arc<AtomicU32>>,
+  // number of peaks of the timing metric, and of those not matching the preamble or with an invalid header
+  detections: usize,
+  false_alarms: usize,
+}
+
+impl<PG> SchmidlCox<PG>
+where
+  PG: PreambleGen,
+{
+  /// The timing metric must be above this threshold to start looking for a frame.
+  /// The metric of a preamble at an SNR `s` is about `(s / (s + 1))²`, about `1 / L` in noise.
+  pub const METRIC_MIN: f32 = 0.6;
+  /// The normalized correlation with the preamble at the refined timing must be above this threshold.
+  pub const MATCH_MIN: f32 = 0.5;
+  /// The average power of the samples of the preamble must be above this, to skip silence.
+  pub const POWER_MIN: f32 = 1e-8;
+
+  /// Create the detector with the given preamble of two identical halves and payload length
+  /// for frames without a length header.
+  pub fn new<const PAYLOAD_LEN: usize>(preamble_gen: PG) -> SchmidlCox<PG> {
+    Self::with_payload_len(preamble_gen, PAYLOAD_LEN)
+  }
+
+  /// Create the detector with the given preamble of two identical halves and payload length.
+  /// Same as [`SchmidlCox::new`], but the payload length is given at runtime.
+  pub fn with_payload_len(preamble_gen: PG, payload_len: usize) -> SchmidlCox<PG> {
+    let samples = preamble_gen.samples();
+    let half = samples.len() / 2;
+    assert!(
+      half > 0 && samples[..half] == samples[half..],
+      "the preamble halves must be identical"
+    );
+    let samples: Vec<_> = samples.iter().map(|x| x.into_f32()).collect();
+    Self {
+      state: FramingState::DetectPreamble,
+      half,
+      reference: analytic_signal(&samples),
+      // the detector needs the preamble and half of it on each side for the timing,
+      // the transformer the samples on each side of its centre
+      samples: VecDeque::from(vec![0.0; HILBERT_HALF]),
+      analytic: VecDeque::with_capacity(4 * half),
+      received: HILBERT_HALF,
+      corr: Complex64::default(),
+      energy: 0.0,
+      frame_payload: Payload::new(payload_len),
+      last_metric: 0.0,
+      last_offset: None,
+      header_offset: None,
+      detections: 0,
+      false_alarms: 0,
+      preamble_gen,
+    }
+  }
+
+  /// Create the detector for the frames sent by [`crate::phy_packet::txrx::PhySender`]:
+  /// the length header is demodulated by the given modem once received,
+  /// with the frequency offset of the frame corrected, see [`Modem::set_frequency_offset`].
+  /// The frame is dropped if the length is invalid, the payloads found do not include the header.
+  pub fn with_modem<MM>(preamble_gen: PG, mut modem: MM) -> SchmidlCox<PG>
+  where
+    MM: Modem + Send + 'static,
+  {
+    let max_len = modem.bytes_per_packet();
+    assert!(max_len <= LengthHeader::MAX_LEN);
+    let header_len = modem.samples_for(LengthHeader::BYTES);
+    let offset = Arc::new(AtomicU32::new(0));
+    let header_offset = offset.clone();
+    let decoder = Box::new(move |samples: &[FP]| {
+      modem.set_frequency_offset(f32::from_bits(header_offset.load(Ordering::Relaxed)));
+      let len = LengthHeader::decode(&modem.demodulate(samples)[..LengthHeader::BYTES]);
+      (len <= max_len).then(|| (len, modem.samples_for(len)))
+    });
+    Self {
+      frame_payload: Payload::with_header(header_len, decoder),
+      header_offset: Some(offset),
+      ..Self::with_payload_len(preamble_gen, 0)
+    }
+  }
+
+  /// the preamble to detect
+  pub fn preamble(&self) -> &PG {
+    &self.preamble_gen
+  }
+
+  /// number of samples of the length header after the preamble, 0 if there is no header
+  pub fn header_samples(&self) -> usize {
+    self.frame_payload.header_len()
+  }
+
+  /// The timing metric of the preamble of the last frame detected, within `[0, 1]`,
+  /// e.g. to tell how clean the frame is above the detection threshold [`Self::METRIC_MIN`].
+  pub fn last_metric(&self) -> f32 {
+    self.last_metric
+  }
+
+  // push a sample into the analytic signal, update the autocorrelation and the energy of the last 2L samples
+  fn update(&mut self, sample: f32) {
+    self.samples.push_back(sample);
+    self.received += 1;
+    let centre = self.samples.len() - 1 - HILBERT_HALF;
+    if centre >= HILBERT_HALF {
+      let x = |i: usize| self.samples[i];
+      let imag = (1..=HILBERT_HALF)
+        .step_by(2)
+        .map(|k| hilbert_tap(k) * (x(centre - k) - x(centre + k)))
+        .sum();
+      self.push_analytic(Complex::new(x(centre), imag));
+    }
+    if self.samples.len() > 5 * self.half + 2 * HILBERT_HALF {
+      self.samples.pop_front();
+    }
+  }
+
+  fn push_analytic(&mut self, a: Complex) {
+    let l = self.half;
+    self.analytic.push_back(a);
+    let len = self.analytic.len();
+    let a = Complex64::new(a.re as f64, a.im as f64);
+    let at = |i: usize| {
+      let x = self.analytic[i];
+      Complex64::new(x.re as f64, x.im as f64)
+    };
+    self.energy += a.norm_sqr();
+    if len > l {
+      self.corr += at(len - 1 - l).conj() * a;
+    }
+    if len > 2 * l {
+      let old = at(len - 1 - 2 * l);
+      self.corr -= old.conj() * at(len - 1 - l);
+      self.energy -= old.norm_sqr();
+    }
+    if len > 4 * l {
+      self.analytic.pop_front();
+    }
+  }
+
+  // index of the first analytic sample in the buffer
+  fn analytic_start(&self) -> usize {
+    self.received - HILBERT_HALF - self.analytic.len()
+  }
+
+  // the timing metric of the preamble starting 2L samples before the last analytic sample,
+  // `None` before 2L samples or in silence
+  fn metric(&self) -> Option<f64> {
+    let window = 2 * self.half;
+    if self.analytic.len() <= window || self.energy < (Self::POWER_MIN as f64) * window as f64 {
+      return None;
+    }
+    Some(self.corr.norm_sqr() / (self.energy / 2.0).powi(2))
+  }
+
+  // The normalized correlation of the analytic preamble with the analytic samples from `start`,
+  // the frequency offset removed, `None` if the samples are no longer buffered.
+  fn matched(&self, start: usize, offset: f32) -> Option<f32> {
+    let first = start.checked_sub(self.analytic_start())?;
+    let window = self.analytic.range(first..).take(self.reference.len());
+    let (mut corr, mut energy) = (Complex::default(), 0.0);
+    for (i, (a, p)) in window.zip(&self.reference).enumerate() {
+      let turns = (offset * i as f32).rem_euclid(1.0);
+      corr += p.conj() * a * Complex::from_polar(1.0, -TAU * turns);
+      energy += a.norm_sqr();
+    }
+    let norm = self.reference.iter().map(Complex::norm_sqr).sum::<f32>() * energy;
+    (norm > 0.0).then(|| corr.norm() / norm.sqrt())
+  }
+
+  // detect the metric above the threshold, then wait for its peak
+  fn detect_preamble(&mut self) -> Option<FramePayload> {
+    let metric = self.metric()?;
+    let start = self.received - HILBERT_HALF - 2 * self.half;
+    match self.state {
+      FramingState::DetectPreamble if metric >= Self::METRIC_MIN as f64 => {
+        self.state = FramingState::DetectPeak {
+          start,
+          metric,
+          corr: self.corr,
+        };
+        None
+      }
+      FramingState::DetectPeak { metric: best, .. } if metric > best => {
+        self.state = FramingState::DetectPeak {
+          start,
+          metric,
+          corr: self.corr,
+        };
+        None
+      }
+      // no better start for half a half: the peak is found
+      FramingState::DetectPeak {
+        start: best,
+        metric,
+        corr,
+      } if start > best + self.half / 2 => {
+        self.state = FramingState::DetectPreamble;
+        self.on_peak(best, metric, corr)
+      }
+      _ => None,
+    }
+  }
+
+  // refine the timing around the peak, then start the payload
+  fn on_peak(&mut self, peak: usize, metric: f64, corr: Complex64) -> Option<FramePayload> {
+    self.detections += 1;
+    let offset = (corr.arg() / (std::f64::consts::TAU * self.half as f64)) as f32;
+    let range = peak.saturating_sub(self.half / 2)..=peak + self.half / 2;
+    let best = range
+      .filter_map(|start| Some((start, self.matched(start, offset)?)))
+      .max_by(|(_, a), (_, b)| a.total_cmp(b));
+    let Some((start, _)) = best.filter(|(_, matched)| *matched >= Self::MATCH_MIN) else {
+      self.false_alarms += 1;
+      return None;
+    };
+    self.last_metric = metric as f32;
+    self.last_offset = Some(offset);
+    if let Some(header_offset) = &self.header_offset {
+      header_offset.store(offset.to_bits(), Ordering::Relaxed);
+    }
+
+    let payload_start = start + 2 * self.half;
+    let first = payload_start.saturating_sub(self.received - self.samples.len());
+    let samples = self.samples.range(first..).map(|&x| FP::from_f32(x)).collect();
+    match self.frame_payload.extend(samples) {
+      Ok(None) => {
+        self.state = FramingState::WaitPayload;
+        None
+      }
+      Ok(payload) => payload,
+      Err(()) => {
+        self.false_alarms += 1;
+        None
+      }
+    }
+  }
+}
+
+impl<PG> FrameDetector for SchmidlCox<PG>
+where
+  PG: PreambleGen,
+{
+  fn on_sample(&mut self, sample: FP) -> Option<FramePayload> {
+    self.update(sample.into_f32());
+    match self.state {
+      FramingState::WaitPayload => match self.frame_payload.update(sample) {
+        Ok(None) => None,
+        Ok(payload) => {
+          self.state = FramingState::DetectPreamble;
+          payload
+        }
+        Err(()) => {
+          self.state = FramingState::DetectPreamble;
+          self.false_alarms += 1;
+          None
+        }
+      },
+      _ => self.detect_preamble(),
+    }
+  }
+
+  fn payload_bytes(&self) -> Option<usize> {
+    self.frame_payload.bytes
+  }
+
+  fn frequency_offset(&self) -> Option<f32> {
+    self.last_offset
+  }
+
+  fn detections(&self) -> usize {
+    self.detections
+  }
+
+  /// The peaks of the timing metric not matching the preamble, e.g. a tone, and the frames with an invalid header.
+  fn false_alarms(&self) -> usize {
+    self.false_alarms
+  }
+}
+
+/// the tap `k` (odd) of the Hilbert transformer, windowed by a Hann window
+fn hilbert_tap(k: usize) -> f32 {
+  let window = 0.5 + 0.5 * (std::f32::consts::PI * k as f32 / (HILBERT_HALF + 1) as f32).cos();
+  2.0 / (std::f32::consts::PI * k as f32) * window
+}
+
+/// the analytic signal of a sequence of samples, the samples around the sequence are zero
+fn analytic_signal(samples: &[f32]) -> Vec<Complex> {
+  let x = |i: isize| {
+    usize::try_from(i)
+      .ok()
+      .and_then(|i| samples.get(i))
+      .copied()
+      .unwrap_or_default()
+  };
+  (0..samples.len() as isize)
+    .map(|t| {
+      let imag = (1..=HILBERT_HALF as isize)
+        .step_by(2)
+        .map(|k| hilbert_tap(k as usize) * (x(t - k) - x(t + k)))
+        .sum();
+      Complex::new(x(t), imag)
+    })
+    .collect()
+}
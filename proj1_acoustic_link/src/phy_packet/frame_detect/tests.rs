@@ -106,7 +106,7 @@ mod wired_tests {
     const PL_LEN: usize = 500;
     const FILE_NAME: &str = "corr_detect_air.wav";
     // Write samples to the file.
-    let mut hound_out_stream = HoundOutStream::create(FILE_NAME);
+    let mut hound_out_stream = HoundOutStream::create(FILE_NAME).unwrap();
     hound_out_stream
       .write(ChirpUpDown::generate().samples().as_slice())
       .unwrap();
@@ -114,9 +114,9 @@ mod wired_tests {
     hound_out_stream.write(payload.as_slice()).unwrap();
     hound_out_stream.finalize();
     // Read the samples and try to detect samples again.
-    let mut hound_in_stream = HoundInStream::open(FILE_NAME);
-    let mut buf = [FP::ZERO; ChirpUpDown::N + 2 * PL_LEN];
+    let mut hound_in_stream = HoundInStream::open(FILE_NAME).unwrap();
     let mut detector = CorrelationFraming::new::<PL_LEN>(ChirpUpDown::new());
+    let mut buf = vec![FP::ZERO; detector.preamble().preamble_len() + 2 * PL_LEN];
     let mut s = 0;
     hound_in_stream.read_exact(&mut buf).unwrap();
     for x in buf.iter() {
@@ -138,7 +138,7 @@ mod wired_tests {
     const FILE_NAME: &str = "corr_detect_air_multi.wav";
     const PACKET_NUM: usize = 20;
     // Write samples to the file.
-    let mut hound_out_stream = HoundOutStream::create(FILE_NAME);
+    let mut hound_out_stream = HoundOutStream::create(FILE_NAME).unwrap();
     let payload: Vec<FP> = (0..PL_LEN).map(|x| FP::from_f32(x as f32 * 0.33).sin()).collect();
 
     for _ in 0..PACKET_NUM {
@@ -149,9 +149,9 @@ mod wired_tests {
     }
     hound_out_stream.finalize();
     // Read the samples and try to detect samples again.
-    let mut hound_in_stream = HoundInStream::open(FILE_NAME);
-    let mut buf = [FP::ZERO; ChirpUpDown::N + PL_LEN];
+    let mut hound_in_stream = HoundInStream::open(FILE_NAME).unwrap();
     let mut detector = CorrelationFraming::new::<PL_LEN>(ChirpUpDown::new());
+    let mut buf = vec![FP::ZERO; detector.preamble().preamble_len() + PL_LEN];
     let mut s = 0;
     while let Ok(num) = hound_in_stream.read(&mut buf) {
       if num == 0 {
@@ -174,7 +174,7 @@ mod wired_tests {
 
 #[cfg(not(feature = "wired"))]
 mod wireless_tests {
-  use crate::phy_packet::{frame_detect::CorrelationFraming, preambles::ChirpUpDown, FrameDetector};
+  use crate::phy_packet::{frame_detect::CorrelationFraming, preambles::ChirpUpDown, FrameDetector, PreambleGen};
   use crate::sample_stream::CpalInStream;
   use crate::traits::InStream;
   use crate::traits::{Sample, FP};
@@ -182,10 +182,10 @@ mod wireless_tests {
   #[test]
   fn corr_detect_air_recv() {
     const PL_LEN: usize = 500;
-    let mut buf = [FP::ZERO; ChirpUpDown::N + PL_LEN];
+    let mut detector = CorrelationFraming::new::<PL_LEN>(ChirpUpDown::new());
+    let mut buf = vec![FP::ZERO; detector.preamble().preamble_len() + PL_LEN];
     let mut cpal_in_stream = CpalInStream::default();
     let mut s = 0;
-    let mut detector = CorrelationFraming::new::<PL_LEN>(ChirpUpDown::new());
 
     'outer: loop {
       cpal_in_stream.read_exact(&mut buf).unwrap();
@@ -205,10 +205,10 @@ mod wireless_tests {
 
     const PL_LEN: usize = 2000;
     const PACKET_NUM: usize = 20;
-    let mut buf = [FP::ZERO; ChirpUpDown::N + PL_LEN];
+    let mut detector = CorrelationFraming::new::<PL_LEN>(ChirpUpDown::new());
+    let mut buf = vec![FP::ZERO; detector.preamble().preamble_len() + PL_LEN];
     let mut cpal_in_stream = CpalInStream::default();
     let mut s = 0;
-    let mut detector = CorrelationFraming::new::<PL_LEN>(ChirpUpDown::new());
 
     let mut last_time = std::time::Instant::now();
     'outer: loop {
@@ -226,3 +226,272 @@ mod wireless_tests {
     assert_eq!(s, PACKET_NUM);
   }
 }
+
+#[cfg(not(feature = "nofloat"))]
+mod schmidl_cox_tests {
+  use crate::phy_packet::{
+    frame_detect::SchmidlCox, header::LengthHeader, modem::OFDM, preambles::RepeatedHalves, FrameDetector, Modem,
+    PreambleGen,
+  };
+  use crate::traits::{Sample, FP};
+  use rand::{distributions::Standard, Rng};
+  use rustfft::{num_complex::Complex32, FftPlanner};
+
+  /// shift the frequencies of a real signal by `offset` cycles per sample, through its analytic signal
+  fn shift_frequency(x: &[f32], offset: f32) -> Vec<f32> {
+    let n = x.len();
+    let mut buf: Vec<_> = x.iter().map(|&x| Complex32::new(x, 0.0)).collect();
+    let mut planner = FftPlanner::new();
+    planner.plan_fft_forward(n).process(&mut buf);
+    buf.iter_mut().skip(1).take((n - 1) / 2).for_each(|x| *x *= 2.0);
+    buf.iter_mut().skip(n / 2 + 1).for_each(|x| *x = Complex32::default());
+    planner.plan_fft_inverse(n).process(&mut buf);
+    buf
+      .iter()
+      .enumerate()
+      .map(|(t, x)| (x * Complex32::from_polar(1.0, std::f32::consts::TAU * offset * t as f32)).re / n as f32)
+      .collect()
+  }
+
+  fn noise(len: usize, std: f32) -> Vec<f32> {
+    let mut rng = rand::thread_rng();
+    (0..len).map(|_| rng.gen_range(-std..std) * 3f32.sqrt()).collect()
+  }
+
+  /// the samples of a preamble followed by random samples, amid noise
+  fn frame(payload_len: usize, gain: f32, snr_db: f32) -> (Vec<f32>, usize) {
+    const LEAD: usize = 300;
+    let preamble: Vec<_> = RepeatedHalves::new().iter().map(|x| x.into_f32()).collect();
+    let mut signal = vec![0.0; LEAD];
+    signal.extend(&preamble);
+    signal.extend(noise(payload_len, 0.3));
+    signal.extend(vec![0.0; LEAD]);
+    let noise_std = 0.3 * 10f32.powf(-snr_db / 20.0);
+    let samples = signal
+      .iter()
+      .zip(noise(signal.len(), noise_std))
+      .map(|(x, n)| gain * (x + n))
+      .collect();
+    (samples, LEAD + preamble.len())
+  }
+
+  /// the payload is found at the exact timing at any amplitude
+  #[test]
+  fn schmidl_cox_timing() {
+    const PL_LEN: usize = 500;
+    for gain in [0.001, 0.1, 1.0] {
+      let (samples, start) = frame(PL_LEN, gain, 20.0);
+      let mut detector = SchmidlCox::new::<PL_LEN>(RepeatedHalves::new());
+      let payloads: Vec<_> = samples
+        .iter()
+        .filter_map(|&x| detector.on_sample(FP::from_f32(x)))
+        .collect();
+      assert_eq!(payloads.len(), 1, "gain {}", gain);
+      let expected: Vec<_> = samples[start..start + PL_LEN]
+        .iter()
+        .map(|&x| FP::from_f32(x))
+        .collect();
+      assert_eq!(payloads[0], expected, "gain {}", gain);
+      assert!(detector.last_metric() > 0.8);
+      assert_eq!(
+        (detector.detections(), detector.false_alarms()),
+        (1, 0),
+        "gain {}",
+        gain
+      );
+    }
+  }
+
+  /// a tone is periodic at any delay, but does not match the preamble
+  #[test]
+  fn schmidl_cox_rejects_tone() {
+    let mut detector = SchmidlCox::new::<500>(RepeatedHalves::new());
+    for t in 0..20000 {
+      let x = (0.2 * t as f32).sin() * 0.5;
+      assert_eq!(detector.on_sample(FP::from_f32(x)), None);
+    }
+    // the peaks of the timing metric are all false alarms
+    assert!(detector.detections() > 0);
+    assert_eq!(detector.false_alarms(), detector.detections());
+  }
+
+  /// the frequency offset of a frame is estimated from its preamble,
+  /// the OFDM modem decodes the header and the payload once it is corrected
+  #[test]
+  fn schmidl_cox_frequency_offset() {
+    const OFFSET: f32 = 0.003;
+    let mut modem = OFDM::new();
+    let bytes: Vec<u8> = rand::thread_rng()
+      .sample_iter(Standard)
+      .take(modem.bytes_per_packet())
+      .collect();
+    let mut sent = vec![0.0; 300];
+    sent.extend(RepeatedHalves::new().iter().map(|x| x.into_f32()));
+    sent.extend(
+      modem
+        .modulate(&LengthHeader::encode(bytes.len()))
+        .iter()
+        .map(|x| x.into_f32()),
+    );
+    sent.extend(modem.modulate(&bytes).iter().map(|x| x.into_f32()));
+    sent.extend(vec![0.0; 300]);
+    let received = shift_frequency(&sent, OFFSET);
+
+    let mut detector = SchmidlCox::with_modem(RepeatedHalves::new(), OFDM::new());
+    let payloads: Vec<_> = received
+      .iter()
+      .filter_map(|&x| detector.on_sample(FP::from_f32(x)))
+      .collect();
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(detector.payload_bytes(), Some(bytes.len()));
+    let offset = detector.frequency_offset().unwrap();
+    assert!((offset - OFFSET).abs() < 1e-4, "{}", offset);
+
+    modem.set_frequency_offset(offset);
+    assert_eq!(modem.demodulate(&payloads[0]), bytes);
+  }
+}
+
+mod cfar_tests {
+  use crate::phy_packet::{
+    frame_detect::CorrelationFraming, header::LengthHeader, modem::PSK, preambles::ChirpUpDown, FrameDetector, Modem,
+    PreambleGen,
+  };
+  use crate::sample_stream::{ChannelSimConfig, ChannelSimStream};
+  use crate::traits::{InStream, OutStream, Sample, FP};
+  use rand::{distributions::Standard, Rng};
+
+  const LEAD: usize = 5000;
+
+  /// pass the samples through the channel, followed by some silence to flush it
+  fn through(channel: ChannelSimConfig, samples: &[FP]) -> Vec<FP> {
+    let mut chan = ChannelSimStream::new(channel);
+    chan.write_exact(samples).unwrap();
+    let mut received = vec![FP::ZERO; 2 * samples.len()];
+    let n = chan.read(&mut received).unwrap();
+    received.truncate(n);
+    received
+  }
+
+  /// the noise alone: the threshold follows the noise floor, no preamble is detected at any noise level
+  #[test]
+  fn cfar_false_alarms() {
+    for (seed, noise_std) in [(1, 0.001), (2, 0.05), (3, 0.5)] {
+      let channel = ChannelSimConfig {
+        seed,
+        noise_std,
+        ..Default::default()
+      };
+      let received = through(channel, &vec![FP::ZERO; 200000]);
+      let mut detector = CorrelationFraming::with_modem(ChirpUpDown::new(), PSK::default());
+      received
+        .iter()
+        .for_each(|&x| assert_eq!(detector.on_sample(x), None, "noise {}", noise_std));
+      assert_eq!(detector.detections(), 0, "noise {}", noise_std);
+      assert_eq!(detector.false_alarms(), 0, "noise {}", noise_std);
+
+      let norm = ChirpUpDown::new().norm().into_f32();
+      let expected = (CorrelationFraming::<ChirpUpDown>::CFAR_SCALE * noise_std * norm)
+        .max(CorrelationFraming::<ChirpUpDown>::CORR_MIN);
+      let threshold = detector.threshold();
+      assert!(
+        (threshold - expected).abs() < 0.2 * expected,
+        "noise {}: threshold {} expected {}",
+        noise_std,
+        threshold,
+        expected
+      );
+    }
+  }
+
+  /// the frames are found at any volume, with as many detections as frames sent
+  #[test]
+  fn cfar_detections() {
+    const FRAMES: usize = 10;
+    let mut modem = PSK::default();
+    let preamble = ChirpUpDown::new();
+    for (seed, gain, noise_std) in [(4, 0.02, 0.002), (5, 0.3, 0.03), (6, 1.0, 0.1)] {
+      let mut sent = vec![FP::ZERO; LEAD];
+      let packets: Vec<Vec<u8>> = (0..FRAMES)
+        .map(|_| {
+          let packet: Vec<u8> = rand::thread_rng()
+            .sample_iter(Standard)
+            .take(modem.bytes_per_packet())
+            .collect();
+          sent.extend(preamble.iter());
+          sent.extend(modem.modulate(&LengthHeader::encode(packet.len())));
+          sent.extend(modem.modulate(&packet));
+          sent.extend([FP::ZERO; 1000]);
+          packet
+        })
+        .collect();
+      let channel = ChannelSimConfig {
+        seed,
+        gain,
+        taps: vec![(12, 0.1 * gain)],
+        noise_std,
+        ..Default::default()
+      };
+      let received = through(channel, &sent);
+
+      let mut detector = CorrelationFraming::with_modem(ChirpUpDown::new(), PSK::default());
+      let frames: Vec<_> = received.iter().filter_map(|&x| detector.on_sample(x)).collect();
+      let decoded: Vec<_> = frames.iter().map(|frame| modem.demodulate(frame)).collect();
+      assert_eq!(decoded, packets, "gain {}", gain);
+      assert_eq!(detector.detections(), FRAMES, "gain {}", gain);
+      assert_eq!(detector.false_alarms(), 0, "gain {}", gain);
+    }
+  }
+
+  /// a preamble followed by an invalid length header is detected, but counted as a false alarm
+  #[test]
+  fn cfar_invalid_header() {
+    let mut modem = PSK::default();
+    let mut sent = vec![FP::ZERO; LEAD];
+    sent.extend(ChirpUpDown::new().iter());
+    sent.extend(modem.modulate(&LengthHeader::encode(modem.bytes_per_packet() + 1)));
+    sent.extend(vec![FP::ZERO; modem.samples_for(modem.bytes_per_packet())]);
+
+    let mut detector = CorrelationFraming::with_modem(ChirpUpDown::new(), PSK::default());
+    assert!(sent.iter().all(|&x| detector.on_sample(x).is_none()));
+    assert_eq!(detector.detections(), 1);
+    assert_eq!(detector.false_alarms(), 1);
+  }
+
+  /// A frame received so quietly that its correlation peak is far below 3.0, the former fixed threshold,
+  /// is found above the floor [`CorrelationFraming::CORR_MIN`] on a quiet channel.
+  #[test]
+  fn cfar_quiet_frame() {
+    let mut modem = PSK::default();
+    let preamble = ChirpUpDown::new();
+    // the correlation peak of the preamble received with a gain `g` is `g` times its energy
+    let gain = 1.0 / preamble.norm().into_f32().powi(2);
+    let packet: Vec<u8> = rand::thread_rng()
+      .sample_iter(Standard)
+      .take(modem.bytes_per_packet())
+      .collect();
+    let mut sent = vec![FP::ZERO; LEAD];
+    sent.extend(preamble.iter());
+    sent.extend(modem.modulate(&LengthHeader::encode(packet.len())));
+    sent.extend(modem.modulate(&packet));
+    sent.extend([FP::ZERO; 1000]);
+    let channel = ChannelSimConfig {
+      seed: 7,
+      gain,
+      noise_std: 0.01 * gain,
+      ..Default::default()
+    };
+    let received = through(channel, &sent);
+
+    let mut detector = CorrelationFraming::with_modem(ChirpUpDown::new(), PSK::default());
+    let frames: Vec<_> = received.iter().filter_map(|&x| detector.on_sample(x)).collect();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(modem.demodulate(&frames[0]), packet);
+    let peak = detector.last_peak().into_f32();
+    assert!(
+      peak > CorrelationFraming::<ChirpUpDown>::CORR_MIN && peak < 3.0,
+      "peak {}",
+      peak
+    );
+  }
+}
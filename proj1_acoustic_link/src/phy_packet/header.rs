@@ -0,0 +1,26 @@
+/// The length header sent right after the preamble, so that a frame only carries the bytes of its packet.
+///
+/// The header is modulated by the same modem as the packet.
+/// The length byte `L` is repeated as `[L, !L, L]` and decoded by a bitwise majority vote,
+/// the inverted copy avoids a long run of the same bit which could be the case of a short length.
+pub struct LengthHeader;
+
+impl LengthHeader {
+  /// number of bytes in the header
+  pub const BYTES: usize = 3;
+  /// the maximum length of a packet the header can carry
+  pub const MAX_LEN: usize = u8::MAX as usize;
+
+  pub fn encode(len: usize) -> [u8; Self::BYTES] {
+    assert!(len <= Self::MAX_LEN);
+    let len = len as u8;
+    [len, !len, len]
+  }
+
+  /// Decode the packet length from the header bytes, any single corrupted copy of each bit is corrected.
+  pub fn decode(bytes: &[u8]) -> usize {
+    assert_eq!(bytes.len(), Self::BYTES);
+    let (a, b, c) = (bytes[0], !bytes[1], bytes[2]);
+    ((a & b) | (b & c) | (a & c)) as usize
+  }
+}
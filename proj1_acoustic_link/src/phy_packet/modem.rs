@@ -1,15 +1,21 @@
 #[cfg(not(feature = "nofloat"))]
 mod ofdm;
 #[cfg(not(feature = "nofloat"))]
-pub use ofdm::OFDM;
+pub use ofdm::{BitLoading, Constellation, OfdmParams, Subcarriers, OFDM};
 
 mod psk;
 // pub use psk::PSK;
 mod proj2_modem;
-pub use proj2_modem::PSK;
+pub use proj2_modem::{PskParams, PSK};
 
 mod line_code;
-pub use line_code::LineCode;
+pub use line_code::{LineCode, LineCodeParams};
+
+mod fsk;
+pub use fsk::{FskParams, FSK};
+
+mod dpsk;
+pub use dpsk::{DpskParams, DPSK};
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,261 @@
+use crate::{
+  helper::{bytes_to_bits, dot_product},
+  phy_packet::{
+    params::{below_nyquist, packet_bytes, ParamError},
+    soft::hard_decision,
+    traits::{FramePayload, Modem, PhyPacket, SoftBits},
+  },
+  traits::{Sample, FP},
+  AudioConfig,
+};
+
+/// share of the timing error measured on a symbol corrected at once
+const TIMING_GAIN: f32 = 0.2;
+/// number of symbols at the head of the payload used to find the initial timing
+const ACQUISITION_SYMBOLS: usize = 16;
+
+/// Parameters of [`DPSK`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DpskParams {
+  /// frequency of the carrier wave
+  pub carrier_freq: f32,
+  /// number of bits carried by a phase transition, 1 (DBPSK) or 2 (DQPSK)
+  pub bits_per_symbol: usize,
+  /// number of samples of a symbol, at least one period of the carrier
+  pub samples_per_symbol: usize,
+  /// number of symbols in one packet after the reference symbol, whole bytes
+  pub symbols_per_packet: usize,
+}
+
+impl DpskParams {
+  /// DQPSK on a 6kHz carrier with 0.5ms symbols at 48kHz, 4kbps
+  pub const DEFAULT: Self = Self {
+    carrier_freq: 6000.0,
+    bits_per_symbol: 2,
+    samples_per_symbol: 24,
+    symbols_per_packet: 320,
+  };
+
+  /// maximum number of bytes in one packet
+  pub const fn bytes_per_packet(&self) -> usize {
+    self.bits_per_symbol * self.symbols_per_packet / 8
+  }
+
+  /// the timing of the symbols is searched up to a quarter of a symbol off
+  pub const fn max_shift(&self) -> usize {
+    self.samples_per_symbol / 4
+  }
+
+  pub fn validate(&self, config: &AudioConfig) -> Result<(), ParamError> {
+    if ![1, 2].contains(&self.bits_per_symbol) {
+      return Err(ParamError::Invalid("bits per symbol", "must be 1 or 2"));
+    }
+    below_nyquist("carrier frequency", self.carrier_freq, config)?;
+    if self.samples_per_symbol < 4 {
+      return Err(ParamError::Invalid(
+        "samples per symbol",
+        "must be at least 4 for the timing recovery",
+      ));
+    }
+    if self.carrier_freq * (self.samples_per_symbol as f32) < config.sample_rate as f32 {
+      return Err(ParamError::Invalid(
+        "samples per symbol",
+        "must cover at least one period of the carrier",
+      ));
+    }
+    if !(self.bits_per_symbol * self.symbols_per_packet).is_multiple_of(8) {
+      return Err(ParamError::Invalid("symbols per packet", "must be whole bytes"));
+    }
+    packet_bytes(self.bytes_per_packet())
+  }
+}
+
+impl Default for DpskParams {
+  fn default() -> Self {
+    Self::DEFAULT
+  }
+}
+
+/// Differential PSK: the data is carried by the phase turn from a symbol to the next,
+/// by half a turn for DBPSK, by a Gray-coded quarter turn for DQPSK.
+/// A reference symbol is sent ahead of the data.
+///
+/// The receiver compares the phases of two successive symbols, so it needs no phase reference:
+/// reading the payload a few samples early or late turns the phases of all the symbols alike.
+/// Only the symbol boundaries matter, which a timing recovery loop finds and follows.
+pub struct DPSK {
+  params: DpskParams,
+  // sine and cosine of the carrier over a whole packet, timed from the start of the payload
+  sin: Vec<FP>,
+  cos: Vec<FP>,
+}
+
+impl DPSK {
+  pub fn new() -> Self {
+    Self::with_config(&AudioConfig::default())
+  }
+
+  /// build the carrier waves with the default parameters at the sampling rate of the audio config
+  pub fn with_config(config: &AudioConfig) -> Self {
+    Self::with_params(&DpskParams::DEFAULT, config).expect("invalid default DPSK for the audio config")
+  }
+
+  /// build the carrier waves with the given parameters at the sampling rate of the audio config
+  pub fn with_params(params: &DpskParams, config: &AudioConfig) -> Result<Self, ParamError> {
+    use std::f32::consts::TAU;
+    params.validate(config)?;
+    let dt = 1.0 / config.sample_rate as f32;
+    let len = (params.symbols_per_packet + 1) * params.samples_per_symbol;
+    // the phase is computed in f64 to stay accurate along the packet
+    let phase = |i: usize| TAU as f64 * params.carrier_freq as f64 * dt as f64 * i as f64;
+    Ok(Self {
+      params: *params,
+      sin: (0..len).map(|i| FP::from_f32(phase(i).sin() as f32)).collect(),
+      cos: (0..len).map(|i| FP::from_f32(phase(i).cos() as f32)).collect(),
+    })
+  }
+
+  pub fn params(&self) -> &DpskParams {
+    &self.params
+  }
+
+  /// The soft bits of a phase turn `(re, im)`, its projections on the boundaries of the decisions:
+  /// the real axis for DBPSK, the diagonals for the Gray-coded quarter turns of DQPSK
+  fn soft_bits(&self, (re, im): (f32, f32), soft: &mut SoftBits) {
+    if self.params.bits_per_symbol == 1 {
+      soft.push(re);
+    } else {
+      // no turn and a quarter turn have the first bit at 0, no turn and three quarters the second one
+      soft.extend([re + im, re - im]);
+    }
+  }
+
+  /// the phasor of the carrier in the symbol starting at `start`,
+  /// the samples out of the payload are taken as silence
+  fn phasor(&self, samples: &[FP], start: isize) -> (f32, f32) {
+    let first = start.max(0) as usize;
+    let last = ((start + self.params.samples_per_symbol as isize).max(0) as usize).min(samples.len());
+    if first >= last {
+      return (0.0, 0.0);
+    }
+    let symbol = &samples[first..last];
+    let re = dot_product(symbol.iter(), self.sin[first..last].iter());
+    let im = dot_product(symbol.iter(), self.cos[first..last].iter());
+    (re.into_f32(), im.into_f32())
+  }
+
+  /// the shift of the symbol boundaries within [`DpskParams::max_shift`] samples
+  /// with the most energy on the first symbols, where the phase turns between them
+  fn acquire(&self, samples: &[FP], symbols: usize) -> isize {
+    let max_shift = self.params.max_shift() as isize;
+    let sps = self.params.samples_per_symbol as isize;
+    let energy = |shift: isize| -> f32 {
+      (0..symbols.min(ACQUISITION_SYMBOLS) as isize + 1)
+        .map(|k| magnitude(self.phasor(samples, shift + k * sps)))
+        .sum()
+    };
+    (-max_shift..=max_shift)
+      .map(|shift| (shift, energy(shift)))
+      .fold((0, f32::MIN), |best, x| if x.1 > best.1 { x } else { best })
+      .0
+  }
+}
+
+/// number of quarter turns of the phase encoding the bits of a symbol
+fn quarter_turns(bits: &[u8]) -> usize {
+  match bits {
+    [0] => 0,
+    [1] => 2,
+    [0, 0] => 0,
+    [0, 1] => 1,
+    [1, 1] => 2,
+    [1, 0] => 3,
+    _ => panic!("invalid bits of a symbol"),
+  }
+}
+
+fn magnitude((re, im): (f32, f32)) -> f32 {
+  (re * re + im * im).sqrt()
+}
+
+impl Default for DPSK {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Modem for DPSK {
+  fn bytes_per_packet(&self) -> usize {
+    self.params.bytes_per_packet()
+  }
+
+  /// `8 / bits_per_symbol` symbols per byte, one more for the reference symbol
+  fn samples_for(&self, bytes: usize) -> usize {
+    (bytes * 8 / self.params.bits_per_symbol + 1) * self.params.samples_per_symbol
+  }
+
+  fn modulate(&mut self, bytes: &[u8]) -> FramePayload {
+    assert!(bytes.len() <= self.bytes_per_packet());
+    let sps = self.params.samples_per_symbol;
+    let mut turns = 0;
+    let phases = std::iter::once(0).chain(
+      bytes_to_bits(bytes)
+        .chunks_exact(self.params.bits_per_symbol)
+        .map(|bits| {
+          turns = (turns + quarter_turns(bits)) % 4;
+          turns
+        })
+        .collect::<Vec<_>>(),
+    );
+    let (sin, cos) = (&self.sin, &self.cos);
+    // sin(ωt + kπ/2) is one of sin(ωt), cos(ωt), -sin(ωt), -cos(ωt)
+    phases
+      .enumerate()
+      .flat_map(|(k, turns)| {
+        (k * sps..(k + 1) * sps).map(move |i| match turns {
+          0 => sin[i],
+          1 => cos[i],
+          2 => -sin[i],
+          _ => -cos[i],
+        })
+      })
+      .collect()
+  }
+
+  fn demodulate(&mut self, samples: &[FP]) -> PhyPacket {
+    hard_decision(&self.demodulate_soft(samples))
+  }
+
+  /// The timing is first searched on the head of the payload, see [`DpskParams::max_shift`],
+  /// then an early-late loop follows it symbol by symbol:
+  /// a symbol read early has less energy than the same symbol read a sample later, and the other way round.
+  fn demodulate_soft(&mut self, samples: &[FP]) -> SoftBits {
+    let sps = self.params.samples_per_symbol;
+    assert!(samples.len() >= sps && samples.len().is_multiple_of(sps));
+    let symbols = samples.len() / sps - 1;
+    assert_eq!(symbols % (8 / self.params.bits_per_symbol), 0);
+
+    let mut shift = self.acquire(samples, symbols) as f32;
+    let mut previous = self.phasor(samples, shift as isize);
+    let mut soft = Vec::with_capacity(symbols * self.params.bits_per_symbol);
+    for k in 1..=symbols {
+      let start = (k * sps) as isize + shift.round() as isize;
+      let prompt = self.phasor(samples, start);
+      let early = magnitude(self.phasor(samples, start - 1));
+      let late = magnitude(self.phasor(samples, start + 1));
+      // the phase turn from the previous symbol: prompt times the conjugate of previous
+      let turn = (
+        prompt.0 * previous.0 + prompt.1 * previous.1,
+        prompt.1 * previous.0 - prompt.0 * previous.1,
+      );
+      self.soft_bits(turn, &mut soft);
+
+      let power = magnitude(prompt);
+      if power > 0.0 {
+        shift += TIMING_GAIN * (late - early) / power;
+      }
+      previous = prompt;
+    }
+    soft
+  }
+}
@@ -0,0 +1,214 @@
+use crate::{
+  helper::{bits_to_bytes, bytes_to_bits, dot_product},
+  phy_packet::{
+    params::{below_nyquist, packet_bytes, positive, ParamError},
+    traits::{FramePayload, Modem, PhyPacket, SoftBits},
+  },
+  traits::{Sample, FP},
+  AudioConfig,
+};
+
+/// Parameters of [`FSK`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FskParams {
+  /// frequency of the lowest tone
+  pub base_freq: f32,
+  /// frequency between two neighbour tones,
+  /// at least the inverse of the symbol duration so that the tones are orthogonal
+  pub tone_spacing: f32,
+  /// number of bits carried by a symbol, 1 (2-FSK), 2 (4-FSK) or 4 (16-FSK),
+  /// there are `2^bits_per_symbol` tones
+  pub bits_per_symbol: usize,
+  /// number of samples of a symbol, a longer symbol is more robust to echoes and noise
+  pub samples_per_symbol: usize,
+  /// number of symbols in one packet, whole bytes
+  pub symbols_per_packet: usize,
+}
+
+impl FskParams {
+  /// 4-FSK at 3kHz, 3.5kHz, 4kHz and 4.5kHz with 2ms symbols at 48kHz, 1kbps
+  pub const DEFAULT: Self = Self {
+    base_freq: 3000.0,
+    tone_spacing: 500.0,
+    bits_per_symbol: 2,
+    samples_per_symbol: 96,
+    symbols_per_packet: 160,
+  };
+
+  /// number of tones
+  pub const fn tones(&self) -> usize {
+    1 << self.bits_per_symbol
+  }
+
+  /// maximum number of bytes in one packet
+  pub const fn bytes_per_packet(&self) -> usize {
+    self.bits_per_symbol * self.symbols_per_packet / 8
+  }
+
+  pub fn validate(&self, config: &AudioConfig) -> Result<(), ParamError> {
+    if ![1, 2, 4].contains(&self.bits_per_symbol) {
+      return Err(ParamError::Invalid("bits per symbol", "must be 1, 2 or 4"));
+    }
+    positive("samples per symbol", self.samples_per_symbol)?;
+    below_nyquist("lowest tone", self.base_freq, config)?;
+    let highest = self.base_freq + self.tone_spacing * (self.tones() - 1) as f32;
+    below_nyquist("highest tone", highest, config)?;
+    let symbol_duration = self.samples_per_symbol as f32 / config.sample_rate as f32;
+    if self.tone_spacing * symbol_duration < 1.0 {
+      return Err(ParamError::Invalid(
+        "tone spacing",
+        "must be at least the inverse of the symbol duration",
+      ));
+    }
+    if !(self.bits_per_symbol * self.symbols_per_packet).is_multiple_of(8) {
+      return Err(ParamError::Invalid("symbols per packet", "must be whole bytes"));
+    }
+    packet_bytes(self.bytes_per_packet())
+  }
+}
+
+impl Default for FskParams {
+  fn default() -> Self {
+    Self::DEFAULT
+  }
+}
+
+/// Non-coherent M-ary FSK: each symbol is one of `2^bits` tones.
+///
+/// The receiver compares the energy of the symbol at each tone, the sum of the squared correlations
+/// with a sine and a cosine, so it needs no phase reference:
+/// the phase shifts of echoes and of a moving sender do not matter, slow but reliable in a noisy room.
+pub struct FSK {
+  params: FskParams,
+  // the symbol of each tone
+  symbols: Vec<Vec<FP>>,
+  // the sine and cosine references of each tone
+  references: Vec<(Vec<FP>, Vec<FP>)>,
+}
+
+impl FSK {
+  pub fn new() -> Self {
+    Self::with_config(&AudioConfig::default())
+  }
+
+  /// build the tones with the default parameters at the sampling rate of the audio config
+  pub fn with_config(config: &AudioConfig) -> Self {
+    Self::with_params(&FskParams::DEFAULT, config).expect("invalid default FSK for the audio config")
+  }
+
+  /// build the tones with the given parameters at the sampling rate of the audio config
+  pub fn with_params(params: &FskParams, config: &AudioConfig) -> Result<Self, ParamError> {
+    use std::f32::consts::{FRAC_PI_2, TAU};
+    params.validate(config)?;
+    let dt = 1.0 / config.sample_rate as f32;
+    let wave = |freq: f32, phase: f32| -> Vec<FP> {
+      (0..params.samples_per_symbol)
+        .map(|i| FP::from_f32((TAU * freq * dt * i as f32 + phase).sin()))
+        .collect()
+    };
+    let freqs: Vec<_> = (0..params.tones())
+      .map(|tone| params.base_freq + params.tone_spacing * tone as f32)
+      .collect();
+
+    Ok(Self {
+      params: *params,
+      symbols: freqs.iter().map(|freq| wave(*freq, 0.0)).collect(),
+      references: freqs
+        .iter()
+        .map(|freq| (wave(*freq, 0.0), wave(*freq, FRAC_PI_2)))
+        .collect(),
+    })
+  }
+
+  pub fn params(&self) -> &FskParams {
+    &self.params
+  }
+
+  /// the energy of the symbol at each tone
+  fn energies(&self, symbol: &[FP]) -> Vec<FP> {
+    self
+      .references
+      .iter()
+      .map(|(sin, cos)| {
+        let i = dot_product(symbol.iter(), sin.iter());
+        let q = dot_product(symbol.iter(), cos.iter());
+        i * i + q * q
+      })
+      .collect()
+  }
+
+  /// the tone of the symbol with the highest energy
+  fn detect(&self, symbol: &[FP]) -> usize {
+    let energies = self.energies(symbol);
+    (0..energies.len())
+      .max_by(|&a, &b| energies[a].into_f32().total_cmp(&energies[b].into_f32()))
+      .unwrap()
+  }
+}
+
+impl Default for FSK {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Modem for FSK {
+  fn bytes_per_packet(&self) -> usize {
+    self.params.bytes_per_packet()
+  }
+
+  /// `8 / bits_per_symbol` symbols per byte
+  fn samples_for(&self, bytes: usize) -> usize {
+    bytes * 8 / self.params.bits_per_symbol * self.params.samples_per_symbol
+  }
+
+  fn modulate(&mut self, bytes: &[u8]) -> FramePayload {
+    assert!(bytes.len() <= self.bytes_per_packet());
+    bytes_to_bits(bytes)
+      .chunks_exact(self.params.bits_per_symbol)
+      .flat_map(|bits| {
+        let tone = bits.iter().fold(0, |acc, bit| (acc << 1) | *bit as usize);
+        self.symbols[tone].iter().copied()
+      })
+      .collect()
+  }
+
+  fn demodulate(&mut self, samples: &[FP]) -> PhyPacket {
+    assert_eq!(samples.len() % self.samples_for(1), 0);
+    let bits_per_symbol = self.params.bits_per_symbol;
+    let bits: Vec<_> = samples
+      .chunks_exact(self.params.samples_per_symbol)
+      .flat_map(|symbol| {
+        let tone = self.detect(symbol);
+        (0..bits_per_symbol).rev().map(move |k| ((tone >> k) & 1) as u8)
+      })
+      .collect();
+    bits_to_bytes(&bits)
+  }
+
+  /// The max-log approximation on the amplitudes of the tones:
+  /// the strongest tone with the bit at `0` against the strongest tone with the bit at `1`
+  fn demodulate_soft(&mut self, samples: &[FP]) -> SoftBits {
+    assert_eq!(samples.len() % self.samples_for(1), 0);
+    let bits_per_symbol = self.params.bits_per_symbol;
+    samples
+      .chunks_exact(self.params.samples_per_symbol)
+      .flat_map(|symbol| {
+        let amplitudes: Vec<_> = self
+          .energies(symbol)
+          .into_iter()
+          .map(|energy| energy.into_f32().sqrt())
+          .collect();
+        (0..bits_per_symbol).rev().map(move |k| {
+          let strongest = |bit: usize| {
+            (0..amplitudes.len())
+              .filter(|tone| (tone >> k) & 1 == bit)
+              .map(|tone| amplitudes[tone])
+              .fold(f32::MIN, f32::max)
+          };
+          strongest(0) - strongest(1)
+        })
+      })
+      .collect()
+  }
+}
@@ -1,56 +1,134 @@
 use crate::{
-  helper::{decode_4b5b, decode_nrzi, encode_4b5b, encode_nrzi},
-  phy_packet::traits::{FramePayload, Modem, PhyPacket},
+  helper::{decode_4b5b, decode_4b5b_soft, decode_nrzi, decode_nrzi_soft, encode_4b5b, encode_nrzi},
+  phy_packet::{
+    params::{packet_bytes, positive, ParamError},
+    traits::{FramePayload, Modem, PhyPacket, SoftBits},
+  },
   traits::{Sample, FP},
+  AudioConfig,
 };
 use bitvec::prelude::*;
 
-/// Line Code: 4b5b + NRZI
-#[derive(Default)]
-pub struct LineCode;
-impl LineCode {
+/// Parameters of [`LineCode`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineCodeParams {
   /// number of samples used to encode a bit
-  pub const SAMPLES_PER_BIT: usize = 3;
-  /// number of bits in one packet
-  pub const BITS_PER_PACKET: usize = 800;
+  pub samples_per_bit: usize,
+  /// number of bits in one packet, whole bytes
+  pub bits_per_packet: usize,
+}
+
+impl LineCodeParams {
+  pub const DEFAULT: Self = Self {
+    samples_per_bit: 3,
+    bits_per_packet: 800,
+  };
+
+  /// maximum number of bytes in one packet
+  pub const fn bytes_per_packet(&self) -> usize {
+    self.bits_per_packet / 8
+  }
+
+  /// line code works on samples directly, the audio config is not checked
+  pub fn validate(&self, _config: &AudioConfig) -> Result<(), ParamError> {
+    positive("samples per bit", self.samples_per_bit)?;
+    if !self.bits_per_packet.is_multiple_of(8) {
+      return Err(ParamError::Invalid("bits per packet", "must be whole bytes"));
+    }
+    packet_bytes(self.bytes_per_packet())
+  }
+}
+
+impl Default for LineCodeParams {
+  fn default() -> Self {
+    Self::DEFAULT
+  }
+}
+
+/// Line Code: 4b5b + NRZI
+pub struct LineCode {
+  params: LineCodeParams,
 }
 
 impl Modem for LineCode {
-  const BYTES_PER_PACKET: usize = LineCode::BITS_PER_PACKET / 8;
-  const SAMPLES_PER_PACKET: usize = (LineCode::SAMPLES_PER_BIT * LineCode::BITS_PER_PACKET) / 4 * 5;
+  fn bytes_per_packet(&self) -> usize {
+    self.params.bytes_per_packet()
+  }
+
+  /// 4b5b: 10 code bits per byte
+  fn samples_for(&self, bytes: usize) -> usize {
+    bytes * 8 / 4 * 5 * self.params.samples_per_bit
+  }
 
   fn modulate(&mut self, bytes: &[u8]) -> FramePayload {
-    assert_eq!(bytes.len(), Self::BYTES_PER_PACKET);
+    assert!(bytes.len() <= self.bytes_per_packet());
     let data_bits = bytes.view_bits::<Msb0>().to_owned();
     let code_bits = encode_nrzi(encode_4b5b(data_bits));
     let samples: Vec<_> = code_bits
       .into_iter()
       .flat_map(|x| {
-        if x {
-          [FP::ONE; Self::SAMPLES_PER_BIT]
-        } else {
-          [-FP::ONE; Self::SAMPLES_PER_BIT]
-        }
+        let level = if x { FP::ONE } else { -FP::ONE };
+        std::iter::repeat_n(level, self.params.samples_per_bit)
       })
       .collect();
     samples
   }
 
   fn demodulate(&mut self, samples: &[FP]) -> PhyPacket {
-    assert_eq!(samples.len(), Self::SAMPLES_PER_PACKET);
+    assert_eq!(samples.len() % self.samples_for(1), 0);
     let code_bits = samples
-      .chunks_exact(Self::SAMPLES_PER_BIT)
+      .chunks_exact(self.params.samples_per_bit)
       .into_iter()
       .map(|x| x.iter().fold(FP::ZERO, |acc, &x| acc + x) > FP::ZERO)
       .collect();
     let data_bits = decode_4b5b(decode_nrzi(code_bits));
-    let mut bytes = vec![0; Self::BYTES_PER_PACKET];
+    let mut bytes = vec![0; samples.len() / self.samples_for(1)];
     bytes.view_bits_mut::<Msb0>().copy_from_bitslice(&data_bits);
     bytes
   }
+
+  /// the sum of the samples of a code bit is its log-likelihood ratio, up to the scale of the noise,
+  /// then NRZI and 4b5b are decoded on the soft bits
+  fn demodulate_soft(&mut self, samples: &[FP]) -> SoftBits {
+    assert_eq!(samples.len() % self.samples_for(1), 0);
+    // a high level is a 1
+    let code_bits: Vec<_> = samples
+      .chunks_exact(self.params.samples_per_bit)
+      .map(|x| -x.iter().fold(FP::ZERO, |acc, &x| acc + x).into_f32())
+      .collect();
+    let data_bits = decode_4b5b_soft(&decode_nrzi_soft(&code_bits));
+    // the most significant bit of a byte is sent first
+    data_bits
+      .chunks_exact(8)
+      .flat_map(|byte| byte.iter().rev().copied())
+      .collect()
+  }
 }
 impl LineCode {
   pub fn new() -> Self {
-    Self
+    Self {
+      params: LineCodeParams::DEFAULT,
+    }
+  }
+
+  /// line code works on samples directly, it does not depend on the audio config
+  pub fn with_config(_config: &AudioConfig) -> Self {
+    Self::new()
+  }
+
+  /// build the line code with the given parameters
+  pub fn with_params(params: &LineCodeParams, config: &AudioConfig) -> Result<Self, ParamError> {
+    params.validate(config)?;
+    Ok(Self { params: *params })
+  }
+
+  pub fn params(&self) -> &LineCodeParams {
+    &self.params
+  }
+}
+
+impl Default for LineCode {
+  fn default() -> Self {
+    Self::new()
   }
 }
@@ -1,129 +1,474 @@
 use crate::{
-  helper::{bits_to_bytes, bytes_to_bits, copy},
-  phy_packet::traits::{FramePayload, Modem, PhyPacket},
+  helper::{bytes_to_bits, copy},
+  phy_packet::{
+    params::{packet_bytes, positive, ParamError},
+    soft::hard_decision,
+    traits::{FramePayload, Modem, PhyPacket, SoftBits},
+  },
   traits::{Sample, FP},
+  AudioConfig,
 };
 use rustfft::{algorithm::Radix4, Fft, FftDirection};
 type Complex = rustfft::num_complex::Complex32;
 
-/// OFDM + PSK modulation.  
+mod constellation;
+pub use constellation::Constellation;
+mod bit_loading;
+pub use bit_loading::BitLoading;
+mod tracking;
+use tracking::{delay_bins, Tracker};
+
+/// A set of subcarriers, i.e. the indices of the FFT bins, below [`Subcarriers::MAX`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Subcarriers(u128);
+
+impl Subcarriers {
+  /// the subcarriers must be below this index
+  pub const MAX: usize = 128;
+
+  /// no subcarrier
+  pub const EMPTY: Self = Self(0);
+
+  /// the `count` subcarriers from `start`
+  pub const fn range(start: usize, count: usize) -> Self {
+    assert!(start + count <= Self::MAX);
+    let mask = if count == Self::MAX {
+      u128::MAX
+    } else {
+      (1 << count) - 1
+    };
+    Self(mask << start)
+  }
+
+  /// the set with the subcarrier `k` added
+  pub const fn with(self, k: usize) -> Self {
+    assert!(k < Self::MAX);
+    Self(self.0 | (1 << k))
+  }
+
+  /// the set with the subcarrier `k` removed, e.g. a subcarrier hit by a narrowband noise
+  pub const fn without(self, k: usize) -> Self {
+    assert!(k < Self::MAX);
+    Self(self.0 & !(1 << k))
+  }
+
+  /// number of subcarriers in the set
+  pub const fn len(&self) -> usize {
+    self.0.count_ones() as usize
+  }
+
+  pub const fn is_empty(&self) -> bool {
+    self.0 == 0
+  }
+
+  pub const fn contains(&self, k: usize) -> bool {
+    k < Self::MAX && (self.0 >> k) & 1 == 1
+  }
+
+  /// the subcarriers in ascending order
+  pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+    (0..Self::MAX).filter(move |k| (self.0 >> k) & 1 == 1)
+  }
+}
+
+/// Parameters of [`OFDM`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OfdmParams {
+  /// size of FFT/IFFT, a power of two
+  pub n: usize,
+  /// samples in the cyclic prefix
+  pub m: usize,
+  /// the subcarriers carrying data, the low frequency ones are too noisy to use
+  pub subcarriers: Subcarriers,
+  /// the mapping of the bits on each subcarrier
+  pub constellation: Constellation,
+  /// the subcarriers carrying known points in every symbol, to track the phase and the timing drift within a frame,
+  /// apart from the subcarriers carrying data
+  pub pilots: Subcarriers,
+  /// number of symbols encoding a full packet, the training symbol excluded
+  pub encode_symbols: usize,
+}
+
+impl OfdmParams {
+  /// QPSK on the 16 subcarriers from the 6th (4.5kHz to 16.5kHz at 48kHz), a pilot on each side
+  pub const DEFAULT: Self = Self {
+    n: 64,
+    m: 8,
+    subcarriers: Subcarriers::range(6, 16),
+    constellation: Constellation::Qpsk,
+    pilots: Subcarriers::EMPTY.with(5).with(22),
+    encode_symbols: 32,
+  };
+
+  /// BPSK on the 26 subcarriers from the 3rd, to measure the SNR of each subcarrier of a link
+  pub const SOUNDING: Self = Self {
+    n: 64,
+    m: 8,
+    subcarriers: Subcarriers::range(3, 26),
+    constellation: Constellation::Bpsk,
+    pilots: Subcarriers::EMPTY.with(2).with(29),
+    encode_symbols: 24,
+  };
+
+  /// BPSK on the 3 subcarriers from the 7th, slow but robust in a noisy channel,
+  /// no pilot: all the power goes to the data, the decided points alone keep track of the drifts
+  pub const ROBUST: Self = Self {
+    n: 64,
+    m: 8,
+    subcarriers: Subcarriers::range(7, 3),
+    constellation: Constellation::Bpsk,
+    pilots: Subcarriers::EMPTY,
+    encode_symbols: 24,
+  };
+
+  /// number of bits in one symbol
+  pub const fn bits_per_symbol(&self) -> usize {
+    self.subcarriers.len() * self.constellation.bits()
+  }
+
+  /// maximum number of bytes in one packet
+  pub const fn bytes_per_packet(&self) -> usize {
+    self.bits_per_symbol() * self.encode_symbols / 8
+  }
+
+  /// number of samples in one symbol
+  pub const fn samples_per_symbol(&self) -> usize {
+    self.n + self.m
+  }
+
+  /// the subcarriers are relative to the sampling rate, the audio config is not checked
+  pub fn validate(&self, _config: &AudioConfig) -> Result<(), ParamError> {
+    self.validate_loading(&BitLoading::uniform(self.subcarriers, self.constellation))
+  }
+
+  /// check the parameters with a bit-loading table in place of the subcarriers and the constellation
+  pub fn validate_loading(&self, loading: &BitLoading) -> Result<(), ParamError> {
+    if !self.n.is_power_of_two() {
+      return Err(ParamError::Invalid("FFT size", "must be a power of two"));
+    }
+    if self.m > self.n {
+      return Err(ParamError::Invalid("cyclic prefix", "must not be longer than a symbol"));
+    }
+    let subcarriers = loading.subcarriers();
+    positive("subcarriers", subcarriers.len())?;
+    let out_of_band = |k| k == 0 || k >= self.n / 2;
+    if subcarriers.iter().any(out_of_band) || self.pilots.iter().any(out_of_band) {
+      return Err(ParamError::Invalid(
+        "subcarriers",
+        "must be above DC and below the Nyquist frequency",
+      ));
+    }
+    if self.pilots.iter().any(|k| subcarriers.contains(k)) {
+      return Err(ParamError::Invalid("pilots", "must not carry data"));
+    }
+    packet_bytes(loading.bits_per_symbol() * self.encode_symbols / 8)
+  }
+}
+
+impl Default for OfdmParams {
+  fn default() -> Self {
+    Self::DEFAULT
+  }
+}
+
+/// OFDM + QAM modulation.  
 ///
 /// A few key points to mention:
 /// - FFT for demodulation
 /// - IFFT for modulation
-/// - use PSK on each frequency channel
+/// - map the bits of each subcarrier onto a [`Constellation`], the same on all subcarriers
+///   or picked per subcarrier by a [`BitLoading`] table
 /// - add guard interval or cyclic prefix between symbols
-/// - the low frequency channels are discarded as they are too noisy
+/// - a training symbol of known points leads the frame,
+///   the channel response of each subcarrier (amplitude and phase) is estimated on it and equalised
+/// - pilots of known points in every symbol, with the decided data points,
+///   track the phase rotation and the timing drift along the frame,
+///   caused by the offsets between the clocks of the sender and the receiver;
+///   the symbols are read at the tracked timing, the fraction of a sample is corrected in the frequency domain
+/// - the SNR of each subcarrier is measured on the decoded frames, see [`OFDM::last_snr`],
+///   or against the points sent if they are known, see [`OFDM::last_error`]
+/// - a carrier frequency offset estimated by the frame detector is removed before the FFT,
+///   see [`Modem::set_frequency_offset`]
 pub struct OFDM {
+  params: OfdmParams,
+  // the constellation of each subcarrier in use
+  loading: BitLoading,
+  // the indices of the pilots
+  pilots: Vec<usize>,
+  // the SNR (dB) of each subcarrier in the last frame decoded
+  snr: Vec<(usize, f32)>,
+  // the equalised points of the data subcarriers in the last frame decoded, symbol after symbol
+  points: Vec<Complex>,
+  // amplitude of the constellation points in the IFFT input, no sample is beyond ±1
+  scale: f32,
+  // the carrier frequency offset (cycles per sample) removed from the frames demodulated
+  frequency_offset: f32,
   fft: Radix4<f32>,
   ifft: Radix4<f32>,
 }
 impl OFDM {
-  /// number of bits in one symbol
-  pub const BITS_PER_SYMBOL: usize = 3;
-  /// number of samples in one symbol
-  pub const SAMPLES_PER_SYMBOL: usize = Self::N + Self::M;
-  /// number of bits in one packet
-  pub const ENCODE_SYMBOLS: usize = 24;
-  /// total number of symbols in one packet, extra one symbol for training
-  pub const SYMBOLS: usize = Self::ENCODE_SYMBOLS + 1;
-  /// total number of samples for one packet
-  pub const PACKET_SAMPLES: usize = Self::SYMBOLS * Self::SAMPLES_PER_SYMBOL;
-
-  /// size of FFT/IFFT
-  pub const N: usize = 64;
-  /// samples in the cyclic prefix
-  pub const M: usize = 8;
-  /// first encoding frequency point
-  pub const START: usize = 7;
+  pub fn new() -> Self {
+    Self::build(OfdmParams::DEFAULT)
+  }
 
-  // for fft scaling
-  const UNIT: f32 = 1.0 / 4.0;
-  const ZERO: Complex = Complex::new(Self::UNIT, 0.0);
-  const ONE: Complex = Complex::new(-Self::UNIT, 0.0);
-  const PHASE: [Complex; 2] = [Self::ZERO, Self::ONE];
+  /// the subcarriers are relative to the sampling rate, OFDM does not depend on the audio config
+  pub fn with_config(_config: &AudioConfig) -> Self {
+    Self::new()
+  }
 
-  pub fn new() -> Self {
+  /// build the modem with the given parameters
+  pub fn with_params(params: &OfdmParams, config: &AudioConfig) -> Result<Self, ParamError> {
+    params.validate(config)?;
+    Ok(Self::build(*params))
+  }
+
+  /// Build the modem with a bit-loading table,
+  /// the subcarriers and the constellation of the parameters are replaced by the table.
+  pub fn with_loading(params: &OfdmParams, loading: BitLoading, _config: &AudioConfig) -> Result<Self, ParamError> {
+    params.validate_loading(&loading)?;
+    Ok(Self::build_loading(*params, loading))
+  }
+
+  pub fn params(&self) -> &OfdmParams {
+    &self.params
+  }
+
+  pub fn loading(&self) -> &BitLoading {
+    &self.loading
+  }
+
+  /// The SNR (dB) of each subcarrier in use in the last frame decoded, empty before the first frame.
+  /// The noise is the distance of the equalised points to the nearest points of the constellation,
+  /// so the SNR is underestimated when the points are beyond the decision boundaries.
+  pub fn last_snr(&self) -> &[(usize, f32)] {
+    &self.snr
+  }
+
+  /// The power of the points sent and the power of their errors on each subcarrier in use in the last frame decoded,
+  /// the bytes `sent` in the frame being known, e.g. a probe.
+  /// Unlike [`OFDM::last_snr`], the points beyond the decision boundaries are measured against the points sent.
+  pub fn last_error(&self, sent: &[u8]) -> Vec<(usize, f32, f32)> {
+    let carriers = self.loading.carriers();
+    let mut power: Vec<_> = carriers.iter().map(|(k, _)| (*k, 0.0, 0.0)).collect();
+    let symbols = self.points.len() / carriers.len().max(1);
+    let mut bits = bytes_to_bits(sent);
+    bits.resize(symbols * self.bits_per_symbol(), 0);
+    let mut bits = &bits[..];
+    for (i, point) in self.points.iter().enumerate() {
+      let (_, constellation) = carriers[i % carriers.len()];
+      let (point_bits, rest) = bits.split_at(constellation.bits());
+      bits = rest;
+      let sent = constellation.map(point_bits);
+      let (_, signal, error) = &mut power[i % carriers.len()];
+      *signal += sent.norm_sqr();
+      *error += (point - sent).norm_sqr();
+    }
+    power
+  }
+
+  fn bits_per_symbol(&self) -> usize {
+    self.loading.bits_per_symbol()
+  }
+
+  fn build(params: OfdmParams) -> Self {
+    Self::build_loading(params, BitLoading::uniform(params.subcarriers, params.constellation))
+  }
+
+  fn build_loading(params: OfdmParams, loading: BitLoading) -> Self {
+    let carriers = loading.carriers();
+    let peak = carriers.iter().map(|(_, c)| c.peak()).fold(1.0, f32::max);
     Self {
-      fft: Radix4::new(Self::N, FftDirection::Forward),
-      ifft: Radix4::new(Self::N, FftDirection::Inverse),
+      scale: 1.0 / ((carriers.len() + params.pilots.len()) as f32 * peak),
+      pilots: params.pilots.iter().collect(),
+      loading,
+      snr: Vec::new(),
+      points: Vec::new(),
+      frequency_offset: 0.0,
+      params,
+      fft: Radix4::new(params.n, FftDirection::Forward),
+      ifft: Radix4::new(params.n, FftDirection::Inverse),
     }
   }
 
-  fn encode_symbol(&self, buf: &mut [Complex], symbol: &mut [f32], bits: &[u8]) {
+  /// The known point of a subcarrier in the training symbol and of a pilot in every symbol:
+  /// unit magnitude with a quadratic phase, so that the training symbol has no sharp peak.
+  fn training_point(k: usize) -> Complex {
+    Complex::from_polar(1.0, std::f32::consts::PI * (k * k) as f32 / 64.0)
+  }
+
+  /// the subcarriers of the training symbol: the data subcarriers and the pilots
+  fn training_points(&self) -> impl Iterator<Item = (usize, Complex)> + '_ {
+    let carriers = self.loading.carriers().iter().map(|(k, _)| *k);
+    carriers
+      .chain(self.pilots.iter().copied())
+      .map(|k| (k, Self::training_point(k)))
+  }
+
+  /// modulate the points on their subcarriers, the other subcarriers are off
+  fn modulate_symbol(&self, buf: &mut [Complex], symbol: &mut [f32], points: impl Iterator<Item = (usize, Complex)>) {
     buf.iter_mut().for_each(|x| *x = Complex::default());
-    let (cp, symbol) = symbol.split_at_mut(Self::M);
+    let OfdmParams { n, m, .. } = self.params;
+    let (cp, symbol) = symbol.split_at_mut(m);
 
-    for (i, bit) in bits.iter().enumerate() {
-      let val = Self::PHASE[*bit as usize];
-      let j = Self::START + i;
-      buf[j] = val;
+    for (k, point) in points {
+      buf[k] = point * self.scale;
     }
     self.ifft.process(buf);
 
-    copy(cp.iter_mut(), buf[Self::N - Self::M..].iter().map(|x| x.re));
+    copy(cp.iter_mut(), buf[n - m..].iter().map(|x| x.re));
     copy(symbol.iter_mut(), buf.iter().map(|x| x.re));
   }
-  fn decode_symbol(&self, buf: &mut [Complex], symbol: &[f32], bits: &mut [u8], train_arg: &[f32]) {
-    buf.iter_mut().for_each(|x| *x = Complex::default());
-    let (_cp, symbol) = symbol.split_at(Self::M);
-
-    copy(buf.iter_mut(), symbol.iter().map(|x| Complex::new(*x, 0.0)));
+  /// FFT of the `n` samples of `frame` from `start`, the samples beyond the frame are zero.
+  /// The frequency offset is removed: the subcarriers received at `k + offset` are moved back to `k`,
+  /// their images at `-k - offset` away to `-k - 2 * offset`, far from the subcarriers in use.
+  fn demodulate_symbol(&self, buf: &mut [Complex], frame: &[f32], start: isize) {
+    for (i, x) in buf.iter_mut().enumerate() {
+      let t = start + i as isize;
+      let sample = usize::try_from(t).ok().and_then(|i| frame.get(i));
+      *x = Complex::new(sample.copied().unwrap_or_default(), 0.0);
+      if self.frequency_offset != 0.0 {
+        // the phase in turns within [0, 1)
+        let turns = (self.frequency_offset * t as f32).rem_euclid(1.0);
+        *x *= Complex::from_polar(1.0, -std::f32::consts::TAU * turns);
+      }
+    }
     self.fft.process(buf);
+  }
 
-    for (i, bit) in bits.iter_mut().enumerate() {
-      let offset = Complex::exp(-Complex::new(0.0, 1.0) * train_arg[i]);
-      let j = Self::START + i;
-      let val = (buf[j] * offset).re;
-      *bit = if val > 0.0 { 0 } else { 1 };
-    }
+  fn encode_symbol(&self, buf: &mut [Complex], symbol: &mut [f32], mut bits: &[u8]) {
+    let points = self.loading.carriers().iter().map(|(k, constellation)| {
+      let (point, rest) = bits.split_at(constellation.bits());
+      bits = rest;
+      (*k, constellation.map(point))
+    });
+    let pilots = self.pilots.iter().map(|k| (*k, Self::training_point(*k)));
+    self.modulate_symbol(buf, symbol, points.chain(pilots));
   }
-  fn train(&self, buf: &mut [Complex], symbol: &[f32]) -> Vec<f32> {
-    buf.iter_mut().for_each(|x| *x = Complex::default());
-    let (_cp, symbol) = symbol.split_at(Self::M);
+  /// Decode the soft bits of the data symbol of `frame` from `start`, the cyclic prefix excluded,
+  /// at the timing predicted by the tracker, and update the tracker with the symbol.
+  /// The signal and noise power of each subcarrier are accumulated in `state`.
+  fn decode_symbol(
+    &self,
+    buf: &mut [Complex],
+    frame: &[f32],
+    start: usize,
+    mut soft: &mut [f32],
+    state: &mut Decoding,
+  ) {
+    let prediction = state.tracker.predict();
+    // read from the nearest sample within the cyclic prefix, then delay by the rest
+    let shift = prediction.delay.round().max(-(self.params.m as f32));
+    self.demodulate_symbol(buf, frame, start as isize + shift as isize);
+    delay_bins(buf, shift - prediction.delay);
 
-    copy(buf.iter_mut(), symbol.iter().map(|x| Complex::new(*x, 0.0)));
-    self.fft.process(buf);
+    let derotate = Complex::from_polar(1.0, -prediction.phase);
+    let points: Vec<_> = self
+      .loading
+      .carriers()
+      .iter()
+      .map(|(k, constellation)| (*k, *constellation, buf[*k] * derotate / state.channel[*k]))
+      .collect();
+    // the pilots, then the data points decided at the predicted phase, against the points sent
+    let pilots = self.pilots.iter().map(|k| {
+      let point = buf[*k] * derotate / state.channel[*k];
+      (*k, point * Self::training_point(*k).conj())
+    });
+    let decided = points.iter().map(|(k, constellation, point)| {
+      let mut bits = vec![0; constellation.bits()];
+      constellation.demap(*point, &mut bits);
+      (*k, point * constellation.map(&bits).conj())
+    });
+    let references: Vec<_> = pilots.chain(decided).collect();
+    let (intercept, slope) = state.tracker.update(&references, self.params.n);
 
-    let mut train_arg = vec![0.0; Self::BITS_PER_SYMBOL];
-    for (i, arg) in train_arg.iter_mut().enumerate() {
-      let j = Self::START + i;
-      *arg = buf[j].arg();
+    for ((k, constellation, point), (signal, noise)) in points.into_iter().zip(&mut state.power) {
+      let (point_soft, rest) = std::mem::take(&mut soft).split_at_mut(constellation.bits());
+      soft = rest;
+      let point = point * Complex::from_polar(1.0, -(intercept + slope * k as f32));
+      state.points.push(point);
+      // the noise on an equalised point is inversely proportional to the power gain of the subcarrier
+      constellation.demap_soft(point, state.channel[k].norm_sqr(), point_soft);
+      let mut point_bits = vec![0; constellation.bits()];
+      constellation.demap(point, &mut point_bits);
+      let nearest = constellation.map(&point_bits);
+      *signal += nearest.norm_sqr();
+      *noise += (point - nearest).norm_sqr();
     }
-    train_arg
+  }
+  /// estimate the channel response of each subcarrier of the training symbol, the scaling of the modulator included
+  fn train(&self, buf: &mut [Complex], frame: &[f32]) -> Vec<Complex> {
+    self.demodulate_symbol(buf, frame, self.params.m as isize);
+    let mut channel = vec![Complex::new(1.0, 0.0); self.params.n];
+    for (k, point) in self.training_points() {
+      channel[k] = buf[k] / point;
+    }
+    channel
   }
 
   fn encode(&mut self, bytes: &[u8]) -> Vec<f32> {
-    assert_eq!(bytes.len(), Self::BYTES_PER_PACKET);
+    assert!(bytes.len() <= self.bytes_per_packet());
+    let samples_per_symbol = self.params.samples_per_symbol();
+    let bits_per_symbol = self.bits_per_symbol();
 
-    let mut frame = vec![0.0; Self::SAMPLES_PER_PACKET];
-    let mut buf = [Complex::default(); Self::N];
-    let mut bits = vec![0; Self::BITS_PER_SYMBOL];
-    bits.extend(bytes_to_bits(bytes));
-    frame
-      .chunks_exact_mut(Self::SAMPLES_PER_SYMBOL)
-      .zip(bits.chunks_exact(Self::BITS_PER_SYMBOL))
+    let mut frame = vec![0.0; self.samples_for(bytes.len())];
+    let mut buf = vec![Complex::default(); self.params.n];
+    let (train, data) = frame.split_at_mut(samples_per_symbol);
+    self.modulate_symbol(&mut buf, train, self.training_points());
+
+    let mut bits = bytes_to_bits(bytes);
+    // pad the last symbol
+    bits.resize(data.len() / samples_per_symbol * bits_per_symbol, 0);
+    data
+      .chunks_exact_mut(samples_per_symbol)
+      .zip(bits.chunks_exact(bits_per_symbol))
       .for_each(|(symbol, bits)| self.encode_symbol(&mut buf, symbol, bits));
     frame
   }
 
-  fn decode(&mut self, samples: &[f32]) -> Vec<u8> {
-    assert_eq!(samples.len(), Self::SAMPLES_PER_PACKET);
-
-    let mut buf = [Complex::default(); Self::N];
+  fn decode(&mut self, samples: &[f32]) -> SoftBits {
+    let samples_per_symbol = self.params.samples_per_symbol();
+    let bits_per_symbol = self.bits_per_symbol();
+    assert_eq!(samples.len() % samples_per_symbol, 0);
+    assert!(samples.len() >= samples_per_symbol);
 
-    let (train_samples, samples) = samples.split_at(Self::SAMPLES_PER_SYMBOL);
-    let train_arg = self.train(&mut buf, train_samples);
+    let mut buf = vec![Complex::default(); self.params.n];
+    let mut state = Decoding {
+      channel: self.train(&mut buf, samples),
+      tracker: Tracker::default(),
+      power: vec![(0.0, 0.0); self.loading.carriers().len()],
+      points: Vec::with_capacity(samples.len() / samples_per_symbol * self.loading.carriers().len()),
+    };
 
-    let mut bits = [0; Self::ENCODE_SYMBOLS * Self::BITS_PER_SYMBOL];
-    samples
-      .chunks_exact(Self::SAMPLES_PER_SYMBOL)
-      .zip(bits.chunks_exact_mut(Self::BITS_PER_SYMBOL))
-      .for_each(|(symbol, bits)| self.decode_symbol(&mut buf, symbol, bits, &train_arg));
-    bits_to_bytes(&bits)
+    let mut soft = vec![0.0; (samples.len() / samples_per_symbol - 1) * bits_per_symbol];
+    for (index, soft) in soft.chunks_exact_mut(bits_per_symbol).enumerate() {
+      let start = (index + 1) * samples_per_symbol + self.params.m;
+      self.decode_symbol(&mut buf, samples, start, soft, &mut state);
+    }
+    self.snr = self
+      .loading
+      .carriers()
+      .iter()
+      .zip(state.power)
+      .map(|((k, _), (signal, noise))| (*k, 10.0 * (signal / noise.max(f32::MIN_POSITIVE)).log10()))
+      .collect();
+    self.points = state.points;
+    // drop the padding of the last symbol
+    soft.truncate(soft.len() / 8 * 8);
+    soft
   }
 }
 
+/// the state of [`OFDM`] along the frame being decoded
+struct Decoding {
+  /// the channel response of each FFT bin, estimated on the training symbol
+  channel: Vec<Complex>,
+  tracker: Tracker,
+  /// the signal and noise power of each data subcarrier
+  power: Vec<(f32, f32)>,
+  /// the equalised points of the data subcarriers
+  points: Vec<Complex>,
+}
+
 impl Default for OFDM {
   fn default() -> Self {
     Self::new()
@@ -131,15 +476,30 @@ impl Default for OFDM {
 }
 
 impl Modem for OFDM {
-  const BYTES_PER_PACKET: usize = OFDM::BITS_PER_SYMBOL * OFDM::ENCODE_SYMBOLS / 8;
-  const SAMPLES_PER_PACKET: usize = OFDM::PACKET_SAMPLES;
+  fn bytes_per_packet(&self) -> usize {
+    self.bits_per_symbol() * self.params.encode_symbols / 8
+  }
+
+  /// one training symbol, then the bits padded to whole symbols
+  fn samples_for(&self, bytes: usize) -> usize {
+    (1 + (bytes * 8).div_ceil(self.bits_per_symbol())) * self.params.samples_per_symbol()
+  }
 
   fn modulate(&mut self, bytes: &[u8]) -> FramePayload {
     OFDM::encode(self, bytes).into_iter().map(FP::from_f32).collect()
   }
 
   fn demodulate(&mut self, samples: &[FP]) -> PhyPacket {
+    hard_decision(&self.demodulate_soft(samples))
+  }
+
+  /// the max-log log-likelihood ratios of the points, weighted by the power gain of their subcarrier
+  fn demodulate_soft(&mut self, samples: &[FP]) -> SoftBits {
     let samples: Vec<_> = samples.iter().cloned().map(FP::into_f32).collect();
     OFDM::decode(self, &samples)
   }
+
+  fn set_frequency_offset(&mut self, offset: f32) {
+    self.frequency_offset = offset;
+  }
 }
@@ -0,0 +1,70 @@
+use super::{Constellation, Subcarriers};
+
+/// A bit-loading table: the constellation of each subcarrier in use, the other subcarriers are disabled.
+///
+/// The table is built from the SNR of each subcarrier measured on a link, see [`BitLoading::from_snr`],
+/// so that a subcarrier carries as many bits as its SNR allows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitLoading(Vec<(usize, Constellation)>);
+
+impl BitLoading {
+  /// the same constellation on all the given subcarriers
+  pub fn uniform(subcarriers: Subcarriers, constellation: Constellation) -> Self {
+    Self(subcarriers.iter().map(|k| (k, constellation)).collect())
+  }
+
+  /// Choose the densest constellation on each subcarrier whose SNR exceeds [`Constellation::min_snr_db`]
+  /// by `margin_db`, the subcarriers too noisy even for BPSK are disabled.
+  /// - `snr_db`: pairs of a subcarrier and its SNR in dB
+  pub fn from_snr(snr_db: &[(usize, f32)], margin_db: f32) -> Self {
+    Self(
+      snr_db
+        .iter()
+        .filter_map(|(k, snr)| {
+          Constellation::ALL
+            .into_iter()
+            .rev()
+            .find(|c| c.min_snr_db() + margin_db <= *snr)
+            .map(|c| (*k, c))
+        })
+        .collect(),
+    )
+  }
+
+  /// the subcarriers in use with their constellation, in ascending order
+  pub fn carriers(&self) -> &[(usize, Constellation)] {
+    &self.0
+  }
+
+  /// the subcarriers in use
+  pub fn subcarriers(&self) -> Subcarriers {
+    self.0.iter().fold(Subcarriers::EMPTY, |set, (k, _)| set.with(*k))
+  }
+
+  /// number of bits in one OFDM symbol
+  pub fn bits_per_symbol(&self) -> usize {
+    self.0.iter().map(|(_, c)| c.bits()).sum()
+  }
+
+  /// Serialize the table, e.g. to send it to the peer: a subcarrier and its bits per point for each subcarrier in use
+  pub fn to_bytes(&self) -> Vec<u8> {
+    self.0.iter().flat_map(|(k, c)| [*k as u8, c.bits() as u8]).collect()
+  }
+
+  /// Parse a table serialized by [`BitLoading::to_bytes`], `None` if the bytes are not a valid table
+  pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+    if !bytes.len().is_multiple_of(2) {
+      return None;
+    }
+    let carriers = bytes
+      .chunks_exact(2)
+      .map(|pair| {
+        let k = pair[0] as usize;
+        (k < Subcarriers::MAX).then_some(())?;
+        Some((k, Constellation::from_bits(pair[1] as usize)?))
+      })
+      .collect::<Option<Vec<_>>>()?;
+    let ascending = carriers.windows(2).all(|pair| pair[0].0 < pair[1].0);
+    ascending.then_some(Self(carriers))
+  }
+}
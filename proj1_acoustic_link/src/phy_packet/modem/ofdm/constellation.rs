@@ -0,0 +1,162 @@
+type Complex = rustfft::num_complex::Complex32;
+
+/// Mapping of the bits carried by one subcarrier onto a complex point.
+///
+/// The square constellations are Gray coded on each axis, so that confusing a point with a neighbour
+/// costs one bit error, and normalised to unit average power.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Constellation {
+  /// 1 bit per subcarrier
+  #[default]
+  Bpsk,
+  /// 2 bits per subcarrier
+  Qpsk,
+  /// 4 bits per subcarrier
+  Qam16,
+  /// 6 bits per subcarrier
+  Qam64,
+}
+
+impl Constellation {
+  /// all the constellations, from the most robust to the densest
+  pub const ALL: [Self; 4] = [Self::Bpsk, Self::Qpsk, Self::Qam16, Self::Qam64];
+
+  /// the constellation carrying the given number of bits per point
+  pub fn from_bits(bits: usize) -> Option<Self> {
+    Self::ALL.into_iter().find(|c| c.bits() == bits)
+  }
+
+  /// SNR (dB) at which the hard decision makes about one bit error in a thousand
+  pub const fn min_snr_db(&self) -> f32 {
+    match self {
+      Self::Bpsk => 7.0,
+      Self::Qpsk => 10.0,
+      Self::Qam16 => 17.0,
+      Self::Qam64 => 23.0,
+    }
+  }
+
+  /// number of bits carried by one point
+  pub const fn bits(&self) -> usize {
+    match self {
+      Self::Bpsk => 1,
+      Self::Qpsk => 2,
+      Self::Qam16 => 4,
+      Self::Qam64 => 6,
+    }
+  }
+
+  /// number of amplitude levels on each axis,
+  /// the in-phase axis carries the first half of the bits, the quadrature axis the rest.
+  fn levels(&self) -> usize {
+    match self {
+      Self::Bpsk => 2,
+      _ => 1 << (self.bits() / 2),
+    }
+  }
+
+  /// scale of the levels `±1, ±3, ...` for unit average power
+  fn norm(&self) -> f32 {
+    let levels = self.levels() as f32;
+    let axes = if *self == Self::Bpsk { 1.0 } else { 2.0 };
+    (axes * (levels * levels - 1.0) / 3.0).sqrt().recip()
+  }
+
+  /// the largest magnitude of the points
+  pub fn peak(&self) -> f32 {
+    let max_level = (self.levels() - 1) as f32 * self.norm();
+    if *self == Self::Bpsk {
+      max_level
+    } else {
+      max_level * std::f32::consts::SQRT_2
+    }
+  }
+
+  /// Map [`Self::bits`] bits, each `0` or `1` with the most significant bit first, onto a point
+  pub fn map(&self, bits: &[u8]) -> Complex {
+    assert_eq!(bits.len(), self.bits());
+    let (i, q) = match self {
+      Self::Bpsk => (bits, &[][..]),
+      _ => bits.split_at(bits.len() / 2),
+    };
+    Complex::new(self.level(i), self.level(q))
+  }
+
+  /// Map a point back onto the bits of the nearest point of the constellation
+  pub fn demap(&self, point: Complex, bits: &mut [u8]) {
+    assert_eq!(bits.len(), self.bits());
+    let (i, q) = match self {
+      Self::Bpsk => (bits, &mut [][..]),
+      _ => bits.split_at_mut(self.bits() / 2),
+    };
+    self.decide(point.re, i);
+    self.decide(point.im, q);
+  }
+
+  /// Log-likelihood ratios of the bits of a point, positive for a `0`, by the max-log approximation:
+  /// the squared distance to the nearest level with the bit at `1` minus the one with the bit at `0`,
+  /// times the `weight` of the point, the inverse of the noise power.
+  pub fn demap_soft(&self, point: Complex, weight: f32, llrs: &mut [f32]) {
+    assert_eq!(llrs.len(), self.bits());
+    let (i, q) = match self {
+      Self::Bpsk => (llrs, &mut [][..]),
+      _ => llrs.split_at_mut(self.bits() / 2),
+    };
+    self.soft_decide(point.re, weight, i);
+    self.soft_decide(point.im, weight, q);
+  }
+
+  /// amplitude of the Gray coded bits on one axis
+  fn level(&self, bits: &[u8]) -> f32 {
+    if bits.is_empty() {
+      return 0.0;
+    }
+    let gray = bits.iter().fold(0, |acc, bit| (acc << 1) | *bit as usize);
+    let index = from_gray(gray);
+    ((2 * index) as f32 - (self.levels() - 1) as f32) * self.norm()
+  }
+
+  /// Gray coded bits of the nearest level on one axis
+  fn decide(&self, value: f32, bits: &mut [u8]) {
+    if bits.is_empty() {
+      return;
+    }
+    let max_index = self.levels() - 1;
+    let index = ((value / self.norm() + max_index as f32) / 2.0).round();
+    let gray = to_gray((index.max(0.0) as usize).min(max_index));
+    let n = bits.len();
+    for (k, bit) in bits.iter_mut().enumerate() {
+      *bit = ((gray >> (n - 1 - k)) & 1) as u8;
+    }
+  }
+
+  /// log-likelihood ratios of the Gray coded bits on one axis, see [`Self::demap_soft`]
+  fn soft_decide(&self, value: f32, weight: f32, llrs: &mut [f32]) {
+    let n = llrs.len();
+    for (k, llr) in llrs.iter_mut().enumerate() {
+      let nearest = |bit: usize| {
+        (0..self.levels())
+          .filter(|index| (to_gray(*index) >> (n - 1 - k)) & 1 == bit)
+          .map(|index| {
+            let level = ((2 * index) as f32 - (self.levels() - 1) as f32) * self.norm();
+            (value - level).powi(2)
+          })
+          .fold(f32::MAX, f32::min)
+      };
+      *llr = weight * (nearest(1) - nearest(0));
+    }
+  }
+}
+
+fn to_gray(index: usize) -> usize {
+  index ^ (index >> 1)
+}
+
+fn from_gray(mut gray: usize) -> usize {
+  let mut index = gray;
+  while gray > 0 {
+    gray >>= 1;
+    index ^= gray;
+  }
+  index
+}
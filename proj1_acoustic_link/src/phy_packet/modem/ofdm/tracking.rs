@@ -0,0 +1,91 @@
+use std::f32::consts::TAU;
+type Complex = rustfft::num_complex::Complex32;
+
+/// share of the error measured on a symbol corrected at once, the rest is left to the next symbols
+const PROPORTIONAL_GAIN: f32 = 0.2;
+/// share of the error measured on a symbol added to the drift per symbol
+const INTEGRAL_GAIN: f32 = 0.03;
+
+/// The phase and the timing of the next symbol predicted by [`Tracker`]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(super) struct Prediction {
+  /// common phase (rad) of all the subcarriers
+  pub phase: f32,
+  /// timing (samples) relative to the training symbol
+  pub delay: f32,
+}
+
+/// Track the common phase error and the timing drift of the symbols of a frame, symbol by symbol,
+/// on the pilots and the data points once decided.
+///
+/// A carrier frequency offset rotates the phase of all the subcarriers by the same step every symbol,
+/// a sampling clock offset drifts the timing of the symbols, which turns the phase of the subcarrier `k`
+/// by `2πk·delay/n`. A second order loop follows both from the phases of the subcarriers:
+/// the intercept of a line fitted through them is the common phase error, the slope the timing error.
+#[derive(Clone, Debug, Default)]
+pub(super) struct Tracker {
+  phase: f32,
+  phase_step: f32,
+  delay: f32,
+  delay_step: f32,
+}
+
+impl Tracker {
+  /// the phase and the timing of the next symbol
+  pub fn predict(&self) -> Prediction {
+    Prediction {
+      phase: self.phase + self.phase_step,
+      delay: self.delay + self.delay_step,
+    }
+  }
+
+  /// Update the loops with the pilots of the symbol read at the predicted timing, see [`Tracker::predict`].
+  /// - `references`: pairs of a subcarrier and its point, equalised and derotated by the predicted phase,
+  ///   times the conjugate of the point sent: known on the pilots, decided on the data subcarriers
+  /// - `n`: size of the FFT
+  ///
+  /// return the phase to remove from the symbol on top of the prediction: intercept and slope per subcarrier
+  pub fn update(&mut self, references: &[(usize, Complex)], n: usize) -> (f32, f32) {
+    let Prediction { phase, delay } = self.predict();
+    let (intercept, slope) = fit_line(references);
+    // a symbol read late by `r` samples turns the subcarrier `k` by `2πk·r/n`
+    let late = slope * n as f32 / TAU;
+
+    self.phase = phase + PROPORTIONAL_GAIN * intercept;
+    self.phase_step += INTEGRAL_GAIN * intercept;
+    self.delay = delay - PROPORTIONAL_GAIN * late;
+    self.delay_step -= INTEGRAL_GAIN * late;
+    (PROPORTIONAL_GAIN * intercept, PROPORTIONAL_GAIN * slope)
+  }
+}
+
+/// Least squares line through the phases of the points, weighted by their magnitudes:
+/// a constant phase if all the points are on the same subcarrier, zero without point
+fn fit_line(points: &[(usize, Complex)]) -> (f32, f32) {
+  let weight: f32 = points.iter().map(|(_, point)| point.norm()).sum();
+  if weight == 0.0 {
+    return (0.0, 0.0);
+  }
+  let mean = |f: &dyn Fn(f32, f32) -> f32| {
+    points
+      .iter()
+      .map(|(k, point)| point.norm() * f(*k as f32, point.arg()))
+      .sum::<f32>()
+      / weight
+  };
+  let mean_k = mean(&|k, _| k);
+  let mean_phase = mean(&|_, phase| phase);
+  let covariance = mean(&|k, phase| (k - mean_k) * (phase - mean_phase));
+  let variance = mean(&|k, _| (k - mean_k).powi(2));
+  let slope = if variance > 0.0 { covariance / variance } else { 0.0 };
+  (mean_phase - slope * mean_k, slope)
+}
+
+/// Delay the symbol in `buf`, the FFT of `n` samples, by a fraction of a sample:
+/// turn the subcarrier `k` below the Nyquist frequency by `-2πk·delay/n`.
+pub(super) fn delay_bins(buf: &mut [Complex], delay: f32) {
+  let n = buf.len();
+  for (k, x) in buf.iter_mut().enumerate().take(n / 2) {
+    *x *= Complex::from_polar(1.0, -TAU * k as f32 * delay / n as f32);
+  }
+}
@@ -1,7 +1,11 @@
 use crate::{
   helper::{bits_to_bytes, bytes_to_bits, dot_product},
-  phy_packet::traits::{FramePayload, Modem, PhyPacket},
+  phy_packet::{
+    params::{below_nyquist, packet_bytes, positive, ParamError},
+    traits::{FramePayload, Modem, PhyPacket, SoftBits},
+  },
   traits::{Sample, FP},
+  AudioConfig,
 };
 
 fn get_bit(symbol: &[FP], reference: &[FP]) -> u8 {
@@ -34,31 +38,67 @@ fn add_vec(v_lo: &[FP], v_hi: &[FP], b_lo: u8, b_hi: u8) -> Vec<FP> {
   v_lo.iter().zip(v_hi.iter()).map(|(x, y)| func(x, y)).collect()
 }
 
+/// Parameters of [`PSK`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PskParams {
+  /// frequency of the carrier wave, the second carrier is at the double frequency
+  pub carrier_freq: f32,
+  /// number of samples used to encode a 2-bit
+  pub samples_per_symbol: usize,
+  /// number of 2-bits in one packet, whole bytes
+  pub symbols_per_packet: usize,
+}
+
+impl PskParams {
+  pub const DEFAULT: Self = Self {
+    carrier_freq: 8000.0,
+    samples_per_symbol: 6,
+    symbols_per_packet: 320,
+  };
+
+  /// maximum number of bytes in one packet
+  pub const fn bytes_per_packet(&self) -> usize {
+    2 * self.symbols_per_packet / 8
+  }
+
+  pub fn validate(&self, config: &AudioConfig) -> Result<(), ParamError> {
+    below_nyquist("carrier frequency", self.carrier_freq, config)?;
+    below_nyquist("second carrier frequency", 2.0 * self.carrier_freq, config)?;
+    positive("samples per symbol", self.samples_per_symbol)?;
+    if !self.symbols_per_packet.is_multiple_of(4) {
+      return Err(ParamError::Invalid("symbols per packet", "must be whole bytes"));
+    }
+    packet_bytes(self.bytes_per_packet())
+  }
+}
+
+impl Default for PskParams {
+  fn default() -> Self {
+    Self::DEFAULT
+  }
+}
+
 /// 2 channel 4-PSK
 pub struct PSK {
+  params: PskParams,
   wave_lo: Vec<FP>,
   wave_hi: Vec<FP>,
   symbols: [Vec<FP>; 4],
 }
-impl PSK {
-  /// sampling rate of the digital signal
-  pub const SAMPLE_RATE: usize = 48000;
-  /// frequency of the carrier wave
-  pub const CARRIER_FREQ: f32 = 8000.0;
-  /// number of samples used to encode a 2-bit
-  pub const SAMPLES_PER_SYMBOL: usize = 6;
-  /// number of 2-bits in one packet
-  pub const SYMBOLS_PER_PACKET: usize = 320;
-}
 impl Modem for PSK {
-  const BYTES_PER_PACKET: usize = 2 * PSK::SYMBOLS_PER_PACKET / 8;
+  fn bytes_per_packet(&self) -> usize {
+    self.params.bytes_per_packet()
+  }
 
-  const SAMPLES_PER_PACKET: usize = PSK::SAMPLES_PER_SYMBOL * PSK::SYMBOLS_PER_PACKET;
+  /// 4 symbols per byte
+  fn samples_for(&self, bytes: usize) -> usize {
+    bytes * 4 * self.params.samples_per_symbol
+  }
 
   fn modulate(&mut self, bytes: &[u8]) -> FramePayload {
-    assert_eq!(bytes.len(), Self::BYTES_PER_PACKET);
+    assert!(bytes.len() <= self.bytes_per_packet());
 
-    let mut frame = FramePayload::with_capacity(Self::SAMPLES_PER_PACKET);
+    let mut frame = FramePayload::with_capacity(self.samples_for(bytes.len()));
     bytes_to_bits(bytes).chunks_exact(2).for_each(|bit2| {
       let (lo, hi) = (bit2[0], bit2[1]);
       let b = (hi << 1) | lo;
@@ -68,10 +108,11 @@ impl Modem for PSK {
   }
 
   fn demodulate(&mut self, samples: &[FP]) -> PhyPacket {
-    assert_eq!(samples.len(), Self::SAMPLES_PER_PACKET);
+    assert_eq!(samples.len() % self.samples_for(1), 0);
 
-    let mut bits = Vec::with_capacity(Self::SYMBOLS_PER_PACKET);
-    samples.chunks_exact(Self::SAMPLES_PER_SYMBOL).for_each(|symbol| {
+    let samples_per_symbol = self.params.samples_per_symbol;
+    let mut bits = Vec::with_capacity(2 * samples.len() / samples_per_symbol);
+    samples.chunks_exact(samples_per_symbol).for_each(|symbol| {
       let b_lo = get_bit(symbol, &self.wave_lo);
       let b_hi = get_bit(symbol, &self.wave_hi);
       bits.push(b_lo);
@@ -79,15 +120,41 @@ impl Modem for PSK {
     });
     bits_to_bytes(&bits)
   }
+
+  /// the correlation of a symbol with each carrier, a negative one is a 1
+  fn demodulate_soft(&mut self, samples: &[FP]) -> SoftBits {
+    assert_eq!(samples.len() % self.samples_for(1), 0);
+    samples
+      .chunks_exact(self.params.samples_per_symbol)
+      .flat_map(|symbol| {
+        [
+          dot_product(symbol.iter(), self.wave_lo.iter()),
+          dot_product(symbol.iter(), self.wave_hi.iter()),
+        ]
+      })
+      .map(FP::into_f32)
+      .collect()
+  }
 }
 impl PSK {
   pub fn new() -> Self {
+    Self::with_config(&AudioConfig::default())
+  }
+
+  /// build the carrier waves with the default parameters at the sampling rate of the audio config
+  pub fn with_config(config: &AudioConfig) -> Self {
+    Self::with_params(&PskParams::DEFAULT, config).expect("invalid default PSK for the audio config")
+  }
+
+  /// build the carrier waves with the given parameters at the sampling rate of the audio config
+  pub fn with_params(params: &PskParams, config: &AudioConfig) -> Result<Self, ParamError> {
     use std::f32::consts::TAU;
-    let dt = 1.0 / Self::SAMPLE_RATE as f32;
-    let t = (0..Self::SAMPLES_PER_SYMBOL).map(|i| dt * i as f32);
+    params.validate(config)?;
+    let dt = 1.0 / config.sample_rate as f32;
+    let t = (0..params.samples_per_symbol).map(|i| dt * i as f32);
 
-    let f_lo = Self::CARRIER_FREQ as f32;
-    let f_hi = 2.0 * Self::CARRIER_FREQ as f32;
+    let f_lo = params.carrier_freq;
+    let f_hi = 2.0 * params.carrier_freq;
     let wave_lo: Vec<_> = t.clone().map(|t| FP::from_f32((TAU * f_lo * t).sin())).collect();
     let wave_hi: Vec<_> = t.clone().map(|t| FP::from_f32((TAU * f_hi * t).sin())).collect();
 
@@ -98,11 +165,16 @@ impl PSK {
       add_vec(&wave_lo, &wave_hi, 1, 1),
     ];
 
-    Self {
+    Ok(Self {
+      params: *params,
       wave_lo,
       wave_hi,
       symbols,
-    }
+    })
+  }
+
+  pub fn params(&self) -> &PskParams {
+    &self.params
   }
 }
 
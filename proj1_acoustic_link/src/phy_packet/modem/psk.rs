@@ -2,6 +2,7 @@ use crate::{
   helper::{bits_to_bytes, bytes_to_bits, dot_product},
   phy_packet::traits::{FramePayload, Modem, PhyPacket},
   traits::{Sample, FP},
+  AudioConfig,
 };
 
 /// PSK (phase shift keying)  
@@ -12,8 +13,6 @@ pub struct PSK {
   symbols: [Vec<FP>; 2],
 }
 impl PSK {
-  /// sampling rate of the digital signal
-  pub const SAMPLE_RATE: usize = 48000;
   /// frequency of the carrier wave
   pub const CARRIER_FREQ: f32 = if cfg!(feature = "wired") { 8000.0 } else { 4800.0 };
   /// number of samples used to encode a bit
@@ -22,14 +21,18 @@ impl PSK {
   pub const SYMBOLS_PER_PACKET: usize = if cfg!(feature = "wired") { 400 } else { 80 };
 }
 impl Modem for PSK {
-  const BYTES_PER_PACKET: usize = PSK::SYMBOLS_PER_PACKET / 8;
+  fn bytes_per_packet(&self) -> usize {
+    PSK::SYMBOLS_PER_PACKET / 8
+  }
 
-  const SAMPLES_PER_PACKET: usize = PSK::SAMPLES_PER_SYMBOL * PSK::SYMBOLS_PER_PACKET;
+  fn samples_for(&self, bytes: usize) -> usize {
+    bytes * 8 * Self::SAMPLES_PER_SYMBOL
+  }
 
   fn modulate(&mut self, bytes: &[u8]) -> FramePayload {
-    assert_eq!(bytes.len(), Self::BYTES_PER_PACKET);
+    assert!(bytes.len() <= self.bytes_per_packet());
 
-    let mut frame = FramePayload::with_capacity(Self::SAMPLES_PER_PACKET);
+    let mut frame = FramePayload::with_capacity(self.samples_for(bytes.len()));
     bytes_to_bits(bytes)
       .into_iter()
       .for_each(|bit| frame.extend(&self.symbols[bit as usize]));
@@ -37,9 +40,9 @@ impl Modem for PSK {
   }
 
   fn demodulate(&mut self, samples: &[FP]) -> PhyPacket {
-    assert_eq!(samples.len(), Self::SAMPLES_PER_PACKET);
+    assert_eq!(samples.len() % self.samples_for(1), 0);
 
-    let mut bits = Vec::with_capacity(Self::SYMBOLS_PER_PACKET);
+    let mut bits = Vec::with_capacity(samples.len() / Self::SAMPLES_PER_SYMBOL);
     samples.chunks_exact(Self::SAMPLES_PER_SYMBOL).for_each(|symbol| {
       let sum = dot_product(symbol.iter(), self.symbols[0].iter());
       let bit = (sum < FP::ZERO) as _;
@@ -50,7 +53,12 @@ impl Modem for PSK {
 }
 impl PSK {
   pub fn new() -> Self {
-    let dt = FP::ONE / FP::from_f32(Self::SAMPLE_RATE as f32);
+    Self::with_config(&AudioConfig::default())
+  }
+
+  /// build the carrier wave at the sampling rate of the audio config
+  pub fn with_config(config: &AudioConfig) -> Self {
+    let dt = FP::ONE / FP::from_f32(config.sample_rate as f32);
     let zero: Vec<_> = (0..Self::SAMPLES_PER_SYMBOL)
       .map(|i| {
         let t = dt * FP::from_f32(i as f32);
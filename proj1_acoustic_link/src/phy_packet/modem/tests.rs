@@ -12,18 +12,33 @@ const MODEM_TESTS: usize = 1000;
 fn test_ideal<T: Modem>(mut modem: T) {
   let bytes: Vec<u8> = rand::thread_rng()
     .sample_iter(Standard)
-    .take(T::BYTES_PER_PACKET)
+    .take(modem.bytes_per_packet())
     .collect();
 
   let encoded = modem.modulate(&bytes);
   let decoded = modem.demodulate(&encoded);
   assert_eq!(bytes.as_slice(), decoded.as_slice());
 }
+/// encode/decode identity of packets of every length up to the maximum in ideal transmission channel,
+/// up to the padding
+fn test_variable_len<T: Modem>(mut modem: T) {
+  for len in 0..=modem.bytes_per_packet() {
+    let bytes: Vec<u8> = rand::thread_rng().sample_iter(Standard).take(len).collect();
+
+    let encoded = modem.modulate(&bytes);
+    assert_eq!(encoded.len(), modem.samples_for(len));
+    // the padding of the last symbol may be decoded too
+    let mut decoded = modem.demodulate(&encoded);
+    assert!(decoded.len() >= len);
+    decoded.truncate(len);
+    assert_eq!(bytes.as_slice(), decoded.as_slice());
+  }
+}
 /// encode/decode identity in noisy channel, where the noise is distributed as `noise_dist`.
 fn test_noisy<T: Modem, D: Distribution<f32>>(mut modem: T, noise_dist: D) {
   let bytes: Vec<u8> = rand::thread_rng()
     .sample_iter(Standard)
-    .take(T::BYTES_PER_PACKET)
+    .take(modem.bytes_per_packet())
     .collect();
 
   let encoded = modem.modulate(&bytes);
@@ -35,6 +50,32 @@ fn test_noisy<T: Modem, D: Distribution<f32>>(mut modem: T, noise_dist: D) {
   let decoded = modem.demodulate(&received);
   assert_eq!(bytes.as_slice(), decoded.as_slice());
 }
+/// the soft decisions agree with the hard ones, the link quality drops with the noise
+fn test_soft<T: Modem>(mut modem: T) {
+  use crate::phy_packet::soft::{hard_decision, quality_db};
+  let bytes: Vec<u8> = rand::thread_rng()
+    .sample_iter(Standard)
+    .take(modem.bytes_per_packet())
+    .collect();
+
+  let encoded = modem.modulate(&bytes);
+  let soft = modem.demodulate_soft(&encoded);
+  assert_eq!(soft.len(), 8 * bytes.len());
+  assert_eq!(hard_decision(&soft), bytes);
+
+  let noise = rand::distributions::Uniform::new(-0.5, 0.5);
+  let received: Vec<_> = encoded
+    .into_iter()
+    .zip(rand::thread_rng().sample_iter(noise).map(FP::from_f32))
+    .map(|(x, y)| x + y)
+    .collect();
+  let noisy = modem.demodulate_soft(&received);
+  assert_eq!(hard_decision(&noisy), modem.demodulate(&received));
+  // the soft bits of a clean frame may be all as reliable, their quality is infinite
+  let noisy = quality_db(&noisy).unwrap();
+  assert!(quality_db(&soft).is_none_or(|clean| noisy < clean));
+  assert_eq!(quality_db(&[]), None);
+}
 
 /// PSK decode in an ideal channel
 #[test]
@@ -43,6 +84,11 @@ fn psk_ideal() {
     test_ideal(crate::phy_packet::modem::psk::PSK::new());
   }
 }
+/// PSK decode of variable length packets in an ideal channel
+#[test]
+fn psk_variable_len() {
+  test_variable_len(crate::phy_packet::modem::psk::PSK::new());
+}
 /// PSK decode in noisy channel, where the noise is distributed as Uniform(-1,+1).
 #[test]
 fn psk_noise() {
@@ -58,6 +104,11 @@ fn multipsk_ideal() {
     test_ideal(crate::phy_packet::modem::proj2_modem::PSK::new());
   }
 }
+/// multi-PSK decode of variable length packets in an ideal channel
+#[test]
+fn multipsk_variable_len() {
+  test_variable_len(crate::phy_packet::modem::proj2_modem::PSK::new());
+}
 /// multi-PSK decode in noisy channel, where the noise is distributed as Uniform(-1,+1).
 #[test]
 fn multipsk_noise() {
@@ -74,15 +125,221 @@ fn ofdm_ideal() {
     test_ideal(super::OFDM::new());
   }
 }
-/// OFDM decode in noisy channel, where the noise is distributed as Uniform(-1,+1).
+/// OFDM decode of variable length packets in an ideal channel
+#[test]
+#[cfg(not(feature = "nofloat"))]
+fn ofdm_variable_len() {
+  test_variable_len(super::OFDM::new());
+}
+/// OFDM decode with the robust parameters in noisy channel, where the noise is distributed as Uniform(-1,+1).
 #[test]
 #[cfg(not(feature = "nofloat"))]
 fn ofdm_noise() {
+  use super::{OfdmParams, OFDM};
+  let config = crate::AudioConfig::default();
   for _ in 0..MODEM_TESTS {
-    test_noisy(super::OFDM::new(), Standard);
+    test_noisy(OFDM::with_params(&OfdmParams::ROBUST, &config).unwrap(), Standard);
+  }
+}
+
+/// every bit pattern maps onto a distinct point and back, with unit average power,
+/// and the nearest neighbours of a point differ in one bit
+#[test]
+#[cfg(not(feature = "nofloat"))]
+fn constellation_gray() {
+  use super::Constellation;
+  for constellation in [
+    Constellation::Bpsk,
+    Constellation::Qpsk,
+    Constellation::Qam16,
+    Constellation::Qam64,
+  ] {
+    let bits = constellation.bits();
+    let patterns: Vec<Vec<u8>> = (0..1usize << bits)
+      .map(|x| (0..bits).rev().map(|k| ((x >> k) & 1) as u8).collect())
+      .collect();
+    let points: Vec<_> = patterns.iter().map(|bits| constellation.map(bits)).collect();
+
+    let power = points.iter().map(|x| x.norm_sqr()).sum::<f32>() / points.len() as f32;
+    assert!((power - 1.0).abs() < 1e-4, "{:?}", constellation);
+    for (pattern, point) in patterns.iter().zip(&points) {
+      let mut decided = vec![0; bits];
+      constellation.demap(*point, &mut decided);
+      assert_eq!(&decided, pattern);
+
+      let nearest = points
+        .iter()
+        .map(|other| (other - point).norm())
+        .filter(|d| *d > 1e-4)
+        .fold(f32::MAX, f32::min);
+      for (other, other_pattern) in points.iter().zip(&patterns) {
+        if ((other - point).norm() - nearest).abs() < 1e-4 {
+          let distance = pattern.iter().zip(other_pattern).filter(|(a, b)| a != b).count();
+          assert_eq!(distance, 1, "{:?}", constellation);
+        }
+      }
+    }
+  }
+}
+
+/// OFDM decode of variable length packets with every constellation in an ideal channel
+#[test]
+#[cfg(not(feature = "nofloat"))]
+fn ofdm_constellations() {
+  use super::{Constellation, OfdmParams, OFDM};
+  let config = crate::AudioConfig::default();
+  for constellation in [
+    Constellation::Bpsk,
+    Constellation::Qpsk,
+    Constellation::Qam16,
+    Constellation::Qam64,
+  ] {
+    let params = OfdmParams {
+      constellation,
+      encode_symbols: 16,
+      ..Default::default()
+    };
+    test_variable_len(OFDM::with_params(&params, &config).unwrap());
   }
 }
 
+/// OFDM equalises the amplitude and the phase of each subcarrier:
+/// 64-QAM is decoded after an attenuation and a delay within the cyclic prefix
+#[test]
+#[cfg(not(feature = "nofloat"))]
+fn ofdm_equalisation() {
+  use super::{Constellation, OfdmParams, OFDM};
+  const DELAY: usize = 5;
+  let params = OfdmParams {
+    constellation: Constellation::Qam64,
+    encode_symbols: 16,
+    ..Default::default()
+  };
+  let mut modem = OFDM::with_params(&params, &crate::AudioConfig::default()).unwrap();
+  let bytes: Vec<u8> = rand::thread_rng()
+    .sample_iter(Standard)
+    .take(modem.bytes_per_packet())
+    .collect();
+
+  let encoded = modem.modulate(&bytes);
+  let received: Vec<_> = std::iter::repeat_n(FP::ZERO, DELAY)
+    .chain(encoded.iter().map(|x| *x * FP::from_f32(0.3)))
+    .take(encoded.len())
+    .collect();
+  assert_eq!(modem.demodulate(&received), bytes);
+}
+
+/// the densest constellation within the SNR margin is picked, the table survives serialization
+#[test]
+#[cfg(not(feature = "nofloat"))]
+fn bit_loading_snr() {
+  use super::{BitLoading, Constellation};
+  let snr = [(5, 3.0), (6, 8.0), (7, 12.0), (8, 19.0), (9, 40.0), (10, 17.5)];
+  let loading = BitLoading::from_snr(&snr, 1.0);
+  let expected = [
+    (6, Constellation::Bpsk),
+    (7, Constellation::Qpsk),
+    (8, Constellation::Qam16),
+    (9, Constellation::Qam64),
+    (10, Constellation::Qpsk),
+  ];
+  assert_eq!(loading.carriers(), expected);
+  assert_eq!(loading.bits_per_symbol(), 15);
+
+  assert_eq!(BitLoading::from_bytes(&loading.to_bytes()), Some(loading));
+  assert_eq!(BitLoading::from_bytes(&[6, 3]), None);
+  assert_eq!(BitLoading::from_bytes(&[7, 1, 6, 1]), None);
+  assert_eq!(BitLoading::from_bytes(&[6]), None);
+}
+/// OFDM with a different constellation on each subcarrier, the SNR measured drops with noise
+#[test]
+#[cfg(not(feature = "nofloat"))]
+fn ofdm_bit_loading() {
+  use super::{BitLoading, Constellation, OfdmParams, Subcarriers, OFDM};
+  let config = crate::AudioConfig::default();
+  let snr: Vec<_> = Subcarriers::range(6, 12)
+    .iter()
+    .map(|k| (k, 2.0 * k as f32 - 6.0))
+    .collect();
+  let loading = BitLoading::from_snr(&snr, 0.0);
+  assert_eq!(loading.carriers()[0], (7, Constellation::Bpsk));
+  let build = || OFDM::with_loading(&OfdmParams::DEFAULT, loading.clone(), &config).unwrap();
+  assert_eq!(build().bytes_per_packet(), loading.bits_per_symbol() * 32 / 8);
+  test_ideal(build());
+  test_variable_len(build());
+
+  let mut modem = build();
+  assert!(modem.last_snr().is_empty());
+  let bytes: Vec<u8> = rand::thread_rng()
+    .sample_iter(Standard)
+    .take(modem.bytes_per_packet())
+    .collect();
+  let encoded = modem.modulate(&bytes);
+  modem.demodulate(&encoded);
+  let clean = modem.last_snr().to_vec();
+  assert_eq!(clean.len(), loading.carriers().len());
+  assert!(clean.iter().all(|(_, snr)| *snr > 40.0));
+
+  let noisy: Vec<_> = encoded
+    .iter()
+    .zip(
+      rand::thread_rng()
+        .sample_iter(Standard)
+        .map(|x: f32| FP::from_f32((x - 0.5) * 0.01)),
+    )
+    .map(|(x, y)| *x + y)
+    .collect();
+  modem.demodulate(&noisy);
+  for ((k, noisy), (_, clean)) in modem.last_snr().iter().zip(&clean) {
+    assert!(
+      noisy < clean,
+      "subcarrier {}: {}dB with noise, {}dB without",
+      k,
+      noisy,
+      clean
+    );
+  }
+
+  let invalid = BitLoading::uniform(Subcarriers::range(0, 4), Constellation::Qpsk);
+  assert!(OFDM::with_loading(&OfdmParams::DEFAULT, invalid, &config).is_err());
+}
+
+/// decode of a frame received with a sampling clock `ppm` fast and slow, the modems being built by `new`
+#[cfg(not(feature = "nofloat"))]
+fn test_clock_offset<T: Modem>(new: impl Fn() -> T, ppm: f64) {
+  use crate::sample_stream::Resampler;
+  for ppm in [ppm, -ppm] {
+    let mut modem = new();
+    let bytes: Vec<u8> = rand::thread_rng()
+      .sample_iter(Standard)
+      .take(modem.bytes_per_packet())
+      .collect();
+    let encoded: Vec<_> = modem.modulate(&bytes).into_iter().map(FP::into_f32).collect();
+    // the first output sample is aligned with the first input sample, the flush pushes out the end of the frame
+    let mut resampler = Resampler::with_ratio(1.0 + ppm * 1e-6);
+    let mut resampled = Vec::new();
+    resampler.process(&encoded, &mut resampled);
+    resampler.flush(&mut resampled);
+    let mut received: Vec<_> = resampled.into_iter().map(FP::from_f32).collect();
+    received.resize(encoded.len(), FP::ZERO);
+    assert_eq!(modem.demodulate(&received), bytes, "{}ppm", ppm);
+  }
+}
+/// OFDM decode of a long frame received with a sampling clock 300ppm fast or slow:
+/// the timing drifts by more than a sample along the frame, the phase of the last subcarriers by a few radians
+#[test]
+#[cfg(not(feature = "nofloat"))]
+fn ofdm_clock_offset() {
+  use super::{OfdmParams, OFDM};
+  const PPM: f64 = 300.0;
+  let config = crate::AudioConfig::default();
+  let params = OfdmParams {
+    encode_symbols: 60,
+    ..Default::default()
+  };
+  test_clock_offset(|| OFDM::with_params(&params, &config).unwrap(), PPM);
+}
+
 /// OFDM encode/decode + ideal transmission through WAV file
 #[test]
 #[cfg(not(feature = "nofloat"))]
@@ -94,20 +351,20 @@ fn ofdm_wav_once() {
   let mut modem = OFDM::new();
   let bytes: Vec<u8> = rand::thread_rng()
     .sample_iter(Standard)
-    .take(OFDM::BYTES_PER_PACKET * MODEM_TESTS)
+    .take(modem.bytes_per_packet() * MODEM_TESTS)
     .collect();
 
-  let mut out_stream = HoundOutStream::create("ofdm_test.wav");
+  let mut out_stream = HoundOutStream::create("ofdm_test.wav").unwrap();
   bytes
-    .chunks_exact(OFDM::BYTES_PER_PACKET)
+    .chunks_exact(modem.bytes_per_packet())
     .for_each(|pack| out_stream.write_exact(&modem.modulate(pack)).unwrap());
   out_stream.finalize();
 
-  let mut in_stream = HoundInStream::open("ofdm_test.wav");
-  let mut received = vec![FP::ZERO; OFDM::SAMPLES_PER_PACKET * MODEM_TESTS];
+  let mut in_stream = HoundInStream::open("ofdm_test.wav").unwrap();
+  let mut received = vec![FP::ZERO; modem.samples_per_packet() * MODEM_TESTS];
   in_stream.read_exact(&mut received).unwrap();
   let decoded: Vec<_> = received
-    .chunks_exact(OFDM::SAMPLES_PER_PACKET)
+    .chunks_exact(modem.samples_per_packet())
     .flat_map(|pack| modem.demodulate(pack))
     .collect();
   assert_eq!(bytes.as_slice(), decoded.as_slice());
@@ -120,6 +377,11 @@ fn lc_ideal() {
     test_ideal(super::LineCode::new());
   }
 }
+/// line code decode of variable length packets in an ideal channel
+#[test]
+fn lc_variable_len() {
+  test_variable_len(super::LineCode::new());
+}
 /// line code decode in noisy channel, where the noise is distributed as Uniform(-1,+1).
 #[test]
 fn lc_noise() {
@@ -127,3 +389,278 @@ fn lc_noise() {
     test_noisy(super::LineCode::new(), Standard);
   }
 }
+
+/// FSK decode in an ideal channel
+#[test]
+fn fsk_ideal() {
+  for _ in 0..MODEM_TESTS {
+    test_ideal(super::FSK::new());
+  }
+}
+/// FSK decode of variable length packets in an ideal channel
+#[test]
+fn fsk_variable_len() {
+  test_variable_len(super::FSK::new());
+}
+/// FSK decode in noisy channel, where the noise is distributed as Uniform(-1,+1).
+#[test]
+fn fsk_noise() {
+  let noise = rand::distributions::Uniform::new(-1.0, 1.0);
+  for _ in 0..MODEM_TESTS {
+    test_noisy(super::FSK::new(), noise);
+  }
+}
+/// FSK decode through a strong echo, inverted and delayed by a fraction of a symbol:
+/// the echo of a tone is out of phase with it, which the energy detection does not mind
+#[test]
+fn fsk_echo() {
+  const DELAY: usize = 20;
+  let mut modem = super::FSK::new();
+  let bytes: Vec<u8> = rand::thread_rng()
+    .sample_iter(Standard)
+    .take(modem.bytes_per_packet())
+    .collect();
+  let encoded = modem.modulate(&bytes);
+  let received: Vec<_> = (0..encoded.len())
+    .map(|i| match i.checked_sub(DELAY) {
+      Some(j) => encoded[i] - encoded[j] * FP::from_f32(0.6),
+      None => encoded[i],
+    })
+    .collect();
+  assert_eq!(modem.demodulate(&received), bytes);
+}
+/// FSK decode of a frame received with a sampling clock 1000ppm fast or slow, as from a moving sender:
+/// the tones shift by a few Hz and the last symbols are read a sixth of a symbol off
+#[test]
+#[cfg(not(feature = "nofloat"))]
+fn fsk_clock_offset() {
+  const PPM: f64 = 1000.0;
+  test_clock_offset(super::FSK::new, PPM);
+}
+
+/// DPSK decode in an ideal channel
+#[test]
+fn dpsk_ideal() {
+  for _ in 0..MODEM_TESTS {
+    test_ideal(super::DPSK::new());
+  }
+}
+/// DPSK decode of variable length packets in an ideal channel
+#[test]
+fn dpsk_variable_len() {
+  test_variable_len(super::DPSK::new());
+}
+/// DPSK decode in noisy channel, where the noise is distributed as Uniform(0,+1).
+#[test]
+fn dpsk_noise() {
+  for _ in 0..MODEM_TESTS {
+    test_noisy(super::DPSK::new(), Standard);
+  }
+}
+/// DPSK decode of a noisy payload cut up to two samples early or late, as by the peak picking of the frame detector
+#[test]
+fn dpsk_timing() {
+  let noise = rand::distributions::Uniform::new(-0.5, 0.5);
+  for shift in -2..=2isize {
+    for _ in 0..MODEM_TESTS / 10 {
+      let mut modem = super::DPSK::new();
+      let bytes: Vec<u8> = rand::thread_rng()
+        .sample_iter(Standard)
+        .take(modem.bytes_per_packet())
+        .collect();
+      let encoded = modem.modulate(&bytes);
+      // the payload starts `shift` samples after the start of the samples received
+      let received: Vec<_> = (0..encoded.len() as isize)
+        .map(|i| match usize::try_from(i - shift) {
+          Ok(j) if j < encoded.len() => encoded[j],
+          _ => FP::ZERO,
+        })
+        .zip(rand::thread_rng().sample_iter(noise).map(FP::from_f32))
+        .map(|(x, y)| x + y)
+        .collect();
+      assert_eq!(modem.demodulate(&received), bytes, "shift {}", shift);
+    }
+  }
+}
+/// DPSK decode of a frame received with a sampling clock 1000ppm fast or slow:
+/// the timing drifts by a third of a symbol along the frame, which the timing recovery follows
+#[test]
+#[cfg(not(feature = "nofloat"))]
+fn dpsk_clock_offset() {
+  const PPM: f64 = 1000.0;
+  test_clock_offset(super::DPSK::new, PPM);
+}
+
+/// every modem gives soft decisions consistent with its hard decisions
+#[test]
+fn soft_decisions() {
+  test_soft(super::PSK::new());
+  test_soft(super::LineCode::new());
+  test_soft(super::FSK::new());
+  test_soft(super::DPSK::new());
+  #[cfg(not(feature = "nofloat"))]
+  test_soft(super::OFDM::new());
+}
+/// in a very noisy channel, the bits decided wrong are less reliable than the bits decided right
+#[test]
+fn soft_reliability() {
+  let noise = rand::distributions::Uniform::new(-2.0, 2.0);
+  let mut modem = super::PSK::new();
+  let bytes: Vec<u8> = rand::thread_rng()
+    .sample_iter(Standard)
+    .take(modem.bytes_per_packet())
+    .collect();
+  let received: Vec<_> = modem
+    .modulate(&bytes)
+    .into_iter()
+    .zip(rand::thread_rng().sample_iter(noise).map(FP::from_f32))
+    .map(|(x, y)| x + y)
+    .collect();
+  let soft = modem.demodulate_soft(&received);
+  let sent = crate::helper::bytes_to_bits(&bytes);
+  let mean = |wrong: bool| {
+    let magnitudes: Vec<_> = soft
+      .iter()
+      .zip(&sent)
+      .filter(|(llr, bit)| ((**llr < 0.0) != (**bit == 1)) == wrong)
+      .map(|(llr, _)| llr.abs())
+      .collect();
+    assert!(!magnitudes.is_empty());
+    magnitudes.iter().sum::<f32>() / magnitudes.len() as f32
+  };
+  assert!(mean(true) < mean(false));
+}
+
+/// modems built with non-default parameters keep the encode/decode identity
+#[test]
+fn custom_params() {
+  use super::{DpskParams, FskParams, LineCode, LineCodeParams, PskParams, DPSK, FSK, PSK};
+  use crate::AudioConfig;
+
+  let config = AudioConfig::default();
+  let psk = PskParams {
+    carrier_freq: 4000.0,
+    samples_per_symbol: 12,
+    symbols_per_packet: 80,
+  };
+  let modem = PSK::with_params(&psk, &config).unwrap();
+  assert_eq!(modem.bytes_per_packet(), 20);
+  test_variable_len(modem);
+  let lc = LineCodeParams {
+    samples_per_bit: 5,
+    bits_per_packet: 160,
+  };
+  test_variable_len(LineCode::with_params(&lc, &config).unwrap());
+  let fsk = FskParams {
+    base_freq: 2000.0,
+    tone_spacing: 375.0,
+    bits_per_symbol: 4,
+    samples_per_symbol: 128,
+    symbols_per_packet: 40,
+  };
+  let modem = FSK::with_params(&fsk, &config).unwrap();
+  assert_eq!(modem.bytes_per_packet(), 20);
+  test_variable_len(modem);
+  let dbpsk = DpskParams {
+    carrier_freq: 3000.0,
+    bits_per_symbol: 1,
+    samples_per_symbol: 20,
+    symbols_per_packet: 160,
+  };
+  let modem = DPSK::with_params(&dbpsk, &config).unwrap();
+  assert_eq!(modem.bytes_per_packet(), 20);
+  test_variable_len(modem);
+  #[cfg(not(feature = "nofloat"))]
+  {
+    use super::{Constellation, Subcarriers};
+    use super::{OfdmParams, OFDM};
+    let ofdm = OfdmParams {
+      n: 128,
+      m: 16,
+      subcarriers: Subcarriers::range(10, 8).without(12).with(30),
+      constellation: Constellation::Qam16,
+      pilots: Subcarriers::EMPTY.with(12).with(40),
+      encode_symbols: 10,
+    };
+    test_variable_len(OFDM::with_params(&ofdm, &config).unwrap());
+  }
+}
+
+/// invalid parameters are rejected
+#[test]
+fn invalid_params() {
+  use super::{DpskParams, FskParams, LineCodeParams, PskParams, PSK};
+  use crate::phy_packet::params::ParamError;
+  use crate::AudioConfig;
+
+  let config = AudioConfig::default();
+  let above_nyquist = PskParams {
+    carrier_freq: config.sample_rate as f32 / 3.0,
+    ..Default::default()
+  };
+  assert!(matches!(
+    PSK::with_params(&above_nyquist, &config),
+    Err(ParamError::Frequency(..))
+  ));
+  let partial_byte = PskParams {
+    symbols_per_packet: 321,
+    ..Default::default()
+  };
+  assert!(matches!(partial_byte.validate(&config), Err(ParamError::Invalid(..))));
+  let no_samples = LineCodeParams {
+    samples_per_bit: 0,
+    ..Default::default()
+  };
+  assert!(matches!(no_samples.validate(&config), Err(ParamError::NotPositive(_))));
+  let too_long = LineCodeParams {
+    bits_per_packet: 8 * 256,
+    ..Default::default()
+  };
+  assert!(too_long.validate(&config).is_err());
+  // no room for the checksums and the headers of the upper layers
+  let too_short = LineCodeParams {
+    bits_per_packet: 8 * 4,
+    ..Default::default()
+  };
+  assert!(matches!(too_short.validate(&config), Err(ParamError::Invalid(..))));
+  let three_bits = FskParams {
+    bits_per_symbol: 3,
+    ..Default::default()
+  };
+  assert!(matches!(three_bits.validate(&config), Err(ParamError::Invalid(..))));
+  let overlapping_tones = FskParams {
+    tone_spacing: 250.0,
+    ..Default::default()
+  };
+  assert!(matches!(
+    overlapping_tones.validate(&config),
+    Err(ParamError::Invalid(..))
+  ));
+  let highest_above_nyquist = FskParams {
+    base_freq: 23000.0,
+    ..Default::default()
+  };
+  assert!(matches!(
+    highest_above_nyquist.validate(&config),
+    Err(ParamError::Frequency(..))
+  ));
+  let short_symbols = DpskParams {
+    samples_per_symbol: 4,
+    ..Default::default()
+  };
+  assert!(matches!(short_symbols.validate(&config), Err(ParamError::Invalid(..))));
+  #[cfg(not(feature = "nofloat"))]
+  {
+    use super::OfdmParams;
+    let not_power_of_two = OfdmParams {
+      n: 48,
+      ..Default::default()
+    };
+    assert!(not_power_of_two.validate(&config).is_err());
+    let dc = OfdmParams {
+      subcarriers: super::Subcarriers::EMPTY.with(0),
+      ..Default::default()
+    };
+    assert!(dc.validate(&config).is_err());
+  }
+}
@@ -0,0 +1,67 @@
+use super::header::LengthHeader;
+use crate::AudioConfig;
+use std::fmt;
+
+/// Invalid runtime parameters of a modem or a preamble
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamError {
+  /// the parameter must be positive
+  NotPositive(&'static str),
+  /// the frequency (Hz) must be positive and below the Nyquist frequency of the sampling rate
+  Frequency(&'static str, f32),
+  /// the parameter breaks a constraint of the modem/preamble, with the constraint
+  Invalid(&'static str, &'static str),
+}
+
+impl fmt::Display for ParamError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::NotPositive(name) => write!(f, "{} must be positive", name),
+      Self::Frequency(name, freq) => write!(f, "{} ({}Hz) must be within (0, sample rate / 2)", name, freq),
+      Self::Invalid(name, constraint) => write!(f, "invalid {}: {}", name, constraint),
+    }
+  }
+}
+
+impl std::error::Error for ParamError {}
+
+/// check a count parameter is positive
+pub(crate) fn positive(name: &'static str, value: usize) -> Result<(), ParamError> {
+  if value > 0 {
+    Ok(())
+  } else {
+    Err(ParamError::NotPositive(name))
+  }
+}
+
+/// check a frequency can be represented at the sampling rate of the audio config
+pub(crate) fn below_nyquist(name: &'static str, freq: f32, config: &AudioConfig) -> Result<(), ParamError> {
+  if freq > 0.0 && freq < config.sample_rate as f32 / 2.0 {
+    Ok(())
+  } else {
+    Err(ParamError::Frequency(name, freq))
+  }
+}
+
+/// The minimum number of bytes in one packet of a modem,
+/// so that the checksums of the PHY layers built on the modem and the headers of the upper layers
+/// leave room for some data.
+pub const MIN_PACKET_BYTES: usize = 8;
+
+/// check a packet of the given number of bytes can be sent with a [`LengthHeader`]
+pub(crate) fn packet_bytes(bytes: usize) -> Result<(), ParamError> {
+  positive("bytes per packet", bytes)?;
+  if bytes < MIN_PACKET_BYTES {
+    Err(ParamError::Invalid(
+      "bytes per packet",
+      "a packet must hold at least MIN_PACKET_BYTES bytes",
+    ))
+  } else if bytes <= LengthHeader::MAX_LEN {
+    Ok(())
+  } else {
+    Err(ParamError::Invalid(
+      "bytes per packet",
+      "a packet can not be longer than the length header allows",
+    ))
+  }
+}
@@ -1,57 +1,241 @@
 use crate::helper::chirp;
 use crate::traits::{Sample, FP};
+use crate::AudioConfig;
 
-use super::{traits::FramePreamble, PreambleGen};
+use super::{
+  params::{below_nyquist, positive, ParamError},
+  traits::FramePreamble,
+  PreambleGen,
+};
 
-/// an chirp signal preamble sequence, the frequency goes up then down.  
-/// **NOTE** Due to lack of rustc features, the following parameters are not configurable.  
+/// implement [`PreambleGen`] for a preamble type with `samples` and `norm` fields and a `new` constructor
+macro_rules! sampled_preamble {
+  ($name:ident) => {
+    impl PreambleGen for $name {
+      fn samples(&self) -> FramePreamble {
+        self.samples.clone()
+      }
+      fn iter(&self) -> std::slice::Iter<'_, FP> {
+        self.samples.iter()
+      }
+      fn norm(&self) -> FP {
+        self.norm
+      }
+
+      fn generate() -> Self {
+        Self::new()
+      }
+    }
+
+    impl Default for $name {
+      fn default() -> Self {
+        Self::new()
+      }
+    }
+  };
+}
+
+mod repeated;
+pub use repeated::{RepeatedHalves, RepeatedHalvesParams};
+mod sequences;
+pub use sequences::{Barker13, CarrierParams, MSequence, MSequenceParams, ZadoffChu, ZadoffChuParams, BARKER_13};
+
+#[cfg(test)]
+mod tests;
+
+// the euclidean norm of the samples
+fn norm(samples: &[FP]) -> FP {
+  samples.iter().fold(FP::ZERO, |s, &x| s + x * x).sqrt()
+}
+
+/// Parameters of [`ChirpUpDown`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChirpParams {
+  /// the lowest frequency
+  pub fa: f32,
+  /// the highest frequency
+  pub fb: f32,
+  /// number of samples, even
+  pub n: usize,
+}
+
+impl ChirpParams {
+  /// the profile for a wired link: a short and wide band chirp
+  pub const WIRED: Self = Self {
+    fa: 2000.0,
+    fb: 12000.0,
+    n: 80,
+  };
+  /// the profile for an acoustic link through the air
+  pub const AIR: Self = Self {
+    fa: 3000.0,
+    fb: 6000.0,
+    n: 440,
+  };
+
+  pub fn validate(&self, config: &AudioConfig) -> Result<(), ParamError> {
+    positive("chirp length", self.n)?;
+    if !self.n.is_multiple_of(2) {
+      return Err(ParamError::Invalid("chirp length", "must be even"));
+    }
+    below_nyquist("chirp lowest frequency", self.fa, config)?;
+    below_nyquist("chirp highest frequency", self.fb, config)?;
+    if self.fa >= self.fb {
+      return Err(ParamError::Invalid(
+        "chirp frequencies",
+        "the lowest frequency must be below the highest",
+      ));
+    }
+    Ok(())
+  }
+}
+
+impl Default for ChirpParams {
+  /// [`ChirpParams::WIRED`] if the `wired` feature is enabled, otherwise [`ChirpParams::AIR`]
+  fn default() -> Self {
+    if cfg!(feature = "wired") {
+      Self::WIRED
+    } else {
+      Self::AIR
+    }
+  }
+}
+
+/// an chirp signal preamble sequence, the frequency goes up then down, see [`ChirpParams`].
 pub struct ChirpUpDown {
   samples: Vec<FP>,
   norm: FP,
 }
 
 impl ChirpUpDown {
-  /// the lowest frequency  
-  pub const FA: f32 = if cfg!(feature = "wired") { 2000.0 } else { 3000.0 };
-  /// the highest frequency  
-  pub const FB: f32 = if cfg!(feature = "wired") { 12000.0 } else { 6000.0 };
-  /// number of samples  
-  pub const N: usize = if cfg!(feature = "wired") { 80 } else { 440 };
-  /// the sampling frequency  
-  pub const FS: usize = 48000;
-
   pub fn new() -> ChirpUpDown {
-    let fa = FP::from_f32(ChirpUpDown::FA);
-    let fb = FP::from_f32(ChirpUpDown::FB);
-    let m = ChirpUpDown::N / 2;
-    let fs = ChirpUpDown::FS;
+    Self::with_config(&AudioConfig::default())
+  }
+
+  /// generate the chirp with the default parameters at the sampling rate of the audio config
+  pub fn with_config(config: &AudioConfig) -> ChirpUpDown {
+    Self::with_params(&ChirpParams::default(), config).expect("invalid default chirp for the audio config")
+  }
+
+  /// generate the chirp with the given parameters at the sampling rate of the audio config
+  pub fn with_params(params: &ChirpParams, config: &AudioConfig) -> Result<ChirpUpDown, ParamError> {
+    params.validate(config)?;
+    let fa = FP::from_f32(params.fa);
+    let fb = FP::from_f32(params.fb);
+    let m = params.n / 2;
+    let fs = config.sample_rate as usize;
 
     let samples: Vec<FP> = chirp(fa, fb, m, fs).chain(chirp(fb, fa, m, fs)).collect();
-    let norm = samples.iter().fold(FP::ZERO, |s, &x| s + x * x).sqrt();
-    Self { samples, norm }
+    Ok(Self {
+      norm: norm(&samples),
+      samples,
+    })
   }
 }
 
-impl Default for ChirpUpDown {
-  fn default() -> Self {
-    Self::new()
+sampled_preamble!(ChirpUpDown);
+
+/// The preamble of a link picked at runtime, see [`AnyPreamble`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PreambleParams {
+  /// a chirp going up then down, see [`ChirpUpDown`]
+  Chirp(ChirpParams),
+  /// a maximum length sequence on a carrier, see [`MSequence`]
+  MSequence(MSequenceParams),
+  /// the Barker code of length 13 on a carrier, see [`Barker13`]
+  Barker13(CarrierParams),
+  /// a Zadoff-Chu sequence on a carrier, see [`ZadoffChu`]
+  ZadoffChu(ZadoffChuParams),
+  /// a training symbol of two identical halves, see [`RepeatedHalves`]
+  RepeatedHalves(RepeatedHalvesParams),
+}
+
+impl PreambleParams {
+  /// Distinct preambles for the nodes sharing a channel: Zadoff-Chu sequences of different roots,
+  /// with a low cross-correlation to tell the sender of a frame, see [`attribute`].
+  pub fn for_node(node: usize) -> Self {
+    Self::ZadoffChu(ZadoffChuParams::for_node(node))
+  }
+
+  pub fn validate(&self, config: &AudioConfig) -> Result<(), ParamError> {
+    match self {
+      Self::Chirp(params) => params.validate(config),
+      Self::MSequence(params) => params.validate(config),
+      Self::Barker13(carrier) => carrier.validate(config),
+      Self::ZadoffChu(params) => params.validate(config),
+      Self::RepeatedHalves(params) => params.validate(config),
+    }
   }
 }
 
-impl PreambleGen for ChirpUpDown {
-  const PREAMBLE_LEN: usize = ChirpUpDown::N;
+impl Default for PreambleParams {
+  /// the default chirp, see [`ChirpParams::default`]
+  fn default() -> Self {
+    Self::Chirp(ChirpParams::default())
+  }
+}
 
-  fn samples(&self) -> FramePreamble {
-    self.samples.clone()
+impl From<ChirpParams> for PreambleParams {
+  fn from(params: ChirpParams) -> Self {
+    Self::Chirp(params)
   }
-  fn iter(&self) -> std::slice::Iter<FP> {
-    self.samples.iter()
+}
+
+/// A preamble of any family, picked at runtime with [`PreambleParams`],
+/// e.g. for the PHY layers to select their preamble without recompiling.
+pub struct AnyPreamble {
+  samples: Vec<FP>,
+  norm: FP,
+}
+
+impl AnyPreamble {
+  pub fn new() -> AnyPreamble {
+    Self::with_config(&AudioConfig::default())
   }
-  fn norm(&self) -> FP {
-    self.norm
+
+  /// generate the default preamble at the sampling rate of the audio config
+  pub fn with_config(config: &AudioConfig) -> AnyPreamble {
+    Self::with_params(&PreambleParams::default(), config).expect("invalid default preamble for the audio config")
   }
 
-  fn generate() -> Self {
-    Self::new()
+  /// generate the preamble of the given family and parameters at the sampling rate of the audio config
+  pub fn with_params(params: &PreambleParams, config: &AudioConfig) -> Result<AnyPreamble, ParamError> {
+    let samples = match params {
+      PreambleParams::Chirp(params) => ChirpUpDown::with_params(params, config)?.samples(),
+      PreambleParams::MSequence(params) => MSequence::with_params(params, config)?.samples(),
+      PreambleParams::Barker13(carrier) => Barker13::with_params(carrier, config)?.samples(),
+      PreambleParams::ZadoffChu(params) => ZadoffChu::with_params(params, config)?.samples(),
+      PreambleParams::RepeatedHalves(params) => RepeatedHalves::with_params(params, config)?.samples(),
+    };
+    Ok(Self {
+      norm: norm(&samples),
+      samples,
+    })
   }
 }
+
+sampled_preamble!(AnyPreamble);
+
+/// Tell which preamble ends the window of samples, e.g. the samples up to a correlation peak,
+/// to attribute a frame to its sender before demodulating it.
+/// Return the index of the preamble with the highest normalized correlation,
+/// `None` if no preamble reaches `min_corr` (within `[0, 1]`) or the window is shorter than the preambles.
+pub fn attribute<PG: PreambleGen>(window: &[FP], preambles: &[PG], min_corr: f32) -> Option<usize> {
+  let corr = |preamble: &PG| {
+    let len = preamble.preamble_len();
+    let window = window.get(window.len().checked_sub(len)?..)?;
+    let dot = window
+      .iter()
+      .zip(preamble.iter())
+      .fold(0.0, |s, (x, y)| s + x.into_f32() * y.into_f32());
+    let power = window.iter().fold(0.0, |s, x| s + x.into_f32() * x.into_f32());
+    (power > 0.0).then(|| dot / (power.sqrt() * preamble.norm().into_f32()))
+  };
+  preambles
+    .iter()
+    .enumerate()
+    .filter_map(|(i, preamble)| Some((i, corr(preamble)?)))
+    .filter(|&(_, corr)| corr >= min_corr)
+    .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    .map(|(i, _)| i)
+}
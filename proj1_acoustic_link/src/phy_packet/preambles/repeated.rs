@@ -0,0 +1,103 @@
+use super::{norm, FramePreamble, PreambleGen};
+use crate::phy_packet::params::{positive, ParamError};
+use crate::traits::{Sample, FP};
+use crate::AudioConfig;
+
+/// Parameters of [`RepeatedHalves`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RepeatedHalvesParams {
+  /// number of samples of a half
+  pub half_len: usize,
+  /// the lowest frequency bin of a half, in multiples of `sample rate / half_len`
+  pub first_bin: usize,
+  /// number of bins from the lowest one
+  pub bins: usize,
+}
+
+impl RepeatedHalvesParams {
+  /// the profile for a wired link: the 14 bins of 750Hz from 2.25kHz at 48kHz
+  pub const WIRED: Self = Self {
+    half_len: 64,
+    first_bin: 3,
+    bins: 14,
+  };
+  /// the profile for an acoustic link through the air: the 8 bins of 375Hz from 3kHz at 48kHz
+  pub const AIR: Self = Self {
+    half_len: 128,
+    first_bin: 8,
+    bins: 8,
+  };
+
+  /// the bins are relative to the sampling rate, the audio config is not checked
+  pub fn validate(&self, _config: &AudioConfig) -> Result<(), ParamError> {
+    positive("repeated half length", self.half_len)?;
+    positive("repeated half bins", self.bins)?;
+    if self.first_bin == 0 || 2 * (self.first_bin + self.bins) > self.half_len {
+      return Err(ParamError::Invalid(
+        "repeated half bins",
+        "must be above DC and below the Nyquist frequency",
+      ));
+    }
+    Ok(())
+  }
+}
+
+impl Default for RepeatedHalvesParams {
+  /// [`RepeatedHalvesParams::WIRED`] if the `wired` feature is enabled, otherwise [`RepeatedHalvesParams::AIR`]
+  fn default() -> Self {
+    if cfg!(feature = "wired") {
+      Self::WIRED
+    } else {
+      Self::AIR
+    }
+  }
+}
+
+/// A training symbol of two identical halves, for the delayed autocorrelation of
+/// [`crate::phy_packet::frame_detect::SchmidlCox`]:
+/// each half is a multitone on whole bins of its length, with quadratic phases for a low peak to average ratio.
+pub struct RepeatedHalves {
+  samples: Vec<FP>,
+  norm: FP,
+}
+
+impl RepeatedHalves {
+  pub fn new() -> RepeatedHalves {
+    Self::with_config(&AudioConfig::default())
+  }
+
+  /// generate the symbol with the default parameters at the sampling rate of the audio config
+  pub fn with_config(config: &AudioConfig) -> RepeatedHalves {
+    Self::with_params(&RepeatedHalvesParams::default(), config)
+      .expect("invalid default repeated halves for the audio config")
+  }
+
+  /// generate the symbol with the given parameters at the sampling rate of the audio config
+  pub fn with_params(params: &RepeatedHalvesParams, config: &AudioConfig) -> Result<RepeatedHalves, ParamError> {
+    params.validate(config)?;
+    let RepeatedHalvesParams {
+      half_len,
+      first_bin,
+      bins,
+    } = *params;
+    let half: Vec<f32> = (0..half_len)
+      .map(|t| {
+        (0..bins)
+          .map(|j| {
+            // the phase in turns within [0, 1), where the fixed point sine is accurate
+            let turns = ((first_bin + j) * t) as f32 / half_len as f32 + (j * j) as f32 / (2 * bins) as f32;
+            (FP::TAU * FP::from_f32(turns.rem_euclid(1.0))).sin().into_f32()
+          })
+          .sum()
+      })
+      .collect();
+    let peak = half.iter().fold(f32::MIN_POSITIVE, |peak, x| peak.max(x.abs()));
+    let samples: Vec<FP> = half.iter().chain(&half).map(|x| FP::from_f32(x / peak)).collect();
+    Ok(Self {
+      norm: norm(&samples),
+      samples,
+    })
+  }
+}
+
+sampled_preamble!(RepeatedHalves);
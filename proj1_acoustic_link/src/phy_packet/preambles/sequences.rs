@@ -0,0 +1,304 @@
+use super::{norm, FramePreamble, PreambleGen};
+use crate::phy_packet::params::{below_nyquist, positive, ParamError};
+use crate::traits::{Sample, FP};
+use crate::AudioConfig;
+use std::f32::consts::{PI, TAU};
+use std::iter::repeat_n;
+
+/// the Barker code of length 13: the aperiodic autocorrelation sidelobes are at most 1
+pub const BARKER_13: [i8; 13] = [1, 1, 1, 1, 1, -1, -1, 1, 1, -1, 1, -1, 1];
+
+/// length of the Zadoff-Chu sequences of [`ZadoffChuParams::for_node`], a prime for 30 roots
+const NODE_ZC_LEN: usize = 31;
+
+/// The carrier a chip sequence is modulated onto:
+/// each chip shifts the phase of the carrier for a number of samples.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CarrierParams {
+  /// frequency of the carrier
+  pub freq: f32,
+  /// number of samples of a chip
+  pub samples_per_chip: usize,
+}
+
+impl CarrierParams {
+  /// the profile for a wired link: short chips on a carrier within the band of [`super::ChirpParams::WIRED`]
+  pub const WIRED: Self = Self {
+    freq: 6000.0,
+    samples_per_chip: 4,
+  };
+  /// the profile for an acoustic link through the air: 3kHz chips within the band of [`super::ChirpParams::AIR`]
+  pub const AIR: Self = Self {
+    freq: 4500.0,
+    samples_per_chip: 16,
+  };
+
+  pub fn validate(&self, config: &AudioConfig) -> Result<(), ParamError> {
+    positive("samples per chip", self.samples_per_chip)?;
+    below_nyquist("carrier frequency", self.freq, config)
+  }
+
+  // the samples of the chips, each chip given by its phase shift in radians
+  fn modulate(&self, phases: impl Iterator<Item = f32>, config: &AudioConfig) -> Vec<FP> {
+    let cycles_per_sample = self.freq / config.sample_rate as f32;
+    phases
+      .flat_map(|phase| repeat_n(phase, self.samples_per_chip))
+      .enumerate()
+      .map(|(i, phase)| {
+        // the phase in turns within [0, 1), where the fixed point sine is accurate
+        let turns = (i as f32 * cycles_per_sample + phase / TAU).rem_euclid(1.0);
+        (FP::TAU * FP::from_f32(turns)).sin()
+      })
+      .collect()
+  }
+
+  // the samples of binary chips: the carrier for a `1`, the carrier inverted for a `-1`
+  fn modulate_binary(&self, chips: &[i8], config: &AudioConfig) -> Vec<FP> {
+    self.modulate(chips.iter().map(|&chip| if chip > 0 { 0.0 } else { PI }), config)
+  }
+}
+
+impl Default for CarrierParams {
+  /// [`CarrierParams::WIRED`] if the `wired` feature is enabled, otherwise [`CarrierParams::AIR`]
+  fn default() -> Self {
+    if cfg!(feature = "wired") {
+      Self::WIRED
+    } else {
+      Self::AIR
+    }
+  }
+}
+
+/// Parameters of [`MSequence`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MSequenceParams {
+  /// degree of the feedback polynomial, the sequence has `2^degree - 1` chips
+  pub degree: u32,
+  /// Coefficients of the primitive feedback polynomial below the leading term:
+  /// bit `i` for `x^i`, e.g. `0b00101` for `x^5 + x^2 + 1`.
+  pub taps: u32,
+  pub carrier: CarrierParams,
+}
+
+impl MSequenceParams {
+  /// the primitive feedback polynomials of degree 5, see [`MSequenceParams::taps`]
+  pub const POLYNOMIALS_5: [u32; 6] = [0b00101, 0b01001, 0b01111, 0b10111, 0b11011, 0b11101];
+
+  /// a distinct sequence of 31 chips for each of up to 6 nodes, on the default carrier
+  pub fn for_node(node: usize) -> Self {
+    Self {
+      degree: 5,
+      taps: Self::POLYNOMIALS_5[node % Self::POLYNOMIALS_5.len()],
+      carrier: CarrierParams::default(),
+    }
+  }
+
+  pub fn validate(&self, config: &AudioConfig) -> Result<(), ParamError> {
+    if !(2..=16).contains(&self.degree) {
+      return Err(ParamError::Invalid("m-sequence degree", "must be within 2 and 16"));
+    }
+    if self.taps >> self.degree != 0 || self.taps & 1 == 0 || self.period() != self.chips_len() {
+      return Err(ParamError::Invalid(
+        "m-sequence taps",
+        "must be a primitive polynomial of the degree",
+      ));
+    }
+    self.carrier.validate(config)
+  }
+
+  /// number of chips of the sequence
+  pub fn chips_len(&self) -> usize {
+    (1 << self.degree) - 1
+  }
+
+  /// The chips of the sequence, `1` or `-1`.
+  /// The periodic autocorrelation is `-1` at every non-zero shift.
+  pub fn chips(&self) -> Vec<i8> {
+    let mut state = self.chips_len() as u32;
+    (0..self.chips_len())
+      .map(|_| {
+        let chip = 1 - 2 * (state & 1) as i8;
+        state = self.shift(state);
+        chip
+      })
+      .collect()
+  }
+
+  // the next state of the shift register, the bit `i` of the state is the chip `n + i`
+  fn shift(&self, state: u32) -> u32 {
+    let feedback = (state & self.taps).count_ones() & 1;
+    (state >> 1) | (feedback << (self.degree - 1))
+  }
+
+  // number of shifts until the register is back to its initial state, the register is invertible with tap 0
+  fn period(&self) -> usize {
+    let start = self.chips_len() as u32;
+    let mut state = self.shift(start);
+    let mut period = 1;
+    while state != start {
+      state = self.shift(state);
+      period += 1;
+    }
+    period
+  }
+}
+
+impl Default for MSequenceParams {
+  fn default() -> Self {
+    Self::for_node(0)
+  }
+}
+
+/// A maximum length sequence preamble: a pseudo-noise sequence from a linear feedback shift register
+/// on a carrier, see [`MSequenceParams`].
+pub struct MSequence {
+  samples: Vec<FP>,
+  norm: FP,
+}
+
+impl MSequence {
+  pub fn new() -> MSequence {
+    Self::with_config(&AudioConfig::default())
+  }
+
+  /// generate the sequence with the default parameters at the sampling rate of the audio config
+  pub fn with_config(config: &AudioConfig) -> MSequence {
+    Self::with_params(&MSequenceParams::default(), config).expect("invalid default m-sequence for the audio config")
+  }
+
+  /// generate the sequence with the given parameters at the sampling rate of the audio config
+  pub fn with_params(params: &MSequenceParams, config: &AudioConfig) -> Result<MSequence, ParamError> {
+    params.validate(config)?;
+    let samples = params.carrier.modulate_binary(&params.chips(), config);
+    Ok(Self {
+      norm: norm(&samples),
+      samples,
+    })
+  }
+}
+
+sampled_preamble!(MSequence);
+
+/// The Barker code of length 13 preamble on a carrier, see [`BARKER_13`] and [`CarrierParams`].
+/// There is a single code: the nodes sharing a channel need distinct carriers to tell each other apart.
+pub struct Barker13 {
+  samples: Vec<FP>,
+  norm: FP,
+}
+
+impl Barker13 {
+  pub fn new() -> Barker13 {
+    Self::with_config(&AudioConfig::default())
+  }
+
+  /// generate the code on the default carrier at the sampling rate of the audio config
+  pub fn with_config(config: &AudioConfig) -> Barker13 {
+    Self::with_params(&CarrierParams::default(), config).expect("invalid default carrier for the audio config")
+  }
+
+  /// generate the code on the given carrier at the sampling rate of the audio config
+  pub fn with_params(carrier: &CarrierParams, config: &AudioConfig) -> Result<Barker13, ParamError> {
+    carrier.validate(config)?;
+    let samples = carrier.modulate_binary(&BARKER_13, config);
+    Ok(Self {
+      norm: norm(&samples),
+      samples,
+    })
+  }
+}
+
+sampled_preamble!(Barker13);
+
+/// Parameters of [`ZadoffChu`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ZadoffChuParams {
+  /// number of chips, odd
+  pub len: usize,
+  /// the root of the sequence, coprime with the length
+  pub root: usize,
+  pub carrier: CarrierParams,
+}
+
+impl ZadoffChuParams {
+  /// a distinct root of a sequence of 31 chips for each of up to 30 nodes, on the default carrier
+  pub fn for_node(node: usize) -> Self {
+    Self {
+      len: NODE_ZC_LEN,
+      root: node % (NODE_ZC_LEN - 1) + 1,
+      carrier: CarrierParams::default(),
+    }
+  }
+
+  pub fn validate(&self, config: &AudioConfig) -> Result<(), ParamError> {
+    positive("Zadoff-Chu length", self.len)?;
+    if self.len.is_multiple_of(2) {
+      return Err(ParamError::Invalid("Zadoff-Chu length", "must be odd"));
+    }
+    if !(1..self.len).contains(&self.root) || gcd(self.root, self.len) != 1 {
+      return Err(ParamError::Invalid(
+        "Zadoff-Chu root",
+        "must be below the length and coprime with it",
+      ));
+    }
+    self.carrier.validate(config)
+  }
+
+  /// The phases in radians of the chips `exp(-i * pi * root * k * (k + 1) / len)`.
+  /// The periodic autocorrelation is zero at every non-zero shift,
+  /// the periodic cross-correlation of two roots of a prime length is `sqrt(len)` at every shift.
+  pub fn phases(&self) -> Vec<f32> {
+    let (len, root) = (self.len as u64, self.root as u64);
+    (0..len)
+      .map(|k| {
+        // k * (k + 1) / 2 is whole, the phase is a whole number of turns / len
+        let index = root * (k * (k + 1) / 2 % len) % len;
+        -TAU * index as f32 / len as f32
+      })
+      .collect()
+  }
+}
+
+impl Default for ZadoffChuParams {
+  fn default() -> Self {
+    Self::for_node(0)
+  }
+}
+
+/// A Zadoff-Chu sequence preamble: a constant amplitude sequence of chirp-like phases on a carrier,
+/// see [`ZadoffChuParams`].
+pub struct ZadoffChu {
+  samples: Vec<FP>,
+  norm: FP,
+}
+
+impl ZadoffChu {
+  pub fn new() -> ZadoffChu {
+    Self::with_config(&AudioConfig::default())
+  }
+
+  /// generate the sequence with the default parameters at the sampling rate of the audio config
+  pub fn with_config(config: &AudioConfig) -> ZadoffChu {
+    Self::with_params(&ZadoffChuParams::default(), config)
+      .expect("invalid default Zadoff-Chu sequence for the audio config")
+  }
+
+  /// generate the sequence with the given parameters at the sampling rate of the audio config
+  pub fn with_params(params: &ZadoffChuParams, config: &AudioConfig) -> Result<ZadoffChu, ParamError> {
+    params.validate(config)?;
+    let samples = params.carrier.modulate(params.phases().into_iter(), config);
+    Ok(Self {
+      norm: norm(&samples),
+      samples,
+    })
+  }
+}
+
+sampled_preamble!(ZadoffChu);
+
+fn gcd(a: usize, b: usize) -> usize {
+  if b == 0 {
+    a
+  } else {
+    gcd(b, a % b)
+  }
+}
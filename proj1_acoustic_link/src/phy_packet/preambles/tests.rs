@@ -0,0 +1,185 @@
+use super::{attribute, AnyPreamble, CarrierParams, MSequenceParams, PreambleParams, ZadoffChuParams, BARKER_13};
+use crate::phy_packet::{params::ParamError, PreambleGen};
+use crate::traits::{Sample, FP};
+use crate::AudioConfig;
+use rand::Rng;
+
+fn periodic_autocorrelation(chips: &[i8], shift: usize) -> i32 {
+  (0..chips.len())
+    .map(|i| (chips[i] * chips[(i + shift) % chips.len()]) as i32)
+    .sum()
+}
+
+/// the magnitude of the periodic correlation of two sequences of phases
+fn periodic_correlation(a: &[f32], b: &[f32], shift: usize) -> f32 {
+  let (re, im) = (0..a.len()).fold((0.0, 0.0), |(re, im), i| {
+    let phase = a[i] - b[(i + shift) % b.len()];
+    (re + phase.cos(), im + phase.sin())
+  });
+  (re * re + im * im).sqrt()
+}
+
+#[test]
+fn barker_autocorrelation() {
+  let n = BARKER_13.len();
+  for shift in 1..n {
+    let sidelobe: i32 = (0..n - shift)
+      .map(|i| (BARKER_13[i] * BARKER_13[i + shift]) as i32)
+      .sum();
+    assert!(sidelobe.abs() <= 1, "shift {}: {}", shift, sidelobe);
+  }
+}
+
+/// the primitive polynomials give maximum length sequences, the others are rejected
+#[test]
+fn msequence_autocorrelation() {
+  let config = AudioConfig::default();
+  for node in 0..MSequenceParams::POLYNOMIALS_5.len() {
+    let params = MSequenceParams::for_node(node);
+    params.validate(&config).unwrap();
+    let chips = params.chips();
+    assert_eq!(chips.len(), 31);
+    assert_eq!(periodic_autocorrelation(&chips, 0), 31);
+    (1..31).for_each(|shift| assert_eq!(periodic_autocorrelation(&chips, shift), -1, "{:#b}", params.taps));
+  }
+
+  let long = MSequenceParams {
+    degree: 7,
+    taps: 0b11,
+    ..Default::default()
+  };
+  long.validate(&config).unwrap();
+  assert_eq!(long.chips().len(), 127);
+
+  // x^5 + x + 1 = (x^2 + x + 1)(x^3 + x^2 + 1)
+  let reducible = MSequenceParams {
+    taps: 0b00011,
+    ..Default::default()
+  };
+  assert!(matches!(reducible.validate(&config), Err(ParamError::Invalid(..))));
+}
+
+/// zero autocorrelation at non-zero shifts, constant cross-correlation between the roots of a prime length
+#[test]
+fn zadoff_chu_correlation() {
+  let sequences: Vec<_> = (0..4).map(|node| ZadoffChuParams::for_node(node).phases()).collect();
+  for (i, a) in sequences.iter().enumerate() {
+    assert!((periodic_correlation(a, a, 0) - 31.0).abs() < 1e-3);
+    (1..31).for_each(|shift| assert!(periodic_correlation(a, a, shift) < 1e-3));
+    for b in sequences.iter().skip(i + 1) {
+      (0..31).for_each(|shift| assert!((periodic_correlation(a, b, shift) - 31f32.sqrt()).abs() < 1e-3));
+    }
+  }
+
+  let config = AudioConfig::default();
+  let even = ZadoffChuParams {
+    len: 30,
+    ..Default::default()
+  };
+  assert!(even.validate(&config).is_err());
+  let not_coprime = ZadoffChuParams {
+    len: 15,
+    root: 5,
+    ..Default::default()
+  };
+  assert!(not_coprime.validate(&config).is_err());
+}
+
+/// every family is generated at runtime with the carrier checked against the audio config
+#[test]
+fn any_preamble() {
+  let config = AudioConfig::default();
+  let carrier = CarrierParams::default();
+  let families = [
+    PreambleParams::default(),
+    PreambleParams::MSequence(Default::default()),
+    PreambleParams::Barker13(carrier),
+    PreambleParams::ZadoffChu(Default::default()),
+    PreambleParams::RepeatedHalves(Default::default()),
+  ];
+  let chips = [None, Some(31), Some(13), Some(31), None];
+  for (params, chips) in families.iter().zip(chips) {
+    let preamble = AnyPreamble::with_params(params, &config).unwrap();
+    if let Some(chips) = chips {
+      assert_eq!(preamble.preamble_len(), chips * carrier.samples_per_chip);
+    }
+    let energy: f32 = preamble.iter().map(|x| x.into_f32() * x.into_f32()).sum();
+    assert!((preamble.norm().into_f32() - energy.sqrt()).abs() < 1e-2);
+  }
+
+  let invalid = PreambleParams::Barker13(CarrierParams {
+    freq: config.sample_rate as f32,
+    ..carrier
+  });
+  assert!(matches!(
+    AnyPreamble::with_params(&invalid, &config),
+    Err(ParamError::Frequency(..))
+  ));
+}
+
+/// the sender of a frame is told by its preamble in noise, no sender is found in the noise alone
+#[test]
+fn attribute_nodes() {
+  let config = AudioConfig::default();
+  let preambles: Vec<_> = (0..4)
+    .map(|node| AnyPreamble::with_params(&PreambleParams::for_node(node), &config).unwrap())
+    .collect();
+  let mut rng = rand::thread_rng();
+  let mut noise = |len| -> Vec<FP> { (0..len).map(|_| FP::from_f32(rng.gen_range(-0.1..0.1))).collect() };
+
+  for (node, preamble) in preambles.iter().enumerate() {
+    let mut window = noise(50);
+    window.extend(
+      preamble
+        .iter()
+        .zip(noise(preamble.preamble_len()))
+        .map(|(&x, n)| x * FP::from_f32(0.5) + n),
+    );
+    assert_eq!(attribute(&window, &preambles, 0.5), Some(node));
+  }
+  assert_eq!(attribute(&noise(200), &preambles, 0.5), None);
+  assert_eq!(attribute(&noise(10), &preambles, 0.0), None);
+}
+
+/// a link carries packets with a preamble of every family picked at runtime
+#[test]
+#[cfg(feature = "wired")]
+fn preamble_families() {
+  use crate::phy_layer::{PhyParams, PlainPHY};
+  use crate::sample_stream::VirtualAir;
+  use crate::traits::{PacketReceiver, PacketSender};
+  use rand::distributions::Standard;
+  use std::time::Duration;
+
+  let families = [
+    PreambleParams::MSequence(Default::default()),
+    PreambleParams::Barker13(CarrierParams::default()),
+    PreambleParams::ZadoffChu(Default::default()),
+    PreambleParams::for_node(3),
+  ];
+  for preamble in families {
+    let air = VirtualAir::new(2);
+    let params = PhyParams {
+      preamble,
+      ..Default::default()
+    };
+    let mut phys: Vec<_> = (0..2)
+      .map(|id| {
+        let (stream_in, stream_out, power_probe) = air.node(id);
+        <PlainPHY>::with_streams_params(stream_in, stream_out, power_probe, &Default::default(), &params).unwrap()
+      })
+      .collect();
+
+    let packet: Vec<u8> = rand::thread_rng()
+      .sample_iter(Standard)
+      .take(phys[0].packet_bytes() / 2)
+      .collect();
+    phys[0].send(packet.clone()).unwrap();
+    assert_eq!(
+      phys[1].recv_timeout(Duration::from_millis(500)).unwrap(),
+      packet,
+      "{:?}",
+      preamble
+    );
+  }
+}
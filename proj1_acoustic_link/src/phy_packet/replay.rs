@@ -0,0 +1,90 @@
+use super::{frame_detect::CorrelationFraming, FrameDetector, Modem, PhyPacket, PreambleGen};
+use crate::{
+  helper::SEQ_MOD,
+  traits::{InStream, Sample, FP},
+};
+
+/// A frame found in a recording by [`replay`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayFrame {
+  /// index of the first sample of the frame (the preamble) in the recording
+  pub offset: usize,
+  /// the correlation peak of the preamble, see [`CorrelationFraming::last_peak`]
+  pub peak: f32,
+  /// the demodulated packet
+  pub packet: PhyPacket,
+}
+
+/// Run the receive chain on a finite stream, e.g. a wav capture, until the end of the stream.
+/// Return every frame detected by the given detector with the packet demodulated,
+/// the detector is usually built by [`CorrelationFraming::with_modem`] to read the length header.
+///
+/// Unlike [`super::txrx::PhyReceiver`], everything is done in the calling thread,
+/// so the result does not depend on the timing of a worker.
+pub fn replay<PG, MM, SS, E>(
+  mut stream_in: SS,
+  mut detector: CorrelationFraming<PG>,
+  mut modem: MM,
+) -> Result<Vec<ReplayFrame>, E>
+where
+  PG: PreambleGen,
+  MM: Modem,
+  SS: InStream<FP, E>,
+{
+  const CHUNK: usize = 4096;
+  let mut buf = vec![FP::ZERO; CHUNK];
+  let mut frames = Vec::new();
+  let mut samples = 0;
+  loop {
+    let n = stream_in.read(&mut buf)?;
+    if n == 0 {
+      return Ok(frames);
+    }
+    for x in &buf[..n] {
+      samples += 1;
+      if let Some(payload) = detector.on_sample(*x) {
+        let mut packet = modem.demodulate(&payload);
+        if let Some(bytes) = detector.payload_bytes() {
+          packet.truncate(bytes);
+        }
+        frames.push(ReplayFrame {
+          offset: samples
+            - detector.last_lag()
+            - payload.len()
+            - detector.header_samples()
+            - detector.preamble().preamble_len(),
+          peak: detector.last_peak().into_f32(),
+          packet,
+        });
+      }
+    }
+  }
+}
+
+/// Pair the frames found by [`replay`] with the packets sent, return the index of the packet sent for each frame.
+///
+/// A frame whose sequence number can be read by `seq`, e.g. with [`crate::helper::CrcSeq::unpack`],
+/// is paired with the next packet sent with the same sequence number,
+/// so the frames after a lost packet are still paired correctly
+/// as long as less than [`SEQ_MOD`] packets in a row are lost.
+/// A frame whose sequence number matches none of these packets is left unpaired (`None`), e.g. a false detection.
+/// A frame without a sequence number, e.g. a corrupted one, is paired with the next packet.
+pub fn align<F>(frames: &[ReplayFrame], sent: &[PhyPacket], seq: F) -> Vec<Option<usize>>
+where
+  F: Fn(&[u8]) -> Option<u8>,
+{
+  let mut next = 0;
+  frames
+    .iter()
+    .map(|frame| {
+      let index = match seq(&frame.packet) {
+        Some(n) => (next..sent.len().min(next + SEQ_MOD as usize)).find(|&i| seq(&sent[i]) == Some(n)),
+        None => (next < sent.len()).then_some(next),
+      };
+      if let Some(index) = index {
+        next = index + 1;
+      }
+      index
+    })
+    .collect()
+}
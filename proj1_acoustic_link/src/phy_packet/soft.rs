@@ -0,0 +1,30 @@
+use super::{PhyPacket, SoftBits};
+use crate::helper::{bits_to_bytes, bytes_to_bits};
+
+/// the bytes of the hard decisions on the soft bits, a negative soft bit is a `1`
+pub fn hard_decision(soft: &[f32]) -> PhyPacket {
+  let bits: Vec<_> = soft.iter().map(|llr| (*llr < 0.0) as u8).collect();
+  bits_to_bytes(&bits)
+}
+
+/// the soft bits of hard decided bytes, `+1` for a `0` and `-1` for a `1`
+pub fn from_hard(bytes: &[u8]) -> SoftBits {
+  bytes_to_bits(bytes)
+    .into_iter()
+    .map(|bit| if bit == 0 { 1.0 } else { -1.0 })
+    .collect()
+}
+
+/// Link quality of a packet: the SNR (dB) of the magnitudes of its soft bits, their squared mean over their variance.
+/// It does not depend on the scale of the soft bits.
+/// Return `None` if there is no soft bit, or if they are all as reliable so that the SNR is infinite,
+/// e.g. the hard decisions of a modem without soft information.
+pub fn quality_db(soft: &[f32]) -> Option<f32> {
+  if soft.is_empty() {
+    return None;
+  }
+  let n = soft.len() as f32;
+  let mean = soft.iter().map(|llr| llr.abs()).sum::<f32>() / n;
+  let variance = soft.iter().map(|llr| (llr.abs() - mean).powi(2)).sum::<f32>() / n;
+  (variance > 0.0).then(|| 10.0 * (mean * mean / variance).log10())
+}
@@ -6,34 +6,57 @@ pub type PhyPacket = Vec<u8>;
 pub type FramePreamble = Vec<FP>;
 /// the sequence of PCM samples used to encode bytes of a [`PhyPacket`] in acoustic channel.
 pub type FramePayload = Vec<FP>;
+/// Soft decisions on the bits of a [`PhyPacket`], in the order of [`crate::helper::bytes_to_bits`]:
+/// the log-likelihood ratio `ln(P(bit = 0) / P(bit = 1))` of each bit up to a positive scale,
+/// positive for a `0`, negative for a `1`, larger in magnitude for a more reliable bit.
+pub type SoftBits = Vec<f32>;
 
 /// types that can generate preamble sequence
 pub trait PreambleGen {
   /// number of samples in the preamble sequence
-  const PREAMBLE_LEN: usize;
+  fn preamble_len(&self) -> usize {
+    self.iter().len()
+  }
 
-  /// generate the preamble samples, should contain exactly [`Self::PREAMBLE_LEN`] samples.
+  /// generate the preamble samples, should contain exactly [`Self::preamble_len`] samples.
   fn samples(&self) -> FramePreamble;
   fn norm(&self) -> FP;
+  /// the preamble with the default parameters
   fn generate() -> Self;
   fn iter(&self) -> std::slice::Iter<FP>;
 }
 
 /// type traits for encoding/decoding [`PhyPacket`]
 pub trait Modem: Default {
-  /// number of bytes in one packet
-  const BYTES_PER_PACKET: usize;
-  /// number of samples in one packet
-  const SAMPLES_PER_PACKET: usize;
+  /// maximum number of bytes in one packet
+  fn bytes_per_packet(&self) -> usize;
+  /// number of samples in a packet of [`Self::bytes_per_packet`] bytes
+  fn samples_per_packet(&self) -> usize {
+    self.samples_for(self.bytes_per_packet())
+  }
 
+  /// Number of samples to encode a packet of `bytes` bytes.
+  /// `bytes` should be no more than [`Self::bytes_per_packet`].
+  fn samples_for(&self, bytes: usize) -> usize;
   /// Encode a chunk of bytes into a sequence of PCM samples.  
-  /// The given data should have exactly [`Self::BYTES_PER_PACKET`] bytes.
-  /// The returned sequence should have exactly [`Self::SAMPLES_PER_PACKET`] samples.
+  /// The given data should have no more than [`Self::bytes_per_packet`] bytes.
+  /// The returned sequence should have exactly [`Self::samples_for`] samples of the data length.
   fn modulate(&mut self, bytes: &[u8]) -> FramePayload;
   /// Decode a chunk of bytes from a sequence of PCM samples.  
-  /// The given sequence should have exactly [`Self::SAMPLES_PER_PACKET`] samples.
-  /// The return data should have exactly [`Self::BYTES_PER_PACKET`] bytes.
+  /// The given sequence should have exactly [`Self::samples_for`] samples of some data length,
+  /// the return data should have at least that number of bytes:
+  /// a modem padding the data to whole symbols may return the padding bytes too,
+  /// the receiver truncates them to the length found by [`FrameDetector::payload_bytes`].
   fn demodulate(&mut self, samples: &[FP]) -> PhyPacket;
+  /// Decode the bits of a chunk with their reliability from a sequence of PCM samples, see [`SoftBits`].
+  /// The samples and the number of bits are those of [`Self::demodulate`].
+  /// A modem without soft information returns its hard decisions with a unit magnitude.
+  fn demodulate_soft(&mut self, samples: &[FP]) -> SoftBits {
+    crate::phy_packet::soft::from_hard(&self.demodulate(samples))
+  }
+  /// Correct the carrier frequency offset (cycles per sample) of the next chunks demodulated,
+  /// e.g. estimated by [`FrameDetector::frequency_offset`]. A modem without frequency correction ignores it.
+  fn set_frequency_offset(&mut self, _offset: f32) {}
 }
 
 /// type traits for frame detector strategy
@@ -41,4 +64,47 @@ pub trait FrameDetector {
   /// Update the detector state when a new sample is received.  
   /// Return the a frame payload section if we detect any frame.
   fn on_sample(&mut self, sample: FP) -> Option<FramePayload>;
+
+  /// Number of bytes of the last payload found, if the frames carry their length, e.g. in a length header
+  fn payload_bytes(&self) -> Option<usize> {
+    None
+  }
+
+  /// The carrier frequency offset (cycles per sample) of the last payload found, if the detector estimates it,
+  /// positive if the frame is received above the frequencies it is sent on, see [`Modem::set_frequency_offset`]
+  fn frequency_offset(&self) -> Option<f32> {
+    None
+  }
+
+  /// Number of preambles detected since the detector is created, whether they turned out to be a frame or not
+  fn detections(&self) -> usize {
+    0
+  }
+
+  /// Number of the preambles detected which did not produce a valid frame, e.g. with an invalid length header
+  fn false_alarms(&self) -> usize {
+    0
+  }
+}
+
+impl<D: FrameDetector + ?Sized> FrameDetector for Box<D> {
+  fn on_sample(&mut self, sample: FP) -> Option<FramePayload> {
+    (**self).on_sample(sample)
+  }
+
+  fn payload_bytes(&self) -> Option<usize> {
+    (**self).payload_bytes()
+  }
+
+  fn frequency_offset(&self) -> Option<f32> {
+    (**self).frequency_offset()
+  }
+
+  fn detections(&self) -> usize {
+    (**self).detections()
+  }
+
+  fn false_alarms(&self) -> usize {
+    (**self).false_alarms()
+  }
 }
@@ -1,7 +1,12 @@
-use super::{traits::PhyPacket, FrameDetector, FramePayload, Modem, PreambleGen};
+use super::{
+  header::LengthHeader,
+  traits::{PhyPacket, SoftBits},
+  FrameDetector, FramePayload, Modem, PreambleGen,
+};
 use crate::{
+  sample_stream::CaptureLog,
   traits::{InStream, OutStream, PacketReceiver, PacketSender, Sample, FP},
-  DefaultConfig,
+  AudioConfig,
 };
 use crossbeam::channel::{unbounded as unbounded_channel, Receiver, Sender};
 use std::{
@@ -21,6 +26,9 @@ pub struct PhySender<PG, MM, SS, E> {
   preamble_samples: Vec<FP>,
   modem: MM,
   stream_out: SS,
+  // number of samples sent, and the log of the packet boundaries
+  samples: u64,
+  capture: Option<CaptureLog>,
 }
 
 impl<PG, MM, SS, E> PhySender<PG, MM, SS, E>
@@ -30,7 +38,17 @@ where
   SS: OutStream<FP, E>,
 {
   pub fn new(stream_out: SS, modem: MM) -> Self {
-    let preamble_samples = PG::generate().samples();
+    Self::with_preamble(stream_out, PG::generate(), modem)
+  }
+
+  /// Create a sender with a given preamble, e.g. a preamble generated for a specific audio config.
+  pub fn with_preamble(stream_out: SS, preamble: PG, modem: MM) -> Self {
+    Self::with_capture(stream_out, preamble, modem, None)
+  }
+
+  /// Create a sender which logs the first/last sample of each frame sent, see [`CaptureLog`].
+  pub fn with_capture(stream_out: SS, preamble: PG, modem: MM, capture: Option<CaptureLog>) -> Self {
+    let preamble_samples = preamble.samples();
 
     Self {
       _pg: PhantomData::default(),
@@ -38,10 +56,20 @@ where
       preamble_samples,
       modem,
       stream_out,
+      samples: 0,
+      capture,
     }
   }
 
-  pub const SAMPLES_PER_PACKET: usize = PG::PREAMBLE_LEN + MM::SAMPLES_PER_PACKET;
+  /// maximum number of bytes in one packet
+  pub fn packet_bytes(&self) -> usize {
+    self.modem.bytes_per_packet()
+  }
+
+  /// number of samples of the frame sending a packet of `bytes` bytes
+  pub fn frame_samples(&self, bytes: usize) -> usize {
+    self.preamble_samples.len() + self.modem.samples_for(LengthHeader::BYTES) + self.modem.samples_for(bytes)
+  }
 }
 impl<PG, MM, SS, E> PacketSender<PhyPacket, E> for PhySender<PG, MM, SS, E>
 where
@@ -49,22 +77,40 @@ where
   MM: Modem,
   SS: OutStream<FP, E>,
 {
-  /// frame = warm up + preamble + payload  
+  /// frame = warm up + preamble + header + payload  
   /// - warm up: random samples whose absolute value is cloes to 1.0
   /// - preamble: predefined samples
-  /// - payload: output of modulation on packet bytes
+  /// - header: output of modulation on the packet length, see [`LengthHeader`]
+  /// - payload: output of modulation on packet bytes, only as long as the packet needs
   /// NOTE: write them to the underlying stream together with `write_once`
   fn send(&mut self, packet: PhyPacket) -> Result<(), E> {
-    assert_eq!(packet.len(), MM::BYTES_PER_PACKET);
-    let mut buf = Vec::with_capacity(PG::PREAMBLE_LEN + MM::SAMPLES_PER_PACKET);
+    assert!(packet.len() <= self.packet_bytes());
+    let mut buf = Vec::with_capacity(self.frame_samples(packet.len()));
     buf.extend(&self.preamble_samples);
+    buf.extend(self.modem.modulate(&LengthHeader::encode(packet.len())));
     buf.extend(self.modem.modulate(&packet));
+    if let Some(capture) = self.capture.as_mut() {
+      capture.mark(self.samples, "tx frame start");
+    }
     let n = self.stream_out.write_exact(&buf)?;
+    self.samples += buf.len() as u64;
+    if let Some(capture) = self.capture.as_mut() {
+      capture.mark(self.samples, "tx frame end");
+    }
     self.stream_out.wait();
     Ok(n)
   }
 }
 
+// a payload found by the frame detector, with what the detector measured on the frame
+struct DetectedFrame {
+  payload: FramePayload,
+  // the length in bytes if known
+  bytes: Option<usize>,
+  // the carrier frequency offset if estimated
+  frequency_offset: Option<f32>,
+}
+
 /// A receive only PHY layer object.  
 /// - PG: preamble generator
 /// - MM: modulation encoder/decoder
@@ -77,7 +123,7 @@ pub struct PhyReceiver<PG, MM, FD, SS, E> {
   _ss: PhantomData<SS>,
   _err: PhantomData<E>,
   modem: MM,
-  frame_payload_rx: Receiver<FramePayload>,
+  frame_payload_rx: Receiver<DetectedFrame>,
   exit_tx: Sender<()>,
   handler: Option<JoinHandle<()>>,
 }
@@ -94,20 +140,38 @@ where
   /// 0. exit if notified by exit channel
   /// 1. fetch samples from underlying stream
   /// 2. push them to frame detector
-  /// 3. if a frame is detected, send it to the PhyReceiver through a channel
-  fn worker(mut stream_in: SS, mut frame_detector: FD, frame_playload_rx: Sender<FramePayload>, exit_rx: Receiver<()>) {
+  /// 3. if a frame is detected, send it to the PhyReceiver through a channel,
+  ///    and log the index of its last sample if capturing
+  fn worker(
+    mut stream_in: SS,
+    mut frame_detector: FD,
+    frame_playload_rx: Sender<DetectedFrame>,
+    exit_rx: Receiver<()>,
+    config: AudioConfig,
+    mut capture: Option<CaptureLog>,
+  ) {
     // TODO: select a proper interval
-    let fetch_interval =
-      Duration::from_secs_f32(2.0 * DefaultConfig::BUFFER_SIZE as f32 / DefaultConfig::SAMPLE_RATE as f32);
+    let fetch_interval = Duration::from_secs_f32(2.0 * config.buffer_size as f32 / config.sample_rate as f32);
     let last_fetch = Instant::now() - fetch_interval;
     // TODO: select a proper buffer size
-    let mut buf = [Sample::ZERO; DefaultConfig::BUFFER_SIZE * 8];
+    let mut buf = vec![Sample::ZERO; config.buffer_size * 8];
+    let mut samples = 0;
     while exit_rx.try_recv().is_err() {
       if last_fetch.elapsed() > fetch_interval {
         let n = stream_in.read(&mut buf).unwrap();
         buf[..n].iter().for_each(|x| {
+          samples += 1;
           if let Some(payload) = frame_detector.on_sample(*x) {
-            frame_playload_rx.send(payload).unwrap();
+            if let Some(capture) = capture.as_mut() {
+              capture.mark(samples, "rx frame detected");
+            }
+            frame_playload_rx
+              .send(DetectedFrame {
+                payload,
+                bytes: frame_detector.payload_bytes(),
+                frequency_offset: frame_detector.frequency_offset(),
+              })
+              .unwrap();
           }
         });
       }
@@ -116,9 +180,27 @@ where
   }
 
   pub fn new(stream_in: SS, modem: MM, frame_detector: FD) -> Self {
+    Self::with_config(stream_in, modem, frame_detector, &AudioConfig::default())
+  }
+
+  /// Create a receiver whose worker fetches samples at the pace given by the audio config.
+  pub fn with_config(stream_in: SS, modem: MM, frame_detector: FD, config: &AudioConfig) -> Self {
+    Self::with_capture(stream_in, modem, frame_detector, config, None)
+  }
+
+  /// Create a receiver which logs the frames detected, see [`CaptureLog`].
+  pub fn with_capture(
+    stream_in: SS,
+    modem: MM,
+    frame_detector: FD,
+    config: &AudioConfig,
+    capture: Option<CaptureLog>,
+  ) -> Self {
     let (exit_tx, exit_rx) = unbounded_channel();
     let (frame_playload_tx, frame_payload_rx) = unbounded_channel();
-    let handler = thread::spawn(move || Self::worker(stream_in, frame_detector, frame_playload_tx, exit_rx));
+    let config = *config;
+    let handler =
+      thread::spawn(move || Self::worker(stream_in, frame_detector, frame_playload_tx, exit_rx, config, capture));
     Self {
       _pg: PhantomData::default(),
       _fd: PhantomData::default(),
@@ -132,6 +214,49 @@ where
   }
 }
 
+impl<PG, MM: Modem, FD, SS, E> PhyReceiver<PG, MM, FD, SS, E> {
+  /// the modem demodulating the frames received, e.g. to read what it measured on the last frame
+  pub fn modem(&self) -> &MM {
+    &self.modem
+  }
+
+  // demodulate a payload, the padding of the modem is dropped if the length is known
+  fn demodulate(&mut self, frame: DetectedFrame) -> PhyPacket {
+    self
+      .modem
+      .set_frequency_offset(frame.frequency_offset.unwrap_or_default());
+    let mut packet = self.modem.demodulate(&frame.payload);
+    if let Some(bytes) = frame.bytes {
+      packet.truncate(bytes);
+    }
+    packet
+  }
+
+  // demodulate a payload to soft bits, the padding of the modem is dropped if the length is known
+  fn demodulate_soft(&mut self, frame: DetectedFrame) -> SoftBits {
+    self
+      .modem
+      .set_frequency_offset(frame.frequency_offset.unwrap_or_default());
+    let mut soft = self.modem.demodulate_soft(&frame.payload);
+    if let Some(bytes) = frame.bytes {
+      soft.truncate(bytes * 8);
+    }
+    soft
+  }
+
+  /// receive a frame as soft bits, see [`Modem::demodulate_soft`], return immediately
+  pub fn recv_soft(&mut self) -> Option<SoftBits> {
+    let frame = self.frame_payload_rx.try_recv().ok()?;
+    Some(self.demodulate_soft(frame))
+  }
+
+  /// receive a frame as soft bits, wait until timeout
+  pub fn recv_soft_timeout(&mut self, timeout: Duration) -> Option<SoftBits> {
+    let frame = self.frame_payload_rx.recv_timeout(timeout).ok()?;
+    Some(self.demodulate_soft(frame))
+  }
+}
+
 impl<PG, MM, FD, SS, E> PacketReceiver<PhyPacket, ()> for PhyReceiver<PG, MM, FD, SS, E>
 where
   PG: PreambleGen,
@@ -142,14 +267,14 @@ where
   // receive frame from the channel and then demodulate the signal
   fn recv(&mut self) -> Result<PhyPacket, ()> {
     match self.frame_payload_rx.try_recv() {
-      Ok(payload) => Ok(self.modem.demodulate(&payload)),
+      Ok(frame) => Ok(self.demodulate(frame)),
       Err(_) => Err(()),
     }
   }
 
   fn recv_timeout(&mut self, timeout: Duration) -> Result<PhyPacket, ()> {
     match self.frame_payload_rx.recv_timeout(timeout) {
-      Ok(payload) => Ok(self.modem.demodulate(&payload)),
+      Ok(frame) => Ok(self.demodulate(frame)),
       Err(_) => Err(()),
     }
   }
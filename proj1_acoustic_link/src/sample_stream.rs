@@ -1,10 +1,27 @@
+/// sample stream IO through a simulated acoustic channel
+mod channel_sim;
 /// sample stream IO with cpal audio I/O
 mod cpal_stream;
+/// list the cpal audio hosts/devices and select a device by name or index
+pub mod device;
 /// sample stream IO with hound wav reader/writer
 mod hound_stream;
 /// sample stream IO with a concurrent buffer, read out the written samples
 mod loopback_stream;
+/// sample rate conversion adaptors over other sample streams
+mod resample;
+/// mirror the samples of a stream into a recording, and capture the PHY layer into a directory
+mod tee_stream;
+/// sample stream IO on a shared medium simulated in-process
+mod virtual_air;
 
+pub use channel_sim::{ChannelSimConfig, ChannelSimStream};
 pub use cpal_stream::{CpalInStream, CpalOutStream, CpalPowerProbe};
-pub use hound_stream::{HoundInStream, HoundOutStream};
+pub use hound_stream::{ChannelMix, HoundInConfig, HoundInStream, HoundOutStream};
 pub use loopback_stream::LoopBackStream;
+pub use resample::{ResampleInStream, ResampleOutStream, Resampler};
+pub use tee_stream::{CaptureDir, CaptureLog, TapSink, TeeStream};
+pub use virtual_air::{AirInStream, AirOutStream, AirPowerProbe, VirtualAir};
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,208 @@
+use crate::{
+  block_buffer::ConcurrentBuffer,
+  traits::{InStream, OutStream, Sample, FP},
+};
+use parking_lot::Mutex;
+use std::{collections::VecDeque, sync::Arc};
+
+/// Parameters of a simulated acoustic channel.
+/// The default value is an ideal channel: the samples are delivered untouched.
+#[derive(Clone, Debug)]
+pub struct ChannelSimConfig {
+  /// seed of the pseudo-random generator, the same seed reproduces the same channel
+  pub seed: u64,
+  /// amplitude gain of the direct path
+  pub gain: f32,
+  /// echo/multipath taps: `(delay in samples, gain)` added on top of the direct path
+  pub taps: Vec<(usize, f32)>,
+  /// standard deviation of the additive white gaussian noise
+  pub noise_std: f32,
+  /// constant offset added to every received sample
+  pub dc_offset: f32,
+  /// relative sampling clock mismatch between sender and receiver,
+  /// e.g. `1e-4` means the receiver samples 100ppm slower than the sender.
+  pub drift: f32,
+  /// probability that a sample is silently dropped
+  pub drop_prob: f32,
+  /// probability that a spurious sample is inserted
+  pub insert_prob: f32,
+}
+
+impl Default for ChannelSimConfig {
+  fn default() -> Self {
+    Self {
+      seed: 0,
+      gain: 1.0,
+      taps: Vec::new(),
+      noise_std: 0.0,
+      dc_offset: 0.0,
+      drift: 0.0,
+      drop_prob: 0.0,
+      insert_prob: 0.0,
+    }
+  }
+}
+
+// SplitMix64 pseudo-random generator: tiny and seedable, good enough for the impairments of a simulated channel
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+  fn next_u64(&mut self) -> u64 {
+    self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = self.0;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+  }
+
+  // a uniform sample within [0, 1), from the 24 high bits
+  fn uniform(&mut self) -> f32 {
+    (self.next_u64() >> 40) as f32 / (1 << 24) as f32
+  }
+}
+
+// the state of the channel, shared by all the clones of a `ChannelSimStream`
+struct ChannelState {
+  config: ChannelSimConfig,
+  rng: SplitMix64,
+  // the previously written samples, used by the multipath taps
+  history: VecDeque<f32>,
+  // linear interpolation state for clock drift
+  prev: f32,
+  cur: f32,
+  pos: f32,
+}
+
+impl ChannelState {
+  fn new(config: ChannelSimConfig) -> Self {
+    let max_delay = config.taps.iter().map(|&(delay, _)| delay).max().unwrap_or(0);
+    Self {
+      rng: SplitMix64(config.seed),
+      history: VecDeque::from(vec![0.0; max_delay + 1]),
+      prev: 0.0,
+      cur: 0.0,
+      pos: 1.0,
+      config,
+    }
+  }
+
+  // a sample from the standard normal distribution, Box-Muller transform
+  fn gaussian(&mut self) -> f32 {
+    let u1 = self.rng.uniform().max(f32::EPSILON);
+    let u2 = self.rng.uniform();
+    (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+  }
+
+  // the direct path and all the echoes
+  fn multipath(&mut self, x: f32) -> f32 {
+    self.history.pop_back();
+    self.history.push_front(x);
+    let echoes = self
+      .config
+      .taps
+      .iter()
+      .fold(0.0, |s, &(delay, gain)| s + gain * self.history[delay]);
+    self.config.gain * x + echoes
+  }
+
+  // DC offset + AWGN
+  fn distort(&mut self, x: f32) -> f32 {
+    let noise = if self.config.noise_std > 0.0 {
+      self.config.noise_std * self.gaussian()
+    } else {
+      0.0
+    };
+    x + self.config.dc_offset + noise
+  }
+
+  // emit a sample to the receiver side, might be dropped or accompanied by an inserted sample.
+  fn emit(&mut self, x: f32, out: &mut Vec<FP>) {
+    if self.config.drop_prob > 0.0 && self.rng.uniform() < self.config.drop_prob {
+      return;
+    }
+    let y = self.distort(x);
+    out.push(FP::from_f32(y));
+    if self.config.insert_prob > 0.0 && self.rng.uniform() < self.config.insert_prob {
+      let y = self.distort(x);
+      out.push(FP::from_f32(y));
+    }
+  }
+
+  // push a sample into the channel, the received samples are appended to `out`.
+  fn process(&mut self, x: f32, out: &mut Vec<FP>) {
+    let x = self.multipath(x);
+    // resample with linear interpolation to simulate the clock drift
+    self.prev = self.cur;
+    self.cur = x;
+    let step = 1.0 + self.config.drift;
+    while self.pos <= 1.0 {
+      let y = self.cur - (self.cur - self.prev) * (1.0 - self.pos);
+      self.emit(y, out);
+      self.pos += step;
+    }
+    self.pos -= 1.0;
+  }
+}
+
+/// A loopback stream which simulates an acoustic channel.
+/// Samples written into the stream go through
+/// multipath echoes, gain, clock drift, random drop/insert, DC offset and AWGN,
+/// then they can be read out from the stream.
+/// The stream can be cloned to share the channel between a sender thread and a receiver thread.
+/// See [`ChannelSimConfig`] for the channel parameters.
+#[derive(Clone)]
+pub struct ChannelSimStream {
+  state: Arc<Mutex<ChannelState>>,
+  buffer: ConcurrentBuffer<FP>,
+}
+
+impl ChannelSimStream {
+  /// create a channel with the given parameters
+  pub fn new(config: ChannelSimConfig) -> Self {
+    Self {
+      state: Arc::new(Mutex::new(ChannelState::new(config))),
+      buffer: ConcurrentBuffer::new(),
+    }
+  }
+  /// the parameters of the simulated channel
+  pub fn config(&self) -> ChannelSimConfig {
+    self.state.lock().config.clone()
+  }
+}
+
+impl Default for ChannelSimStream {
+  /// an ideal channel
+  fn default() -> Self {
+    Self::new(ChannelSimConfig::default())
+  }
+}
+
+impl InStream<FP, ()> for ChannelSimStream {
+  fn read(&mut self, buf: &mut [FP]) -> Result<usize, ()> {
+    self.buffer.read(buf)
+  }
+
+  fn read_exact(&mut self, buf: &mut [FP]) -> Result<(), ()> {
+    self.buffer.read_exact(buf)
+  }
+}
+
+impl OutStream<FP, ()> for ChannelSimStream {
+  /// pass the samples through the channel, the output of the channel is buffered for reading.
+  fn write(&mut self, buf: &[FP]) -> Result<usize, ()> {
+    let mut received = Vec::with_capacity(buf.len() + buf.len() / 8);
+    let mut state = self.state.lock();
+    buf.iter().for_each(|x| state.process(x.into_f32(), &mut received));
+    self.buffer.write(&received)?;
+    Ok(buf.len())
+  }
+
+  fn write_exact(&mut self, buf: &[FP]) -> Result<(), ()> {
+    self.write(buf).map(|_| ())
+  }
+
+  /// wait for other thread to extract the samples
+  fn wait(&mut self) {
+    self.buffer.wait()
+  }
+}
@@ -1,95 +1,306 @@
 use cpal::{
   traits::{DeviceTrait, HostTrait, StreamTrait},
-  BuildStreamError, Device, StreamConfig,
+  Device, Host,
 };
 use parking_lot::Mutex;
-use std::{collections::VecDeque, sync::Arc};
+use std::{
+  collections::VecDeque,
+  sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+  },
+};
 
+use super::device::{input_device, output_device, DeviceError, DeviceSelector};
 use crate::{
-  block_buffer::ConcurrentBuffer,
-  traits::{InStream, OutStream, Sample, FP},
-  DefaultConfig,
+  block_buffer::{ring_buffer, RingConsumer, RingProducer},
+  traits::{InStream, OutStream, PowerProbe, Sample, FP},
+  AudioConfig,
 };
 
+// a cpal stream shared by the per-channel streams of a multi-channel device
+type SharedStream = Arc<Mutex<cpal::Stream>>;
+
+// the capacity of the ring buffer between a stream and its real-time callback: one second of samples
+fn ring_capacity(config: &AudioConfig) -> usize {
+  config.sample_rate as usize * config.channels as usize
+}
+
+// The buffer a real-time callback casts the samples of a block in, allocated before the stream is built:
+// the callback must not allocate, a longer block is processed in several pieces.
+fn cast_buffer(config: &AudioConfig) -> Vec<FP> {
+  vec![FP::ZERO; config.buffer_size.max(1) * config.channels as usize]
+}
+
 /// An input stream built on cpal input stream. Support reading PCM samples.
 /// The `CpalInStream` fetch samples from a `cpal::Stream`
 pub struct CpalInStream {
-  stream: cpal::Stream,
-  buffer: ConcurrentBuffer<FP>,
+  stream: SharedStream,
+  buffer: RingConsumer<FP>,
 }
 /// An output stream built on cpal output stream. Support writing PCM samples.
 /// The `CpalOutStream` write samples to a `cpal::Stream`
 pub struct CpalOutStream {
-  stream: cpal::Stream,
-  buffer: ConcurrentBuffer<FP>,
+  stream: SharedStream,
+  buffer: RingProducer<FP>,
 }
 /// monitoring the power level on a cpal stream
 pub struct CpalPowerProbe {
-  _stream: cpal::Stream,
-  power: Arc<Mutex<f32>>,
+  _stream: SharedStream,
+  power: Arc<AtomicU32>,
+  window: usize,
 }
 
 impl CpalInStream {
   /// Create an input stream on a given device with a specified config.
   /// The stream is initially in playing state.
-  pub fn new(input_device: Device, stream_config: StreamConfig) -> Result<Self, BuildStreamError> {
+  pub fn new(input_device: Device, config: &AudioConfig) -> Result<Self, DeviceError> {
+    config.validate()?;
     // the callback function  periodically fetch samples
     // from the stream and push them into the buffer
-    let buffer: ConcurrentBuffer<FP> = Default::default();
-    let mut bf = buffer.clone();
-    let mut cast_buf = vec![FP::ZERO; DefaultConfig::BUFFER_SIZE];
+    // samples arriving when the buffer is full are dropped
+    let (mut bf, buffer) = ring_buffer(ring_capacity(config));
+    let mut cast_buf = cast_buffer(config);
     let stream = input_device.build_input_stream(
-      &stream_config,
+      &config.into(),
       move |data: &[f32], _: &_| CpalInStream::read_from_stream(data, &mut cast_buf, &mut bf),
       |err| eprintln!("An error occured at cpal stream {}", err),
     )?;
+    let stream = Arc::new(Mutex::new(stream));
     Ok(CpalInStream { stream, buffer })
   }
+
+  /// Create an input stream and a power probe on a given device with a specified config.
+  /// The probe listens on the same underlying cpal stream, instead of opening the device a second time.
+  /// The stream and the probe are initially in playing state.
+  pub fn with_probe(input_device: Device, config: &AudioConfig) -> Result<(Self, CpalPowerProbe), DeviceError> {
+    config.validate()?;
+    let (mut bf, buffer) = ring_buffer(ring_capacity(config));
+    let mut cast_buf = cast_buffer(config);
+    let mut power_window = PowerWindow::new(config.pwr_probe_wind);
+    let power = power_window.power.clone();
+    let stream = input_device.build_input_stream(
+      &config.into(),
+      move |data: &[f32], _: &_| {
+        CpalInStream::read_from_stream(data, &mut cast_buf, &mut bf);
+        power_window.push(data.iter());
+      },
+      |err| eprintln!("An error occured at cpal stream {}", err),
+    )?;
+    let stream = Arc::new(Mutex::new(stream));
+    let probe = CpalPowerProbe {
+      _stream: stream.clone(),
+      power,
+      window: config.pwr_probe_wind,
+    };
+    Ok((CpalInStream { stream, buffer }, probe))
+  }
+
+  /// Create one input stream for each channel of a given device with a specified config.
+  /// The interleaved samples from the device are split by channel,
+  /// i.e. the i-th stream only reads the samples of the i-th channel.
+  /// The streams are initially in playing state, and share the same underlying cpal stream.
+  pub fn split(input_device: Device, config: &AudioConfig) -> Result<Vec<Self>, DeviceError> {
+    let channels: Vec<_> = (0..config.channels as usize).collect();
+    let streams = Self::split_channels(input_device, config, &channels)?;
+    Ok(streams.into_iter().map(|(stream, _)| stream).collect())
+  }
+
+  /// Create an input stream and a power probe for each of the given channels of a device with a specified config,
+  /// the samples of the other channels are dropped.
+  /// The streams and the probes are initially in playing state, and share the same underlying cpal stream.
+  pub fn split_channels(
+    input_device: Device,
+    config: &AudioConfig,
+    channels: &[usize],
+  ) -> Result<Vec<(Self, CpalPowerProbe)>, DeviceError> {
+    config.validate()?;
+    let total = check_channels(config, channels)?;
+    let (mut bfs, buffers): (Vec<_>, Vec<_>) = channels
+      .iter()
+      .map(|&ch| {
+        let (bf, buffer) = ring_buffer(config.sample_rate as usize);
+        ((ch, bf), buffer)
+      })
+      .unzip();
+    let (mut windows, powers): (Vec<_>, Vec<_>) = channels
+      .iter()
+      .map(|&ch| {
+        let window = PowerWindow::new(config.pwr_probe_wind);
+        let power = window.power.clone();
+        ((ch, window), power)
+      })
+      .unzip();
+    let mut cast_buf = cast_buffer(config);
+    let stream = input_device.build_input_stream(
+      &config.into(),
+      move |data: &[f32], _: &_| {
+        read_channels_from_stream(data, total, &mut cast_buf, &mut bfs);
+        for (ch, window) in windows.iter_mut() {
+          window.push(data.iter().skip(*ch).step_by(total));
+        }
+      },
+      |err| eprintln!("An error occured at cpal stream {}", err),
+    )?;
+    let stream = Arc::new(Mutex::new(stream));
+    Ok(
+      buffers
+        .into_iter()
+        .zip(powers)
+        .map(|(buffer, power)| {
+          let probe = CpalPowerProbe {
+            _stream: stream.clone(),
+            power,
+            window: config.pwr_probe_wind,
+          };
+          let stream = CpalInStream {
+            stream: stream.clone(),
+            buffer,
+          };
+          (stream, probe)
+        })
+        .collect(),
+    )
+  }
+
   /// Start the stream. Change its state to playing and accept samples.
   pub fn play(&self) {
-    self.stream.play().unwrap();
+    self.stream.lock().play().unwrap();
   }
   /// Pause the stream. All the samples come in when stream is paused will be discarded silently.
   pub fn pause(&self) {
-    self.stream.pause().unwrap();
+    self.stream.lock().pause().unwrap();
+  }
+
+  /// Create an input stream on the default input device with a specified config.
+  /// Panic if the stream can not be built.
+  pub fn with_config(config: &AudioConfig) -> Self {
+    let host = cpal::default_host();
+    let input_device = host.default_input_device().expect("no default input device available");
+    Self::new(input_device, config).expect("failed to create input stream")
+  }
+
+  /// Create an input stream and a power probe sharing it on the default input device with a specified config.
+  /// Panic if the stream can not be built.
+  pub fn with_config_probe(config: &AudioConfig) -> (Self, CpalPowerProbe) {
+    let host = cpal::default_host();
+    let input_device = host.default_input_device().expect("no default input device available");
+    Self::with_probe(input_device, config).expect("failed to create input stream")
+  }
+
+  /// Create an input stream on the selected input device of a host with a specified config.
+  pub fn on_device(host: &Host, selector: &DeviceSelector, config: &AudioConfig) -> Result<Self, DeviceError> {
+    Self::new(input_device(host, selector)?, config)
+  }
+
+  /// Create an input stream and a power probe sharing it on the selected input device of a host
+  /// with a specified config.
+  pub fn on_device_with_probe(
+    host: &Host,
+    selector: &DeviceSelector,
+    config: &AudioConfig,
+  ) -> Result<(Self, CpalPowerProbe), DeviceError> {
+    Self::with_probe(input_device(host, selector)?, config)
   }
 
   // the helper function passed to the `stream.build_input_stream`
-  fn read_from_stream(data: &[f32], cast_buf: &mut [FP], dest: &mut ConcurrentBuffer<FP>) {
-    let cast_buf = &mut cast_buf[..data.len()];
-    cast_buf
-      .iter_mut()
-      .zip(data.iter())
-      .for_each(|(x, y)| *x = FP::from_f32(*y));
-    dest.write(cast_buf).unwrap();
+  fn read_from_stream(data: &[f32], cast_buf: &mut [FP], dest: &mut RingProducer<FP>) {
+    for data in data.chunks(cast_buf.len()) {
+      let cast_buf = &mut cast_buf[..data.len()];
+      cast_buf
+        .iter_mut()
+        .zip(data.iter())
+        .for_each(|(x, y)| *x = FP::from_f32(*y));
+      dest.write(cast_buf).unwrap();
+    }
   }
 }
 
 impl CpalOutStream {
   /// create an output stream on a given device with a specified config.
   /// The stream is initially in playing state.
-  pub fn new(output_device: Device, stream_config: StreamConfig) -> Result<Self, BuildStreamError> {
+  pub fn new(output_device: Device, config: &AudioConfig) -> Result<Self, DeviceError> {
+    config.validate()?;
     // the callback function should periodically fetch samples
     // from the buffer and write them into the stream
-    let buffer: ConcurrentBuffer<FP> = Default::default();
-    let mut bf = buffer.clone();
-    let mut cast_buf = vec![FP::ZERO; DefaultConfig::BUFFER_SIZE];
+    let (buffer, mut bf) = ring_buffer(ring_capacity(config));
+    let mut cast_buf = cast_buffer(config);
 
     let stream = output_device.build_output_stream(
-      &stream_config,
+      &config.into(),
       move |data: &mut [f32], _| CpalOutStream::write_to_stream(data, &mut cast_buf, &mut bf),
       |e| eprintln!("An error occured at cpal out stream {}", e),
     )?;
+    let stream = Arc::new(Mutex::new(stream));
     Ok(CpalOutStream { stream, buffer })
   }
 
+  /// Create one output stream for each channel of a given device with a specified config.
+  /// The i-th stream only writes the samples of the i-th channel,
+  /// a channel is silent when there is no sample to play on it.
+  /// The streams are initially in playing state, and share the same underlying cpal stream.
+  pub fn split(output_device: Device, config: &AudioConfig) -> Result<Vec<Self>, DeviceError> {
+    let channels: Vec<_> = (0..config.channels as usize).collect();
+    Self::split_channels(output_device, config, &channels)
+  }
+
+  /// Create one output stream for each of the given channels of a device with a specified config,
+  /// the other channels are silent.
+  /// The streams are initially in playing state, and share the same underlying cpal stream.
+  pub fn split_channels(
+    output_device: Device,
+    config: &AudioConfig,
+    channels: &[usize],
+  ) -> Result<Vec<Self>, DeviceError> {
+    config.validate()?;
+    let total = check_channels(config, channels)?;
+    let (buffers, mut bfs): (Vec<_>, Vec<_>) = channels
+      .iter()
+      .map(|&ch| {
+        let (buffer, bf) = ring_buffer(config.sample_rate as usize);
+        (buffer, (ch, bf))
+      })
+      .unzip();
+    let mut cast_buf = cast_buffer(config);
+    let stream = output_device.build_output_stream(
+      &config.into(),
+      move |data: &mut [f32], _| write_channels_to_stream(data, total, &mut cast_buf, &mut bfs),
+      |e| eprintln!("An error occured at cpal out stream {}", e),
+    )?;
+    let stream = Arc::new(Mutex::new(stream));
+    Ok(
+      buffers
+        .into_iter()
+        .map(|buffer| CpalOutStream {
+          stream: stream.clone(),
+          buffer,
+        })
+        .collect(),
+    )
+  }
+
+  /// Create an output stream on the default output device with a specified config.
+  /// Panic if the stream can not be built.
+  pub fn with_config(config: &AudioConfig) -> Self {
+    let host = cpal::default_host();
+    let output_device = host
+      .default_output_device()
+      .expect("no default output device available");
+    Self::new(output_device, config).expect("failed to create output stream")
+  }
+
+  /// Create an output stream on the selected output device of a host with a specified config.
+  pub fn on_device(host: &Host, selector: &DeviceSelector, config: &AudioConfig) -> Result<Self, DeviceError> {
+    Self::new(output_device(host, selector)?, config)
+  }
+
   /// Start the stream. Change its state to playing and accept samples.
   pub fn play(&self) {
-    self.stream.play().unwrap();
+    self.stream.lock().play().unwrap();
   }
   /// Pause the stream.
   pub fn pause(&self) {
-    self.stream.pause().unwrap();
+    self.stream.lock().pause().unwrap();
   }
   /// Clear the samples not played.
   pub fn clear(&mut self) {
@@ -97,57 +308,183 @@ impl CpalOutStream {
   }
 
   // helper function passed to the `stream.build_output_stream`
-  fn write_to_stream(data: &mut [f32], cast_buf: &mut [FP], src: &mut ConcurrentBuffer<FP>) {
-    let cast_buf = &mut cast_buf[0..data.len()];
-    let read_size = src.read(cast_buf).unwrap();
-    data[..read_size]
-      .iter_mut()
-      .zip(cast_buf.iter())
-      .for_each(|(x, y)| *x = FP::into_f32(*y));
-    data[read_size..].iter_mut().for_each(|x| *x = 0.0);
+  fn write_to_stream(data: &mut [f32], cast_buf: &mut [FP], src: &mut RingConsumer<FP>) {
+    let mut written = 0;
+    while written < data.len() {
+      let len = cast_buf.len().min(data.len() - written);
+      let read_size = src.read(&mut cast_buf[..len]).unwrap();
+      data[written..written + read_size]
+        .iter_mut()
+        .zip(cast_buf.iter())
+        .for_each(|(x, y)| *x = FP::into_f32(*y));
+      written += read_size;
+      if read_size < len {
+        break;
+      }
+    }
+    data[written..].iter_mut().for_each(|x| *x = 0.0);
   }
 }
 
 impl CpalPowerProbe {
   /// create and start to listen on a input stream
-  pub fn new(input_device: Device, stream_config: StreamConfig) -> Result<Self, BuildStreamError> {
-    let power = Arc::new(Mutex::new(0.0));
-    let pw = power.clone();
-    let mut power_queue = VecDeque::with_capacity(DefaultConfig::PWR_PROBE_WIND);
+  pub fn new(input_device: Device, config: &AudioConfig) -> Result<Self, DeviceError> {
+    config.validate()?;
+    let window = config.pwr_probe_wind;
+    let mut power_window = PowerWindow::new(window);
+    let power = power_window.power.clone();
     let _stream = input_device.build_input_stream(
-      &stream_config,
+      &config.into(),
+      move |data: &[f32], _| power_window.push(data.iter()),
+      |e| eprintln!("An error occured at cpal input stream {}", e),
+    )?;
+    let _stream = Arc::new(Mutex::new(_stream));
+    Ok(CpalPowerProbe { _stream, power, window })
+  }
+
+  /// Create one power probe for each channel of a given input device with a specified config.
+  /// The i-th probe only measures the power of the i-th channel.
+  pub fn split(input_device: Device, config: &AudioConfig) -> Result<Vec<Self>, DeviceError> {
+    config.validate()?;
+    let channels = config.channels as usize;
+    let window = config.pwr_probe_wind;
+    let mut power_windows: Vec<_> = (0..channels).map(|_| PowerWindow::new(window)).collect();
+    let powers: Vec<_> = power_windows
+      .iter()
+      .map(|power_window| power_window.power.clone())
+      .collect();
+    let _stream = input_device.build_input_stream(
+      &config.into(),
       move |data: &[f32], _| {
-        let mut pw = pw.lock();
-        data.iter().for_each(|&x| {
-          if power_queue.len() == DefaultConfig::PWR_PROBE_WIND {
-            let y = power_queue.pop_front().unwrap();
-            *pw -= y * y;
-          }
-          power_queue.push_back(x);
-          *pw += x * x;
-        });
+        for (ch, power_window) in power_windows.iter_mut().enumerate() {
+          power_window.push(data.iter().skip(ch).step_by(channels));
+        }
       },
       |e| eprintln!("An error occured at cpal input stream {}", e),
     )?;
-    Ok(CpalPowerProbe { _stream, power })
+    let _stream = Arc::new(Mutex::new(_stream));
+    Ok(
+      powers
+        .into_iter()
+        .map(|power| CpalPowerProbe {
+          _stream: _stream.clone(),
+          power,
+          window,
+        })
+        .collect(),
+    )
+  }
+
+  /// Create a power probe on the default input device with a specified config.
+  /// Panic if the stream can not be built.
+  pub fn with_config(config: &AudioConfig) -> Self {
+    let host = cpal::default_host();
+    let input_device = host.default_input_device().expect("no default input device available");
+    Self::new(input_device, config).expect("failed to create input stream")
+  }
+
+  /// Create a power probe on the selected input device of a host with a specified config.
+  pub fn on_device(host: &Host, selector: &DeviceSelector, config: &AudioConfig) -> Result<Self, DeviceError> {
+    Self::new(input_device(host, selector)?, config)
+  }
+}
+
+// the number of channels of the config, if all the given channels are below it
+fn check_channels(config: &AudioConfig, channels: &[usize]) -> Result<usize, DeviceError> {
+  let total = config.channels as usize;
+  match channels.iter().find(|&&ch| ch >= total) {
+    Some(&ch) => Err(DeviceError::NoChannel(ch)),
+    None => Ok(total),
+  }
+}
+
+// The sum of the squares of the recent samples of a channel, shared with a power probe.
+// The sum is published as the bits of a f32, the real-time callback never waits for a lock.
+struct PowerWindow {
+  power: Arc<AtomicU32>,
+  sum: f32,
+  queue: VecDeque<f32>,
+  window: usize,
+}
+
+impl PowerWindow {
+  fn new(window: usize) -> Self {
+    Self {
+      power: Arc::new(AtomicU32::new(0.0f32.to_bits())),
+      sum: 0.0,
+      queue: VecDeque::with_capacity(window),
+      window,
+    }
   }
+
+  fn push<'a>(&mut self, samples: impl Iterator<Item = &'a f32>) {
+    samples.for_each(|&x| {
+      if self.queue.len() == self.window {
+        let y = self.queue.pop_front().unwrap();
+        self.sum -= y * y;
+      }
+      self.queue.push_back(x);
+      self.sum += x * x;
+    });
+    self.power.store(self.sum.to_bits(), Ordering::Relaxed);
+  }
+}
+
+impl PowerProbe for CpalPowerProbe {
   /// probe the power on the input stream
-  pub fn power(&self) -> f32 {
-    let power = self.power.lock();
-    *power / DefaultConfig::PWR_PROBE_WIND as f32
+  fn power(&self) -> f32 {
+    f32::from_bits(self.power.load(Ordering::Relaxed)) / self.window as f32
+  }
+}
+
+// the helper function passed to `build_input_stream` when the channels are split:
+// de-interleave the samples of `channels` channels and push each selected channel into its own buffer.
+pub(super) fn read_channels_from_stream(
+  data: &[f32],
+  channels: usize,
+  cast_buf: &mut [FP],
+  dests: &mut [(usize, RingProducer<FP>)],
+) {
+  for data in data.chunks(cast_buf.len() * channels) {
+    let frames = data.len() / channels;
+    for (ch, dest) in dests.iter_mut() {
+      cast_buf[..frames]
+        .iter_mut()
+        .zip(data.iter().skip(*ch).step_by(channels))
+        .for_each(|(x, &y)| *x = FP::from_f32(y));
+      dest.write(&cast_buf[..frames]).unwrap();
+    }
+  }
+}
+
+// the helper function passed to `build_output_stream` when the channels are split:
+// fetch samples from the buffer of each selected channel and interleave them into `channels` channels,
+// pad with silence.
+pub(super) fn write_channels_to_stream(
+  data: &mut [f32],
+  channels: usize,
+  cast_buf: &mut [FP],
+  srcs: &mut [(usize, RingConsumer<FP>)],
+) {
+  data.iter_mut().for_each(|x| *x = 0.0);
+  for data in data.chunks_mut(cast_buf.len() * channels) {
+    let frames = data.len() / channels;
+    for (ch, src) in srcs.iter_mut() {
+      let read_size = src.read(&mut cast_buf[..frames]).unwrap();
+      data
+        .iter_mut()
+        .skip(*ch)
+        .step_by(channels)
+        .zip(cast_buf[..read_size].iter())
+        .for_each(|(x, y)| *x = FP::into_f32(*y));
+    }
   }
 }
 
 impl Default for CpalInStream {
-  /// Build CpalInStream with default settings:
-  /// - Channels: 1
-  /// - Sample rate: 48000
-  /// - buffer size: 1024
+  /// Build CpalInStream on the default input device with the default [`AudioConfig`].
   fn default() -> Self {
-    let host = cpal::default_host();
-    let input_device = host.default_input_device().expect("no default input device available");
-    let stream_config = DefaultConfig::new();
-    Self::new(input_device, stream_config).expect("failed to create input stream")
+    Self::with_config(&AudioConfig::default())
   }
 }
 
@@ -165,17 +502,9 @@ impl InStream<FP, ()> for CpalInStream {
 }
 
 impl Default for CpalOutStream {
-  /// Build CpalOutStream with default settings:
-  /// - Channels: 1
-  /// - Sample rate: 48000
-  /// - buffer size: 1024
+  /// Build CpalOutStream on the default output device with the default [`AudioConfig`].
   fn default() -> Self {
-    let host = cpal::default_host();
-    let output_device = host
-      .default_output_device()
-      .expect("no default output device available");
-    let stream_config = DefaultConfig::new();
-    Self::new(output_device, stream_config).expect("failed to create input stream")
+    Self::with_config(&AudioConfig::default())
   }
 }
 
@@ -190,17 +519,15 @@ impl OutStream<FP, ()> for CpalOutStream {
     self.buffer.write_exact(buf)
   }
 
-  /// forward to `ConcurrentBuffer::wait`
+  /// wait for the real-time callback to fetch all the samples
   fn wait(&mut self) {
     self.buffer.wait()
   }
 }
 
 impl Default for CpalPowerProbe {
+  /// Build CpalPowerProbe on the default input device with the default [`AudioConfig`].
   fn default() -> Self {
-    let host = cpal::default_host();
-    let input_device = host.default_input_device().expect("no default input device available");
-    let stream_config = DefaultConfig::new();
-    Self::new(input_device, stream_config).expect("failed to create input stream")
+    Self::with_config(&AudioConfig::default())
   }
 }
@@ -0,0 +1,158 @@
+use crate::phy_packet::params::ParamError;
+pub use cpal::Host;
+use cpal::{
+  traits::{DeviceTrait, HostTrait},
+  BuildStreamError, Device, DevicesError, HostUnavailable,
+};
+use std::{convert::Infallible, fmt, str::FromStr};
+
+/// Select an audio device of a host
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum DeviceSelector {
+  /// the default device of the host
+  #[default]
+  Default,
+  /// the n-th device listed by the host, see [`input_devices`]/[`output_devices`]
+  Index(usize),
+  /// the device with exactly the given name
+  Name(String),
+}
+
+impl FromStr for DeviceSelector {
+  type Err = Infallible;
+
+  /// `"default"` selects the default device, a number selects a device by index,
+  /// otherwise select a device by name.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Ok(if s == "default" {
+      Self::Default
+    } else if let Ok(index) = s.parse() {
+      Self::Index(index)
+    } else {
+      Self::Name(s.to_owned())
+    })
+  }
+}
+
+impl fmt::Display for DeviceSelector {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Default => write!(f, "default"),
+      Self::Index(index) => write!(f, "#{}", index),
+      Self::Name(name) => write!(f, "\"{}\"", name),
+    }
+  }
+}
+
+/// Errors when looking up an audio device and building a stream on it
+#[derive(Debug)]
+pub enum DeviceError {
+  /// no host with the given name on this platform
+  UnknownHost(String),
+  /// the host is supported but unavailable
+  HostUnavailable(HostUnavailable),
+  /// failed to enumerate the devices of the host
+  Devices(DevicesError),
+  /// no device matches the selector
+  NotFound(DeviceSelector),
+  /// the channel index is not less than the number of channels in the audio config
+  NoChannel(usize),
+  /// the device is found but the stream can not be built on it
+  BuildStream(BuildStreamError),
+  /// the audio config of the stream is invalid
+  Config(ParamError),
+}
+
+impl fmt::Display for DeviceError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::UnknownHost(name) => write!(f, "unknown audio host \"{}\"", name),
+      Self::HostUnavailable(e) => write!(f, "{}", e),
+      Self::Devices(e) => write!(f, "failed to list audio devices: {}", e),
+      Self::NotFound(selector) => write!(f, "audio device {} not found", selector),
+      Self::NoChannel(channel) => write!(f, "audio channel {} not available", channel),
+      Self::BuildStream(e) => write!(f, "failed to build audio stream: {}", e),
+      Self::Config(e) => write!(f, "invalid audio config: {}", e),
+    }
+  }
+}
+
+impl std::error::Error for DeviceError {}
+
+impl From<HostUnavailable> for DeviceError {
+  fn from(e: HostUnavailable) -> Self {
+    Self::HostUnavailable(e)
+  }
+}
+impl From<DevicesError> for DeviceError {
+  fn from(e: DevicesError) -> Self {
+    Self::Devices(e)
+  }
+}
+impl From<BuildStreamError> for DeviceError {
+  fn from(e: BuildStreamError) -> Self {
+    Self::BuildStream(e)
+  }
+}
+impl From<ParamError> for DeviceError {
+  fn from(e: ParamError) -> Self {
+    Self::Config(e)
+  }
+}
+
+/// names of the audio hosts available on this platform, e.g. `ALSA`, `JACK`
+pub fn hosts() -> Vec<&'static str> {
+  cpal::available_hosts().iter().map(|id| id.name()).collect()
+}
+
+/// Get an audio host by name (case insensitive), `None` for the default host.
+pub fn host(name: Option<&str>) -> Result<Host, DeviceError> {
+  let name = match name {
+    Some(name) => name,
+    None => return Ok(cpal::default_host()),
+  };
+  let id = cpal::available_hosts()
+    .into_iter()
+    .find(|id| id.name().eq_ignore_ascii_case(name))
+    .ok_or_else(|| DeviceError::UnknownHost(name.to_owned()))?;
+  Ok(cpal::host_from_id(id)?)
+}
+
+// the names of the devices, a device whose name is unavailable is listed as an empty string
+fn device_names(devices: impl Iterator<Item = Device>) -> Vec<String> {
+  devices.map(|device| device.name().unwrap_or_default()).collect()
+}
+
+// find the device by index or by name, or fallback to the default device
+fn select_device(
+  mut devices: impl Iterator<Item = Device>,
+  default: Option<Device>,
+  selector: &DeviceSelector,
+) -> Result<Device, DeviceError> {
+  match selector {
+    DeviceSelector::Default => default,
+    DeviceSelector::Index(index) => devices.nth(*index),
+    DeviceSelector::Name(name) => devices.find(|device| device.name().ok().as_ref() == Some(name)),
+  }
+  .ok_or_else(|| DeviceError::NotFound(selector.clone()))
+}
+
+/// names of the input devices of a host, the index in the list can be used in [`DeviceSelector::Index`]
+pub fn input_devices(host: &Host) -> Result<Vec<String>, DeviceError> {
+  Ok(device_names(host.input_devices()?))
+}
+
+/// names of the output devices of a host, the index in the list can be used in [`DeviceSelector::Index`]
+pub fn output_devices(host: &Host) -> Result<Vec<String>, DeviceError> {
+  Ok(device_names(host.output_devices()?))
+}
+
+/// find an input device of the host
+pub fn input_device(host: &Host, selector: &DeviceSelector) -> Result<Device, DeviceError> {
+  select_device(host.input_devices()?, host.default_input_device(), selector)
+}
+
+/// find an output device of the host
+pub fn output_device(host: &Host, selector: &DeviceSelector) -> Result<Device, DeviceError> {
+  select_device(host.output_devices()?, host.default_output_device(), selector)
+}
@@ -1,32 +1,171 @@
 use crate::traits::{Sample, FP};
 use std::{
+  collections::VecDeque,
   fs::File,
-  io::{BufReader, BufWriter, Read, Seek, Write},
+  io::{self, BufReader, BufWriter, Read, Seek, Write},
   path::Path,
 };
 
-use hound::{Error as WavError, WavReader, WavWriter};
+use hound::{Error as WavError, SampleFormat, WavReader, WavSpec, WavWriter};
 
+use super::Resampler;
 use crate::{
   traits::{InStream, OutStream},
-  DefaultConfig,
+  AudioConfig,
 };
 
-pub struct HoundInStream<R: Read>(WavReader<R>);
+/// How the channels of a wav file become a single stream of samples
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChannelMix {
+  /// keep the interleaved samples of all the channels
+  #[default]
+  Interleaved,
+  /// only the samples of the given channel
+  Pick(u16),
+  /// the average of all the channels
+  Downmix,
+}
+
+/// Options of reading a wav file, see [`HoundInStream::with_config`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HoundInConfig {
+  /// pick or downmix the channels
+  pub channel: ChannelMix,
+  /// Resample the samples to the given rate, e.g. the sampling rate of the link.
+  /// `None` keeps the sampling rate of the file.
+  pub sample_rate: Option<u32>,
+}
+
+/// An input stream reading samples from a wav file.
+/// Support integer PCM of 8/16/24/32 bits and 32-bit float samples.
+pub struct HoundInStream<R: Read> {
+  reader: WavReader<R>,
+  channel: ChannelMix,
+  // the resampler and its output not read yet, `None` if not resampling
+  resampler: Option<(Resampler, VecDeque<f32>)>,
+  flushed: bool,
+}
 
 pub struct HoundOutStream<W: Write + Seek>(WavWriter<W>);
 
 impl<R: Read> HoundInStream<R> {
+  /// read all the channels interleaved at the sampling rate of the file
   pub fn new(wav_reader: WavReader<R>) -> Self {
-    Self(wav_reader)
+    Self::with_config(wav_reader, &HoundInConfig::default()).expect("the default config is valid for any file")
+  }
+
+  /// Read the file with the given options.
+  /// Fail with an `InvalidInput` io error if the picked channel does not exist,
+  /// or the interleaved samples of a multi-channel file are to be resampled.
+  pub fn with_config(wav_reader: WavReader<R>, config: &HoundInConfig) -> Result<Self, WavError> {
+    let spec = wav_reader.spec();
+    if let ChannelMix::Pick(channel) = config.channel {
+      if channel >= spec.channels {
+        return Err(invalid_input(format!("no channel {} in the wav file", channel)));
+      }
+    }
+    let resampler = match config.sample_rate {
+      Some(rate) if rate != spec.sample_rate => {
+        if config.channel == ChannelMix::Interleaved && spec.channels > 1 {
+          return Err(invalid_input(
+            "can not resample interleaved channels, pick or downmix a channel".into(),
+          ));
+        }
+        Some((Resampler::new(spec.sample_rate, rate), VecDeque::new()))
+      }
+      _ => None,
+    };
+    Ok(Self {
+      reader: wav_reader,
+      channel: config.channel,
+      resampler,
+      flushed: false,
+    })
+  }
+
+  /// format of the wav file
+  pub fn spec(&self) -> WavSpec {
+    self.reader.spec()
+  }
+
+  // the next sample in the file, normalized into [-1, 1)
+  fn next_raw(&mut self) -> Result<Option<f32>, WavError> {
+    let spec = self.reader.spec();
+    match spec.sample_format {
+      SampleFormat::Float => self.reader.samples::<f32>().next().transpose(),
+      SampleFormat::Int => {
+        let scale = 1.0 / (1_i64 << (spec.bits_per_sample - 1)) as f32;
+        let x = self.reader.samples::<i32>().next().transpose()?;
+        Ok(x.map(|x| x as f32 * scale))
+      }
+    }
+  }
+
+  // the next sample after picking/downmixing the channels
+  fn next_mixed(&mut self) -> Result<Option<f32>, WavError> {
+    let channels = self.reader.spec().channels;
+    match self.channel {
+      ChannelMix::Interleaved => self.next_raw(),
+      ChannelMix::Pick(channel) => {
+        let mut picked = None;
+        for i in 0..channels {
+          match self.next_raw()? {
+            Some(x) if i == channel => picked = Some(x),
+            Some(_) => {}
+            None => return Ok(None),
+          }
+        }
+        Ok(picked)
+      }
+      ChannelMix::Downmix => {
+        let mut sum = 0.0;
+        for _ in 0..channels {
+          match self.next_raw()? {
+            Some(x) => sum += x,
+            None => return Ok(None),
+          }
+        }
+        Ok(Some(sum / channels as f32))
+      }
+    }
+  }
+
+  // the next sample after resampling
+  fn next_sample(&mut self) -> Result<Option<f32>, WavError> {
+    let Some((_, pending)) = &self.resampler else {
+      return self.next_mixed();
+    };
+    let mut empty = pending.is_empty();
+    while empty && !self.flushed {
+      let x = self.next_mixed()?;
+      let (resampler, pending) = self.resampler.as_mut().unwrap();
+      match x {
+        Some(x) => resampler.process(&[x], pending),
+        // the end of the file, flush the samples held by the resampler
+        None => {
+          resampler.flush(pending);
+          self.flushed = true;
+        }
+      }
+      empty = pending.is_empty();
+    }
+    Ok(self.resampler.as_mut().and_then(|(_, pending)| pending.pop_front()))
   }
 }
 impl HoundInStream<BufReader<File>> {
-  pub fn open<P>(filename: P) -> HoundInStream<BufReader<File>>
+  pub fn open<P>(filename: P) -> Result<HoundInStream<BufReader<File>>, WavError>
   where
     P: AsRef<Path>,
   {
-    HoundInStream::new(hound::WavReader::open(filename).unwrap())
+    Ok(HoundInStream::new(hound::WavReader::open(filename)?))
+  }
+
+  /// open a wav file with the given options, see [`HoundInStream::with_config`]
+  pub fn open_with_config<P>(filename: P, config: &HoundInConfig) -> Result<HoundInStream<BufReader<File>>, WavError>
+  where
+    P: AsRef<Path>,
+  {
+    HoundInStream::with_config(hound::WavReader::open(filename)?, config)
   }
 }
 
@@ -40,20 +179,44 @@ impl<W: Write + Seek> HoundOutStream<W> {
   }
 }
 impl HoundOutStream<BufWriter<File>> {
-  pub fn create<P>(filename: P) -> HoundOutStream<BufWriter<File>>
+  pub fn create<P>(filename: P) -> Result<HoundOutStream<BufWriter<File>>, WavError>
+  where
+    P: AsRef<Path>,
+  {
+    Self::create_with_config(filename, &AudioConfig::default())
+  }
+
+  /// create a wav file whose sampling rate and channels are given by the audio config
+  pub fn create_with_config<P>(filename: P, config: &AudioConfig) -> Result<HoundOutStream<BufWriter<File>>, WavError>
   where
     P: AsRef<Path>,
   {
-    let spec = DefaultConfig::new();
-    HoundOutStream::new(hound::WavWriter::create(filename, spec).unwrap())
+    Self::create_with_spec(filename, config.into())
   }
+
+  /// Create a wav file with the given format, e.g. 16-bit integer PCM.
+  /// The samples written into the stream should be interleaved if there are multiple channels.
+  pub fn create_with_spec<P>(filename: P, spec: WavSpec) -> Result<HoundOutStream<BufWriter<File>>, WavError>
+  where
+    P: AsRef<Path>,
+  {
+    Ok(HoundOutStream::new(hound::WavWriter::create(filename, spec)?))
+  }
+}
+
+// an error of the options of a stream, reported as an io error like the errors of the file
+fn invalid_input(reason: String) -> WavError {
+  WavError::IoError(io::Error::new(io::ErrorKind::InvalidInput, reason))
 }
 
 impl<R: Read> InStream<FP, WavError> for HoundInStream<R> {
   fn read(&mut self, buf: &mut [FP]) -> Result<usize, WavError> {
     let mut n = 0;
-    for (x, sample) in buf.iter_mut().zip(self.0.samples()) {
-      *x = FP::from_f32(sample?);
+    for x in buf.iter_mut() {
+      match self.next_sample()? {
+        Some(sample) => *x = FP::from_f32(sample),
+        None => break,
+      }
       n += 1;
     }
     Ok(n)
@@ -65,9 +228,18 @@ impl<R: Read> InStream<FP, WavError> for HoundInStream<R> {
 }
 impl<W: Write + Seek> OutStream<FP, WavError> for HoundOutStream<W> {
   fn write(&mut self, buf: &[FP]) -> Result<usize, WavError> {
+    let spec = self.0.spec();
     let mut n = 0;
     for x in buf {
-      self.0.write_sample(FP::into_f32(*x))?;
+      match spec.sample_format {
+        SampleFormat::Float => self.0.write_sample(FP::into_f32(*x))?,
+        SampleFormat::Int => {
+          // scale into the integer range, clip the samples out of [-1, 1)
+          let max = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+          let y = (FP::into_f32(*x) * max).round().clamp(-max, max - 1.0);
+          self.0.write_sample(y as i32)?
+        }
+      }
       n += 1;
     }
     Ok(n)
@@ -1,17 +1,30 @@
 use crate::traits::FP;
 use crate::{
-  block_buffer::ConcurrentBuffer,
+  block_buffer::{ring_buffer, RingConsumer, RingProducer},
   traits::{InStream, OutStream},
+  DefaultConfig,
 };
 
 /// A loopback streae.
 /// The stream can read out whatever written into it.
-#[derive(Clone)]
-pub struct LoopBackStream(ConcurrentBuffer<FP>);
+pub struct LoopBackStream(RingProducer<FP>, RingConsumer<FP>);
 
 impl LoopBackStream {
+  /// A loopback stream holding at most 4 seconds of samples
   pub fn new() -> Self {
-    Self(ConcurrentBuffer::new())
+    Self::with_capacity(4 * DefaultConfig::SAMPLE_RATE as usize)
+  }
+
+  /// A loopback stream holding at most `capacity` samples,
+  /// writing into a full stream blocks until some samples are read out.
+  pub fn with_capacity(capacity: usize) -> Self {
+    let (producer, consumer) = ring_buffer(capacity);
+    Self(producer, consumer)
+  }
+
+  /// Split the stream into the writing end and the reading end, e.g. to use them in different threads.
+  pub fn split(self) -> (RingProducer<FP>, RingConsumer<FP>) {
+    (self.0, self.1)
   }
 }
 
@@ -23,11 +36,11 @@ impl Default for LoopBackStream {
 
 impl InStream<FP, ()> for LoopBackStream {
   fn read(&mut self, buf: &mut [FP]) -> Result<usize, ()> {
-    self.0.read(buf)
+    self.1.read(buf)
   }
 
   fn read_exact(&mut self, buf: &mut [FP]) -> Result<(), ()> {
-    self.0.read_exact(buf)
+    self.1.read_exact(buf)
   }
 }
 
@@ -39,7 +52,7 @@ impl OutStream<FP, ()> for LoopBackStream {
   fn write_exact(&mut self, buf: &[FP]) -> Result<(), ()> {
     self.0.write_exact(buf)
   }
-  /// wait for other thread to extract the samples
+  /// wait for the samples to be read out
   fn wait(&mut self) {
     self.0.wait()
   }
@@ -0,0 +1,277 @@
+use crate::traits::{InStream, OutStream, Sample, FP};
+use std::{collections::VecDeque, f32::consts::PI, marker::PhantomData};
+
+/// A windowed-sinc sample rate converter supporting arbitrary (even tiny fractional) ratios.
+///
+/// The interpolation kernel is a Blackman windowed sinc with [`Resampler::HALF_TAPS`] taps on each side,
+/// precomputed into a table with [`Resampler::PHASES`] entries per input sample and linearly interpolated.
+/// When downsampling, the cutoff frequency is lowered to avoid aliasing.
+///
+/// The first output sample is aligned with the first input sample,
+/// an output sample is emitted once the `HALF_TAPS` input samples after it are available.
+/// The computation is done in `f32` in both float and `nofloat` builds.
+#[derive(Clone, Debug)]
+pub struct Resampler {
+  // input samples advanced per output sample
+  step: f64,
+  // the kernel sampled on [-HALF_TAPS, HALF_TAPS] with `PHASES` points per input sample
+  kernel: Vec<f32>,
+  // input samples which are still in the support of the kernel
+  history: VecDeque<f32>,
+  // position of the next output sample in the history
+  time: f64,
+}
+
+impl Resampler {
+  /// number of input samples on each side of an output sample
+  pub const HALF_TAPS: usize = 16;
+  /// resolution of the kernel table: entries per input sample
+  pub const PHASES: usize = 128;
+  /// the cutoff frequency relative to the lower Nyquist frequency
+  const ROLLOFF: f32 = 0.95;
+
+  /// convert from `in_rate` to `out_rate`, e.g. 44100Hz to 48000Hz
+  pub fn new(in_rate: u32, out_rate: u32) -> Self {
+    Self::with_ratio(out_rate as f64 / in_rate as f64)
+  }
+
+  /// Convert with `ratio = output rate / input rate`.
+  /// A ratio close to 1, e.g. `1.0 + 50e-6`, compensates a sampling clock mismatch of 50ppm.
+  pub fn with_ratio(ratio: f64) -> Self {
+    assert!(ratio > 0.0, "resampling ratio must be positive");
+    let half = Self::HALF_TAPS as f32;
+    let cutoff = ratio.min(1.0) as f32 * Self::ROLLOFF;
+    let kernel = (0..=2 * Self::HALF_TAPS * Self::PHASES)
+      .map(|i| {
+        let d = i as f32 / Self::PHASES as f32 - half;
+        let x = d / half;
+        let window = 0.42 + 0.5 * (PI * x).cos() + 0.08 * (2.0 * PI * x).cos();
+        let sinc = if d == 0.0 {
+          1.0
+        } else {
+          (PI * cutoff * d).sin() / (PI * cutoff * d)
+        };
+        cutoff * sinc * window
+      })
+      .collect();
+    Self {
+      step: 1.0 / ratio,
+      kernel,
+      history: VecDeque::from(vec![0.0; Self::HALF_TAPS]),
+      time: Self::HALF_TAPS as f64,
+    }
+  }
+
+  /// output rate / input rate
+  pub fn ratio(&self) -> f64 {
+    1.0 / self.step
+  }
+
+  // the kernel at `d` input samples away from the center, `d` in `[-HALF_TAPS, HALF_TAPS]`
+  fn kernel(&self, d: f32) -> f32 {
+    let pos = (d + Self::HALF_TAPS as f32) * Self::PHASES as f32;
+    let i = (pos as usize).min(self.kernel.len() - 2);
+    let frac = pos - i as f32;
+    self.kernel[i] + (self.kernel[i + 1] - self.kernel[i]) * frac
+  }
+
+  /// Push input samples into the converter, the available output samples are appended to `output`.
+  pub fn process<O: Extend<f32>>(&mut self, input: &[f32], output: &mut O) {
+    let half = Self::HALF_TAPS as isize;
+    for &x in input {
+      self.history.push_back(x);
+      while (self.time.floor() as isize) + half < self.history.len() as isize {
+        let center = self.time.floor() as isize;
+        let frac = (self.time - center as f64) as f32;
+        let y = (center - half + 1..=center + half).fold(0.0, |y, k| {
+          y + self.history[k as usize] * self.kernel(frac + (center - k) as f32)
+        });
+        output.extend(Some(y));
+        self.time += self.step;
+      }
+      // drop the samples out of the support of the kernel
+      let consumed = (self.time.floor() as isize - half + 1).max(0) as usize;
+      self.history.drain(..consumed.min(self.history.len()));
+      self.time -= consumed as f64;
+    }
+  }
+
+  /// Push `HALF_TAPS` silent samples,
+  /// so that all the pushed samples go through the converter.
+  pub fn flush<O: Extend<f32>>(&mut self, output: &mut O) {
+    self.process(&[0.0; Self::HALF_TAPS], output)
+  }
+}
+
+/// An input stream adaptor which converts the sampling rate of the underlying stream,
+/// e.g. to read a 44.1kHz recording with a 48kHz PHY layer, or to compensate clock drift.
+pub struct ResampleInStream<S, E> {
+  stream: S,
+  resampler: Resampler,
+  // converted samples not read yet
+  pending: VecDeque<f32>,
+  buf: Vec<FP>,
+  input: Vec<f32>,
+  _err: PhantomData<E>,
+}
+
+impl<S, E> ResampleInStream<S, E>
+where
+  S: InStream<FP, E>,
+{
+  /// convert the samples of `stream` from `in_rate` to `out_rate`
+  pub fn new(stream: S, in_rate: u32, out_rate: u32) -> Self {
+    Self::with_resampler(stream, Resampler::new(in_rate, out_rate))
+  }
+
+  /// convert the samples of `stream` with `ratio = output rate / input rate`
+  pub fn with_ratio(stream: S, ratio: f64) -> Self {
+    Self::with_resampler(stream, Resampler::with_ratio(ratio))
+  }
+
+  fn with_resampler(stream: S, resampler: Resampler) -> Self {
+    Self {
+      stream,
+      resampler,
+      pending: VecDeque::new(),
+      buf: Vec::new(),
+      input: Vec::new(),
+      _err: PhantomData,
+    }
+  }
+
+  /// the underlying stream
+  pub fn into_inner(self) -> S {
+    self.stream
+  }
+
+  // number of input samples needed for `n` more output samples
+  fn input_len(&self, n: usize) -> usize {
+    (n as f64 / self.resampler.ratio()).ceil() as usize + 1
+  }
+
+  // convert the samples in `self.buf`
+  fn convert(&mut self, n: usize) {
+    self.input.clear();
+    self.input.extend(self.buf[..n].iter().map(|x| x.into_f32()));
+    self.resampler.process(&self.input, &mut self.pending);
+  }
+
+  // move the pending samples into `dest`
+  fn pop_pending(&mut self, dest: &mut [FP]) -> usize {
+    let n = dest.len().min(self.pending.len());
+    dest
+      .iter_mut()
+      .zip(self.pending.drain(..n))
+      .for_each(|(x, y)| *x = FP::from_f32(y));
+    n
+  }
+}
+
+impl<S, E> InStream<FP, E> for ResampleInStream<S, E>
+where
+  S: InStream<FP, E>,
+{
+  /// Read the samples available in the underlying stream, return with the number of converted samples read.
+  fn read(&mut self, buf: &mut [FP]) -> Result<usize, E> {
+    if self.pending.len() < buf.len() {
+      let len = self.input_len(buf.len() - self.pending.len());
+      self.buf.resize(len, FP::ZERO);
+      let n = self.stream.read(&mut self.buf)?;
+      self.convert(n);
+    }
+    Ok(self.pop_pending(buf))
+  }
+
+  /// Read exactly `buf.len()` converted samples.
+  fn read_exact(&mut self, buf: &mut [FP]) -> Result<(), E> {
+    while self.pending.len() < buf.len() {
+      let len = self.input_len(buf.len() - self.pending.len());
+      self.buf.resize(len, FP::ZERO);
+      self.stream.read_exact(&mut self.buf)?;
+      self.convert(len);
+    }
+    self.pop_pending(buf);
+    Ok(())
+  }
+}
+
+/// An output stream adaptor which converts the sampling rate of the samples before writing them
+/// into the underlying stream, e.g. to play a 48kHz PHY layer on a 44.1kHz device.
+pub struct ResampleOutStream<S, E> {
+  stream: S,
+  resampler: Resampler,
+  output: Vec<f32>,
+  buf: Vec<FP>,
+  _err: PhantomData<E>,
+}
+
+impl<S, E> ResampleOutStream<S, E>
+where
+  S: OutStream<FP, E>,
+{
+  /// convert the samples written into the stream from `in_rate` to `out_rate`
+  pub fn new(stream: S, in_rate: u32, out_rate: u32) -> Self {
+    Self::with_resampler(stream, Resampler::new(in_rate, out_rate))
+  }
+
+  /// convert the samples written into the stream with `ratio = output rate / input rate`
+  pub fn with_ratio(stream: S, ratio: f64) -> Self {
+    Self::with_resampler(stream, Resampler::with_ratio(ratio))
+  }
+
+  fn with_resampler(stream: S, resampler: Resampler) -> Self {
+    Self {
+      stream,
+      resampler,
+      output: Vec::new(),
+      buf: Vec::new(),
+      _err: PhantomData,
+    }
+  }
+
+  /// the underlying stream, the samples held by the converter are discarded
+  pub fn into_inner(self) -> S {
+    self.stream
+  }
+
+  /// End the stream: flush the samples held by the converter with some silence,
+  /// wait for the underlying stream and return it.
+  pub fn finish(mut self) -> Result<S, E> {
+    self.resampler.flush(&mut self.output);
+    self.write_output()?;
+    self.stream.wait();
+    Ok(self.stream)
+  }
+
+  // write the converted samples into the underlying stream
+  fn write_output(&mut self) -> Result<(), E> {
+    self.buf.clear();
+    self.buf.extend(self.output.drain(..).map(FP::from_f32));
+    self.stream.write_exact(&self.buf)
+  }
+}
+
+impl<S, E> OutStream<FP, E> for ResampleOutStream<S, E>
+where
+  S: OutStream<FP, E>,
+{
+  /// Convert all the samples and write them into the underlying stream.
+  /// The last `Resampler::HALF_TAPS` samples are held until more samples are written or the stream is finished,
+  /// see [`ResampleOutStream::finish`].
+  fn write(&mut self, buf: &[FP]) -> Result<usize, E> {
+    self.write_exact(buf).map(|_| buf.len())
+  }
+
+  fn write_exact(&mut self, buf: &[FP]) -> Result<(), E> {
+    let input: Vec<f32> = buf.iter().map(|x| x.into_f32()).collect();
+    self.resampler.process(&input, &mut self.output);
+    self.write_output()
+  }
+
+  /// Wait for the underlying stream.
+  /// The samples held by the converter are kept, so that the stream goes on seamlessly after waiting.
+  fn wait(&mut self) {
+    self.stream.wait()
+  }
+}
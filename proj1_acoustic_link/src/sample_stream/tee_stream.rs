@@ -0,0 +1,195 @@
+use super::HoundOutStream;
+use crate::{
+  traits::{InStream, OutStream, Sample, FP},
+  AudioConfig,
+};
+use hound::WavSpec;
+use std::{
+  fs::{self, File},
+  io::{self, BufWriter, Write},
+  path::{Path, PathBuf},
+  time::Instant,
+};
+
+/// Where the samples mirrored by a [`TeeStream`] are recorded
+pub enum TapSink {
+  /// a wav file
+  Wav(HoundOutStream<BufWriter<File>>),
+  /// a headerless file of little-endian `f32` samples
+  Raw(BufWriter<File>),
+}
+
+impl TapSink {
+  /// record the samples into a mono wav file at the sampling rate of the audio config
+  pub fn wav<P: AsRef<Path>>(path: P, config: &AudioConfig) -> io::Result<Self> {
+    let spec = WavSpec {
+      channels: 1,
+      ..config.into()
+    };
+    Ok(Self::Wav(HoundOutStream::create_with_spec(path, spec).map_err(wav_error)?))
+  }
+
+  /// record the samples into a raw file
+  pub fn raw<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    Ok(Self::Raw(BufWriter::new(File::create(path)?)))
+  }
+
+  fn record(&mut self, buf: &[FP]) -> io::Result<()> {
+    match self {
+      Self::Wav(wav) => wav.write_exact(buf).map_err(wav_error),
+      Self::Raw(file) => buf.iter().try_for_each(|x| file.write_all(&x.into_f32().to_le_bytes())),
+    }
+  }
+}
+
+// the io error of the file is kept as is, the other errors of hound are wrapped
+fn wav_error(e: hound::Error) -> io::Error {
+  match e {
+    hound::Error::IoError(e) => e,
+    e => io::Error::other(e),
+  }
+}
+
+/// A stream adaptor which mirrors all the samples read from/written into the underlying stream to a [`TapSink`],
+/// e.g. to keep a record of what went over the air.
+///
+/// Failing to record does not affect the underlying stream, the error is reported and the recording stops.
+pub struct TeeStream<S> {
+  stream: S,
+  sink: Option<TapSink>,
+  samples: u64,
+}
+
+impl<S> TeeStream<S> {
+  pub fn new(stream: S, sink: TapSink) -> Self {
+    Self {
+      stream,
+      sink: Some(sink),
+      samples: 0,
+    }
+  }
+
+  /// number of samples passed through the stream
+  pub fn samples(&self) -> u64 {
+    self.samples
+  }
+
+  /// the underlying stream, the recording is finished
+  pub fn into_inner(self) -> S {
+    self.stream
+  }
+
+  fn record(&mut self, buf: &[FP]) {
+    self.samples += buf.len() as u64;
+    if let Some(sink) = self.sink.as_mut() {
+      if let Err(e) = sink.record(buf) {
+        eprintln!("An error occured at tee stream, stop recording: {}", e);
+        self.sink = None;
+      }
+    }
+  }
+}
+
+impl<S, E> InStream<FP, E> for TeeStream<S>
+where
+  S: InStream<FP, E>,
+{
+  fn read(&mut self, buf: &mut [FP]) -> Result<usize, E> {
+    let n = self.stream.read(buf)?;
+    self.record(&buf[..n]);
+    Ok(n)
+  }
+
+  fn read_exact(&mut self, buf: &mut [FP]) -> Result<(), E> {
+    self.stream.read_exact(buf)?;
+    self.record(buf);
+    Ok(())
+  }
+}
+
+impl<S, E> OutStream<FP, E> for TeeStream<S>
+where
+  S: OutStream<FP, E>,
+{
+  fn write(&mut self, buf: &[FP]) -> Result<usize, E> {
+    let n = self.stream.write(buf)?;
+    self.record(&buf[..n]);
+    Ok(n)
+  }
+
+  fn write_exact(&mut self, buf: &[FP]) -> Result<(), E> {
+    self.stream.write_exact(buf)?;
+    self.record(buf);
+    Ok(())
+  }
+
+  fn wait(&mut self) {
+    self.stream.wait()
+  }
+}
+
+/// A log of the events happened on a captured stream, e.g. the packet boundaries.
+/// Each line is `<sample index>,<seconds since the log is created>,<event>`,
+/// the sample index locates the event in the recording of the stream.
+pub struct CaptureLog {
+  file: BufWriter<File>,
+  start: Instant,
+}
+
+impl CaptureLog {
+  pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "sample,seconds,event")?;
+    Ok(Self {
+      file,
+      start: Instant::now(),
+    })
+  }
+
+  /// Log an event at the given sample index, the line is flushed immediately.
+  /// Failing to log is reported but ignored.
+  pub fn mark(&mut self, sample: u64, event: &str) {
+    let seconds = self.start.elapsed().as_secs_f64();
+    let result = writeln!(self.file, "{},{:.6},{}", sample, seconds, event).and_then(|_| self.file.flush());
+    if let Err(e) = result {
+      eprintln!("An error occured at capture log: {}", e);
+    }
+  }
+}
+
+/// A directory holding the capture of a PHY layer:
+/// - `tx.wav`/`rx.wav`: the samples sent/received
+/// - `tx.log`/`rx.log`: the packets sent and the frames detected, see [`CaptureLog`]
+pub struct CaptureDir(PathBuf);
+
+impl CaptureDir {
+  /// use the directory for capturing, create it if not existing
+  pub fn create<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+    fs::create_dir_all(&dir)?;
+    Ok(Self(dir.as_ref().to_path_buf()))
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.0
+  }
+
+  /// record the samples read from an input stream into `rx.wav`
+  pub fn tee_in<S>(&self, stream: S, config: &AudioConfig) -> io::Result<TeeStream<S>> {
+    Ok(TeeStream::new(stream, TapSink::wav(self.0.join("rx.wav"), config)?))
+  }
+
+  /// record the samples written into an output stream into `tx.wav`
+  pub fn tee_out<S>(&self, stream: S, config: &AudioConfig) -> io::Result<TeeStream<S>> {
+    Ok(TeeStream::new(stream, TapSink::wav(self.0.join("tx.wav"), config)?))
+  }
+
+  /// the log of the receiver, `rx.log`
+  pub fn rx_log(&self) -> io::Result<CaptureLog> {
+    CaptureLog::create(self.0.join("rx.log"))
+  }
+
+  /// the log of the sender, `tx.log`
+  pub fn tx_log(&self) -> io::Result<CaptureLog> {
+    CaptureLog::create(self.0.join("tx.log"))
+  }
+}
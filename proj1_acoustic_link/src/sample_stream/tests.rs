@@ -0,0 +1,526 @@
+use super::{ChannelSimConfig, ChannelSimStream, VirtualAir};
+use crate::phy_packet::{
+  frame_detect::CorrelationFraming,
+  modem::{LineCode, DPSK, PSK},
+  preambles::ChirpUpDown,
+  txrx::{PhyReceiver, PhySender},
+  Modem,
+};
+use crate::traits::{InStream, OutStream, PacketReceiver, PacketSender, PowerProbe, Sample, FP};
+use crate::AudioConfig;
+use rand::{distributions::Standard, Rng};
+use std::{thread, time::Duration};
+
+const PACKETS: usize = 5;
+
+fn sine(len: usize) -> Vec<FP> {
+  (0..len).map(|i| FP::from_f32(0.33 * i as f32).sin()).collect()
+}
+
+/// an ideal channel delivers the samples untouched, up to the f32 precision of the simulation
+#[test]
+fn channel_sim_ideal() {
+  let samples = sine(1000);
+  let mut chan = ChannelSimStream::default();
+  chan.write_exact(&samples).unwrap();
+  let mut received = vec![FP::ZERO; samples.len()];
+  assert_eq!(chan.read(&mut received).unwrap(), samples.len());
+  let expected: Vec<_> = samples.iter().map(|x| FP::from_f32(x.into_f32())).collect();
+  assert_eq!(received, expected);
+}
+
+/// the same seed reproduces the same channel
+#[test]
+fn channel_sim_deterministic() {
+  let config = ChannelSimConfig {
+    seed: 120,
+    noise_std: 0.1,
+    drop_prob: 0.01,
+    insert_prob: 0.01,
+    ..Default::default()
+  };
+  let samples = sine(5000);
+  let run = |config: ChannelSimConfig| {
+    let mut chan = ChannelSimStream::new(config);
+    chan.write_exact(&samples).unwrap();
+    let mut received = vec![FP::ZERO; 2 * samples.len()];
+    let n = chan.read(&mut received).unwrap();
+    received.truncate(n);
+    received
+  };
+  assert_eq!(run(config.clone()), run(config.clone()));
+  assert_ne!(run(config.clone()), run(ChannelSimConfig { seed: 121, ..config }));
+}
+
+/// the receiver gets fewer samples when its clock is slower
+#[test]
+fn channel_sim_drift() {
+  const LEN: usize = 100000;
+  let mut chan = ChannelSimStream::new(ChannelSimConfig {
+    drift: 1e-3,
+    ..Default::default()
+  });
+  chan.write_exact(&sine(LEN)).unwrap();
+  let mut received = vec![FP::ZERO; LEN];
+  let n = chan.read(&mut received).unwrap();
+  assert!((n as f32 - LEN as f32 / 1.001).abs() <= 1.0);
+}
+
+/// Send packets with [`PhySender`] and receive them with [`PhyReceiver`] through a simulated channel,
+/// every packet is received with at most `max_bit_errors` bits wrong.
+fn phy_over_channel<MM: Modem + Send + 'static>(config: ChannelSimConfig, max_bit_errors: u32) {
+  let chan = ChannelSimStream::new(config);
+  let mut tx = PhySender::<ChirpUpDown, MM, _, ()>::new(chan.clone(), MM::default());
+  let detector = CorrelationFraming::<ChirpUpDown>::with_modem(ChirpUpDown::new(), MM::default());
+  let mut rx = PhyReceiver::<ChirpUpDown, MM, _, _, ()>::new(chan.clone(), MM::default(), detector);
+
+  // some silence before the first packet
+  chan.clone().write_exact(&[FP::ZERO; 1000]).unwrap();
+  for _ in 0..PACKETS {
+    let packet: Vec<u8> = rand::thread_rng()
+      .sample_iter(Standard)
+      .take(tx.packet_bytes())
+      .collect();
+    tx.send(packet.clone()).unwrap();
+    // the stream goes on after the frame, as an audio stream: a slow or dropping receiver gets the end of the frame
+    chan.clone().write_exact(&[FP::ZERO; 100]).unwrap();
+    let received = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+    assert_eq!(received.len(), packet.len());
+    let bit_errors: u32 = received.iter().zip(&packet).map(|(x, y)| (x ^ y).count_ones()).sum();
+    assert!(bit_errors <= max_bit_errors, "{} bits wrong", bit_errors);
+  }
+}
+
+fn noisy_channel() -> ChannelSimConfig {
+  ChannelSimConfig {
+    seed: 2022,
+    gain: 0.6,
+    taps: vec![(12, 0.1), (40, 0.05)],
+    noise_std: 0.02,
+    dc_offset: 0.01,
+    ..Default::default()
+  }
+}
+
+#[test]
+fn channel_sim_line_code() {
+  phy_over_channel::<LineCode>(noisy_channel(), 0);
+}
+
+#[test]
+fn channel_sim_psk() {
+  phy_over_channel::<PSK>(noisy_channel(), 0);
+}
+
+#[test]
+#[cfg(not(feature = "nofloat"))]
+fn channel_sim_ofdm() {
+  phy_over_channel::<crate::phy_packet::modem::OFDM>(noisy_channel(), 0);
+}
+
+/// DPSK follows the timing of a receiver whose clock is 200ppm slow
+#[test]
+fn channel_sim_drift_dpsk() {
+  let config = ChannelSimConfig {
+    drift: 2e-4,
+    ..noisy_channel()
+  };
+  phy_over_channel::<DPSK>(config, 0);
+}
+
+/// the frames are still found when the receiver drops samples,
+/// DPSK follows the timing slips, a slip only corrupts the symbols around it
+#[test]
+fn channel_sim_drop_dpsk() {
+  let config = ChannelSimConfig {
+    drop_prob: 1e-4,
+    ..noisy_channel()
+  };
+  phy_over_channel::<DPSK>(config, 8);
+}
+
+/// the frames are still found when the receiver inserts spurious samples,
+/// DPSK follows the timing slips, a slip only corrupts the symbols around it
+#[test]
+fn channel_sim_insert_dpsk() {
+  let config = ChannelSimConfig {
+    insert_prob: 1e-4,
+    ..noisy_channel()
+  };
+  phy_over_channel::<DPSK>(config, 8);
+}
+
+/// every node hears the sum of the other transmissions, delayed and scaled by the links
+#[test]
+fn virtual_air_mixing() {
+  const DELAY: usize = 10;
+  let air = VirtualAir::new(3);
+  air.set_link(0, 2, DELAY, 0.5);
+  let (_, mut out0, _) = air.node(0);
+  let (_, mut out1, _) = air.node(1);
+  let (mut in2, _, probe2) = air.node(2);
+
+  let impulse = [FP::ONE];
+  out0.write_exact(&impulse).unwrap();
+  out1.write_exact(&impulse).unwrap();
+  out0.wait();
+  out1.wait();
+  thread::sleep(Duration::from_millis(100));
+
+  let mut received = vec![FP::ZERO; 16 * AudioConfig::default().buffer_size];
+  let n = in2.read(&mut received).unwrap();
+  let start = received[..n].iter().position(|&x| x != FP::ZERO).unwrap();
+  let heard: Vec<_> = received[start..n].iter().map(|x| x.into_f32()).collect();
+  assert_eq!(heard[0], 1.0);
+  assert_eq!(heard[DELAY], 0.5);
+  assert_eq!(heard.iter().filter(|&&x| x != 0.0).count(), 2);
+  // only silence afterwards
+  assert!(probe2.power() < 1e-6);
+}
+
+/// a device is selected by "default", by index or by name
+#[test]
+fn device_selector_parse() {
+  use super::device::DeviceSelector;
+  assert_eq!("default".parse(), Ok(DeviceSelector::Default));
+  assert_eq!("2".parse(), Ok(DeviceSelector::Index(2)));
+  assert_eq!("USB Audio".parse(), Ok(DeviceSelector::Name(String::from("USB Audio"))));
+}
+
+/// interleaved samples of a multi-channel device are split by channel, and merged back
+#[test]
+fn channel_split_merge() {
+  use super::cpal_stream::{read_channels_from_stream, write_channels_to_stream};
+  use crate::block_buffer::ring_buffer;
+
+  // shorter than the block, which is processed in several pieces
+  let mut cast_buf = vec![FP::ZERO; 2];
+  let (mut producers, mut consumers): (Vec<_>, Vec<_>) = (0..2)
+    .map(|ch| {
+      let (producer, consumer) = ring_buffer(16);
+      ((ch, producer), (ch, consumer))
+    })
+    .unzip();
+  let interleaved = [0.1, -0.1, 0.2, -0.2, 0.3, -0.3];
+  read_channels_from_stream(&interleaved, 2, &mut cast_buf, &mut producers);
+  let mut left = [FP::ZERO; 3];
+  let mut right = [FP::ZERO; 3];
+  assert_eq!(consumers[0].1.read(&mut left).unwrap(), 3);
+  assert_eq!(consumers[1].1.read(&mut right).unwrap(), 3);
+  assert_eq!(left, [0.1, 0.2, 0.3].map(FP::from_f32));
+  assert_eq!(right, [-0.1, -0.2, -0.3].map(FP::from_f32));
+
+  // the right channel has fewer samples, the rest is padded with silence
+  producers[0].1.write(&left).unwrap();
+  producers[1].1.write(&right[..1]).unwrap();
+  let mut data = [1.0; 6];
+  write_channels_to_stream(&mut data, 2, &mut cast_buf, &mut consumers);
+  let expected = [0.1, -0.1, 0.2, 0.0, 0.3, 0.0].map(|x| FP::from_f32(x).into_f32());
+  assert_eq!(data, expected);
+
+  // a single channel out of three, the others are dropped on input and silent on output
+  let (producer, consumer) = ring_buffer(16);
+  let (mut producers, mut consumers) = ([(1, producer)], [(1, consumer)]);
+  read_channels_from_stream(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6], 3, &mut cast_buf, &mut producers);
+  let mut middle = [FP::ZERO; 3];
+  assert_eq!(consumers[0].1.read(&mut middle).unwrap(), 2);
+  assert_eq!(middle[..2], [0.2, 0.5].map(FP::from_f32));
+  producers[0].1.write(&middle[..2]).unwrap();
+  let mut data = [1.0; 6];
+  write_channels_to_stream(&mut data, 3, &mut cast_buf, &mut consumers);
+  let expected = [0.0, 0.2, 0.0, 0.0, 0.5, 0.0].map(|x| FP::from_f32(x).into_f32());
+  assert_eq!(data, expected);
+}
+
+// a path in the temporary directory which is not shared with other test processes
+fn wav_path(name: &str) -> std::path::PathBuf {
+  std::env::temp_dir().join(format!("{}_{}", std::process::id(), name))
+}
+
+fn int_spec(channels: u16, sample_rate: u32, bits_per_sample: u16) -> hound::WavSpec {
+  hound::WavSpec {
+    channels,
+    sample_rate,
+    bits_per_sample,
+    sample_format: hound::SampleFormat::Int,
+  }
+}
+
+fn tone(freq: f32, rate: f32, len: usize) -> Vec<f32> {
+  (0..len)
+    .map(|i| (std::f32::consts::TAU * freq * i as f32 / rate).sin())
+    .collect()
+}
+
+/// integer PCM of every width is decoded into normalized samples
+#[test]
+fn hound_int_pcm() {
+  use super::{HoundInStream, HoundOutStream};
+  let samples: Vec<FP> = sine(1000).into_iter().map(|x| x * FP::from_f32(0.9)).collect();
+  for bits in [8, 16, 24, 32] {
+    let path = wav_path(&format!("hound_int_pcm_{}.wav", bits));
+    let mut wav_out = HoundOutStream::create_with_spec(&path, int_spec(1, 48000, bits)).unwrap();
+    wav_out.write_exact(&samples).unwrap();
+    wav_out.finalize();
+
+    let mut wav_in = HoundInStream::open(&path).unwrap();
+    let mut received = vec![FP::ZERO; samples.len()];
+    assert_eq!(wav_in.read(&mut received).unwrap(), samples.len());
+    // quantization error, or the precision of f32 for 32-bit integers
+    let tolerance = (1.0 / (1_i64 << (bits - 1)) as f32).max(1e-6);
+    received
+      .iter()
+      .zip(samples.iter())
+      .for_each(|(x, y)| assert!((x.into_f32() - y.into_f32()).abs() <= tolerance));
+  }
+}
+
+/// a channel of a stereo file is picked, or the channels are downmixed
+#[test]
+fn hound_stereo_mix() {
+  use super::{ChannelMix, HoundInConfig, HoundInStream, HoundOutStream};
+  let path = wav_path("hound_stereo_mix.wav");
+  let left = sine(1000);
+  let interleaved: Vec<FP> = left.iter().flat_map(|&x| [x, FP::ZERO - x]).collect();
+  let mut wav_out = HoundOutStream::create_with_spec(&path, int_spec(2, 44100, 16)).unwrap();
+  wav_out.write_exact(&interleaved).unwrap();
+  wav_out.finalize();
+
+  let read = |channel| {
+    let config = HoundInConfig {
+      channel,
+      ..Default::default()
+    };
+    let mut wav_in = HoundInStream::open_with_config(&path, &config).unwrap();
+    let mut received = vec![FP::ZERO; 2 * left.len()];
+    let n = wav_in.read(&mut received).unwrap();
+    received.truncate(n);
+    received.into_iter().map(|x| x.into_f32()).collect::<Vec<_>>()
+  };
+  let right = read(ChannelMix::Pick(1));
+  assert_eq!(right.len(), left.len());
+  right
+    .iter()
+    .zip(left.iter())
+    .for_each(|(x, y)| assert!((x + y.into_f32()).abs() < 1e-4));
+  let mixed = read(ChannelMix::Downmix);
+  assert_eq!(mixed.len(), left.len());
+  assert!(mixed.iter().all(|x| x.abs() < 1e-4));
+  assert_eq!(read(ChannelMix::Interleaved).len(), interleaved.len());
+
+  // no third channel, and the interleaved channels can not be resampled
+  let missing = HoundInConfig {
+    channel: ChannelMix::Pick(2),
+    ..Default::default()
+  };
+  assert!(HoundInStream::open_with_config(&path, &missing).is_err());
+  let interleaved_resampled = HoundInConfig {
+    sample_rate: Some(48000),
+    ..Default::default()
+  };
+  assert!(HoundInStream::open_with_config(&path, &interleaved_resampled).is_err());
+}
+
+/// a 44.1kHz recording is resampled to the 48kHz link rate
+#[test]
+fn hound_resample() {
+  use super::{ChannelMix, HoundInConfig, HoundInStream, HoundOutStream};
+  const LEN: usize = 44100;
+  let path = wav_path("hound_resample.wav");
+  let mut wav_out = HoundOutStream::create_with_spec(
+    &path,
+    hound::WavSpec {
+      sample_format: hound::SampleFormat::Float,
+      ..int_spec(2, 44100, 32)
+    },
+  )
+  .unwrap();
+  let stereo: Vec<FP> = tone(1000.0, 44100.0, LEN)
+    .into_iter()
+    .flat_map(|x| [FP::ZERO, FP::from_f32(x)])
+    .collect();
+  wav_out.write_exact(&stereo).unwrap();
+  wav_out.finalize();
+
+  let config = HoundInConfig {
+    channel: ChannelMix::Pick(1),
+    sample_rate: Some(48000),
+  };
+  let mut wav_in = HoundInStream::open_with_config(&path, &config).unwrap();
+  let mut received = vec![FP::ZERO; 2 * LEN];
+  let n = wav_in.read(&mut received).unwrap();
+  assert!((n as f32 - LEN as f32 * 48000.0 / 44100.0).abs() <= 2.0);
+  received
+    .iter()
+    .zip(tone(1000.0, 48000.0, n))
+    .for_each(|(x, y)| assert!((x.into_f32() - y).abs() < 0.02));
+}
+
+/// a 16-bit recording is decoded offline by a `PhyReceiver`
+#[test]
+fn hound_offline_decode() {
+  use super::{HoundInStream, HoundOutStream};
+  let path = wav_path("hound_offline_decode.wav");
+  let mut wav_out = HoundOutStream::create_with_spec(&path, int_spec(1, 48000, 16)).unwrap();
+  wav_out.write_exact(&[FP::ZERO; 1000]).unwrap();
+  // the wav file is finalized when the sender is dropped
+  let mut tx = PhySender::<ChirpUpDown, PSK, _, _>::new(wav_out, PSK::default());
+  let packets: Vec<Vec<u8>> = (0..PACKETS)
+    .map(|_| {
+      rand::thread_rng()
+        .sample_iter(Standard)
+        .take(tx.packet_bytes())
+        .collect()
+    })
+    .collect();
+  packets.iter().for_each(|packet| tx.send(packet.clone()).unwrap());
+  drop(tx);
+
+  let wav_in = HoundInStream::open(&path).unwrap();
+  let detector = CorrelationFraming::<ChirpUpDown>::with_modem(ChirpUpDown::new(), PSK::default());
+  let mut rx = PhyReceiver::<ChirpUpDown, PSK, _, _, _>::new(wav_in, PSK::default(), detector);
+  for packet in packets {
+    assert_eq!(rx.recv_timeout(Duration::from_secs(1)).unwrap(), packet);
+  }
+}
+
+/// a 44.1kHz tone read through a 48kHz resampling stream
+#[test]
+fn resample_in_stream() {
+  use super::{LoopBackStream, ResampleInStream};
+  const LEN: usize = 44100;
+  let mut loopback = LoopBackStream::with_capacity(LEN);
+  let samples: Vec<FP> = tone(1000.0, 44100.0, LEN).into_iter().map(FP::from_f32).collect();
+  loopback.write_exact(&samples).unwrap();
+  let mut stream = ResampleInStream::new(loopback, 44100, 48000);
+  let mut received = vec![FP::ZERO; 2 * LEN];
+  let n = stream.read(&mut received).unwrap();
+  // the last samples are held until the input after them arrives
+  assert!((n as f32 - LEN as f32 * 48000.0 / 44100.0).abs() <= 20.0);
+  received
+    .iter()
+    .zip(tone(1000.0, 48000.0, n))
+    .for_each(|(x, y)| assert!((x.into_f32() - y).abs() < 0.01));
+}
+
+/// down-sampled by an output stream then up-sampled back by an input stream
+#[test]
+fn resample_round_trip() {
+  use super::{LoopBackStream, ResampleInStream, ResampleOutStream};
+  const LEN: usize = 48000;
+  let (producer, consumer) = LoopBackStream::with_capacity(LEN).split();
+  let mut tx = ResampleOutStream::new(producer, 48000, 44100);
+  let mut rx = ResampleInStream::new(consumer, 44100, 48000);
+  let samples: Vec<FP> = tone(3000.0, 48000.0, LEN).into_iter().map(FP::from_f32).collect();
+  let sent = samples.clone();
+  let handle = thread::spawn(move || {
+    tx.write_exact(&sent).unwrap();
+    // flush the samples held by the converter
+    tx.wait();
+  });
+  // the last samples are held by the converter of the receiver
+  let mut received = vec![FP::ZERO; LEN - 4 * super::Resampler::HALF_TAPS];
+  rx.read_exact(&mut received).unwrap();
+  // the sender waits until all the samples are read or the receiver is dropped
+  drop(rx);
+  handle.join().unwrap();
+  received
+    .iter()
+    .zip(samples.iter())
+    .for_each(|(x, y)| assert!((x.into_f32() - y.into_f32()).abs() < 0.02));
+}
+
+/// waiting in the middle of a resampled output stream does not break the samples
+#[test]
+fn resample_out_wait() {
+  use super::{ResampleOutStream, Resampler};
+  // collect the samples written, waiting returns immediately
+  struct Collect(Vec<f32>);
+  impl OutStream<FP, ()> for Collect {
+    fn write(&mut self, buf: &[FP]) -> Result<usize, ()> {
+      self.0.extend(buf.iter().map(|x| x.into_f32()));
+      Ok(buf.len())
+    }
+    fn write_exact(&mut self, buf: &[FP]) -> Result<(), ()> {
+      self.write(buf).map(|_| ())
+    }
+    fn wait(&mut self) {}
+  }
+
+  let samples: Vec<FP> = tone(1000.0, 48000.0, 4800).into_iter().map(FP::from_f32).collect();
+  let mut tx = ResampleOutStream::new(Collect(Vec::new()), 48000, 44100);
+  for chunk in samples.chunks(1000) {
+    tx.write_exact(chunk).unwrap();
+    tx.wait();
+  }
+  let Collect(received) = tx.finish().unwrap();
+
+  let mut resampler = Resampler::new(48000, 44100);
+  let mut expected = Vec::new();
+  let input: Vec<f32> = samples.iter().map(|x| x.into_f32()).collect();
+  resampler.process(&input, &mut expected);
+  resampler.flush(&mut expected);
+  assert_eq!(received.len(), expected.len());
+  received
+    .iter()
+    .zip(expected.iter())
+    .for_each(|(x, y)| assert!((x - y).abs() < 1e-3));
+}
+
+/// the clock drift of the channel is compensated by resampling the received samples
+#[test]
+fn resample_drift_compensation() {
+  use super::ResampleInStream;
+  const DRIFT: f32 = 2e-3;
+  let chan = ChannelSimStream::new(ChannelSimConfig {
+    drift: DRIFT,
+    ..noisy_channel()
+  });
+  let mut tx = PhySender::<ChirpUpDown, PSK, _, ()>::new(chan.clone(), PSK::default());
+  let detector = CorrelationFraming::<ChirpUpDown>::with_modem(ChirpUpDown::new(), PSK::default());
+  let stream_in = ResampleInStream::with_ratio(chan.clone(), 1.0 + DRIFT as f64);
+  let mut rx = PhyReceiver::<ChirpUpDown, PSK, _, _, ()>::new(stream_in, PSK::default(), detector);
+
+  chan.clone().write_exact(&[FP::ZERO; 1000]).unwrap();
+  for _ in 0..PACKETS {
+    let packet: Vec<u8> = rand::thread_rng()
+      .sample_iter(Standard)
+      .take(tx.packet_bytes())
+      .collect();
+    tx.send(packet.clone()).unwrap();
+    // the resampler holds the last samples until the following ones arrive
+    chan.clone().write_exact(&[FP::ZERO; 100]).unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(1)).unwrap(), packet);
+  }
+}
+
+/// the samples written into a tee stream are mirrored into a raw file
+#[test]
+fn tee_raw() {
+  use super::{LoopBackStream, TapSink, TeeStream};
+  let path = wav_path("tee_raw.f32");
+  let samples = sine(100);
+  let mut tee = TeeStream::new(LoopBackStream::new(), TapSink::raw(&path).unwrap());
+  tee.write_exact(&samples).unwrap();
+  assert_eq!(tee.samples(), samples.len() as u64);
+  // the file is flushed when the stream is dropped
+  let mut received = vec![FP::ZERO; samples.len()];
+  tee.into_inner().read_exact(&mut received).unwrap();
+  assert_eq!(received, samples);
+
+  let recorded: Vec<f32> = std::fs::read(&path)
+    .unwrap()
+    .chunks(4)
+    .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+    .collect();
+  assert_eq!(recorded, samples.iter().map(|x| x.into_f32()).collect::<Vec<_>>());
+}
+
+/// failing to create the wav file of a tap is reported to the caller
+#[test]
+fn tap_wav_error() {
+  use super::TapSink;
+  let path = wav_path("no_such_dir").join("tap.wav");
+  let e = TapSink::wav(path, &AudioConfig::default()).err().unwrap();
+  assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+}
@@ -0,0 +1,232 @@
+use crate::{
+  block_buffer::ConcurrentBuffer,
+  traits::{InStream, OutStream, PowerProbe, Sample, FP},
+  AudioConfig,
+};
+use crossbeam::channel::{unbounded as unbounded_channel, Sender};
+use parking_lot::Mutex;
+use std::{
+  collections::VecDeque,
+  sync::Arc,
+  thread::{self, JoinHandle},
+  time::{Duration, Instant},
+};
+
+// a directed link from one node to another
+struct Link {
+  gain: FP,
+  // samples on the air, the first `delay` samples are heard by the receiver next
+  delay_line: VecDeque<FP>,
+}
+
+impl Link {
+  fn new(delay: usize, gain: f32) -> Self {
+    Self {
+      gain: FP::from_f32(gain),
+      delay_line: VecDeque::from(vec![FP::ZERO; delay]),
+    }
+  }
+}
+
+// the tx/rx buffers and the power level of a node
+#[derive(Clone)]
+struct NodePort {
+  tx: ConcurrentBuffer<FP>,
+  rx: ConcurrentBuffer<FP>,
+  power: Arc<Mutex<f32>>,
+  window: usize,
+}
+
+struct AirState {
+  config: AudioConfig,
+  ports: Vec<NodePort>,
+  // `links[from][to]`
+  links: Vec<Vec<Link>>,
+  // recently received samples of each node, for power probing
+  power_windows: Vec<VecDeque<f32>>,
+}
+
+impl AirState {
+  // move one block of samples through the air:
+  // fetch from every sender, mix and deliver to every receiver.
+  fn tick(&mut self) {
+    let block = self.config.buffer_size;
+    let n = self.ports.len();
+    let mut tx_block = vec![FP::ZERO; block];
+    for (port, links) in self.ports.iter_mut().zip(self.links.iter_mut()) {
+      tx_block.iter_mut().for_each(|x| *x = FP::ZERO);
+      port.tx.read(&mut tx_block).unwrap();
+      links
+        .iter_mut()
+        .for_each(|link| link.delay_line.extend(tx_block.iter()));
+    }
+
+    for to in 0..n {
+      let mut rx_block = vec![FP::ZERO; block];
+      for from in 0..n {
+        let link = &mut self.links[from][to];
+        let gain = link.gain;
+        rx_block
+          .iter_mut()
+          .zip(link.delay_line.drain(..block))
+          .for_each(|(y, x)| *y += gain * x);
+      }
+
+      let window = &mut self.power_windows[to];
+      let mut power = self.ports[to].power.lock();
+      rx_block.iter().map(|x| x.into_f32()).for_each(|x| {
+        if window.len() == self.config.pwr_probe_wind {
+          let y = window.pop_front().unwrap();
+          *power -= y * y;
+        }
+        window.push_back(x);
+        *power += x * x;
+      });
+      drop(power);
+
+      self.ports[to].rx.write(&rx_block).unwrap();
+    }
+  }
+}
+
+/// An in-process shared acoustic medium for testing multiple access.
+///
+/// Every node gets an input stream, an output stream and a power probe.
+/// A worker thread plays the role of the sound cards:
+/// it consumes the samples written by every node at the sampling rate,
+/// and every node hears the sum of the transmissions of the others,
+/// each delayed and scaled by the parameters of the link.
+///
+/// By default, all the links have no delay and unit gain, and a node does not hear itself.
+pub struct VirtualAir {
+  state: Arc<Mutex<AirState>>,
+  exit_tx: Sender<()>,
+  handler: Option<JoinHandle<()>>,
+}
+
+impl VirtualAir {
+  /// create a medium shared by `nodes` nodes
+  pub fn new(nodes: usize) -> Self {
+    Self::with_config(nodes, &AudioConfig::default())
+  }
+
+  /// Create a medium shared by `nodes` nodes.
+  /// The samples move through the air at the sampling rate, one block of `buffer_size` samples at a time.
+  pub fn with_config(nodes: usize, config: &AudioConfig) -> Self {
+    let ports = (0..nodes)
+      .map(|_| NodePort {
+        tx: ConcurrentBuffer::new(),
+        rx: ConcurrentBuffer::new(),
+        power: Arc::new(Mutex::new(0.0)),
+        window: config.pwr_probe_wind,
+      })
+      .collect();
+    let links = (0..nodes)
+      .map(|from| {
+        (0..nodes)
+          .map(|to| Link::new(0, if from == to { 0.0 } else { 1.0 }))
+          .collect()
+      })
+      .collect();
+    let power_windows = (0..nodes)
+      .map(|_| VecDeque::with_capacity(config.pwr_probe_wind))
+      .collect();
+    let state = Arc::new(Mutex::new(AirState {
+      config: *config,
+      ports,
+      links,
+      power_windows,
+    }));
+
+    let (exit_tx, exit_rx) = unbounded_channel();
+    let st = state.clone();
+    let (sample_rate, block) = (config.sample_rate as f64, config.buffer_size);
+    let handler = thread::spawn(move || {
+      let start = Instant::now();
+      let mut played = 0;
+      while exit_rx.try_recv().is_err() {
+        let due = (start.elapsed().as_secs_f64() * sample_rate) as usize;
+        while played + block <= due {
+          st.lock().tick();
+          played += block;
+        }
+        thread::sleep(Duration::from_millis(1));
+      }
+    });
+
+    Self {
+      state,
+      exit_tx,
+      handler: Some(handler),
+    }
+  }
+
+  /// number of nodes sharing the medium
+  pub fn nodes(&self) -> usize {
+    self.state.lock().ports.len()
+  }
+
+  /// Set the delay (in samples) and the gain of the link from node `from` to node `to`.
+  /// Samples of the link which are still on the air are discarded.
+  pub fn set_link(&self, from: usize, to: usize, delay: usize, gain: f32) {
+    self.state.lock().links[from][to] = Link::new(delay, gain);
+  }
+
+  /// The input stream, the output stream and the power probe of node `id`.
+  pub fn node(&self, id: usize) -> (AirInStream, AirOutStream, AirPowerProbe) {
+    let port = self.state.lock().ports[id].clone();
+    (
+      AirInStream(port.rx),
+      AirOutStream(port.tx),
+      AirPowerProbe(port.power, port.window),
+    )
+  }
+}
+
+impl Drop for VirtualAir {
+  // stop the worker thread
+  fn drop(&mut self) {
+    self.exit_tx.send(()).unwrap();
+    if let Some(worker) = self.handler.take() {
+      worker.join().unwrap();
+    }
+  }
+}
+
+/// Samples heard by a node of a [`VirtualAir`]
+pub struct AirInStream(ConcurrentBuffer<FP>);
+/// Samples sent by a node of a [`VirtualAir`]
+pub struct AirOutStream(ConcurrentBuffer<FP>);
+/// Power level of the signal heard by a node of a [`VirtualAir`]
+pub struct AirPowerProbe(Arc<Mutex<f32>>, usize);
+
+impl InStream<FP, ()> for AirInStream {
+  fn read(&mut self, buf: &mut [FP]) -> Result<usize, ()> {
+    self.0.read(buf)
+  }
+
+  fn read_exact(&mut self, buf: &mut [FP]) -> Result<(), ()> {
+    self.0.read_exact(buf)
+  }
+}
+
+impl OutStream<FP, ()> for AirOutStream {
+  fn write(&mut self, buf: &[FP]) -> Result<usize, ()> {
+    self.0.write(buf)
+  }
+
+  fn write_exact(&mut self, buf: &[FP]) -> Result<(), ()> {
+    self.0.write_exact(buf)
+  }
+
+  /// wait for the samples to be sent on the air
+  fn wait(&mut self) {
+    self.0.wait()
+  }
+}
+
+impl PowerProbe for AirPowerProbe {
+  fn power(&self) -> f32 {
+    *self.0.lock() / self.1 as f32
+  }
+}
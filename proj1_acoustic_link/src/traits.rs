@@ -3,4 +3,4 @@ mod sample;
 mod stream;
 pub use packet::{PacketReceiver, PacketSender};
 pub use sample::{Sample, FP};
-pub use stream::{InStream, OutStream};
+pub use stream::{InStream, OutStream, PowerProbe};
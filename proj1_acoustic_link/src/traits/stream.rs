@@ -22,3 +22,45 @@ pub trait OutStream<T, E> {
   /// Wait until the output stream buffer is empty, all the data are fetched
   fn wait(&mut self);
 }
+
+/// Monitor the power level of the signal on a channel.
+pub trait PowerProbe {
+  /// The average power of the recently received samples.
+  fn power(&self) -> f32;
+}
+
+impl<T, E, S> InStream<T, E> for Box<S>
+where
+  S: InStream<T, E> + ?Sized,
+{
+  fn read(&mut self, buf: &mut [T]) -> Result<usize, E> {
+    (**self).read(buf)
+  }
+
+  fn read_exact(&mut self, buf: &mut [T]) -> Result<(), E> {
+    (**self).read_exact(buf)
+  }
+}
+
+impl<T, E, S> OutStream<T, E> for Box<S>
+where
+  S: OutStream<T, E> + ?Sized,
+{
+  fn write(&mut self, buf: &[T]) -> Result<usize, E> {
+    (**self).write(buf)
+  }
+
+  fn write_exact(&mut self, buf: &[T]) -> Result<(), E> {
+    (**self).write_exact(buf)
+  }
+
+  fn wait(&mut self) {
+    (**self).wait()
+  }
+}
+
+impl<S: PowerProbe + ?Sized> PowerProbe for Box<S> {
+  fn power(&self) -> f32 {
+    (**self).power()
+  }
+}
@@ -34,7 +34,7 @@ pub fn part1_ck2() {
   const RECDURATION: u64 = 10;
   const FILENAME: &str = "winter.wav";
   let mut buf = [FP::ZERO; DefaultConfig::BUFFER_SIZE];
-  let mut hound_in_stream = HoundInStream::open(FILENAME);
+  let mut hound_in_stream = HoundInStream::open(FILENAME).unwrap();
   let mut cpal_out_stream = CpalOutStream::default();
   let mut cpal_in_stream = CpalInStream::default();
   while hound_in_stream.read(&mut buf).unwrap() != 0 {
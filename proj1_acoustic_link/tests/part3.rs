@@ -7,7 +7,7 @@ use std::fs;
 use std::thread::sleep;
 use std::time::Duration;
 
-const CHUNK_LEN: usize = PlainPHY::PACKET_BYTES;
+const CHUNK_LEN: usize = <PlainPHY>::PACKET_BYTES;
 const DATA_LEN: usize = 10000 / 8;
 const PAD_LEN: usize = (CHUNK_LEN - DATA_LEN % CHUNK_LEN) % CHUNK_LEN;
 
@@ -15,7 +15,7 @@ const PAD_LEN: usize = (CHUNK_LEN - DATA_LEN % CHUNK_LEN) % CHUNK_LEN;
 #[ignore]
 fn part3_ck1_send() {
   const FILEPATH: &str = "INPUT.txt";
-  let mut physics_layer = PlainPHY::default();
+  let mut physics_layer = <PlainPHY>::default();
 
   let data_string = fs::read_to_string(FILEPATH).unwrap();
   let bits = chars_to_bits(data_string.trim_end());
@@ -35,7 +35,7 @@ fn part3_ck1_send() {
 #[ignore]
 fn part3_ck1_recv() {
   const FILEPATH: &str = "OUTPUT.txt";
-  let mut physics_layer = PlainPHY::default();
+  let mut physics_layer = <PlainPHY>::default();
 
   let mut bytes = vec![0; DATA_LEN + PAD_LEN];
   bytes.chunks_exact_mut(CHUNK_LEN).enumerate().for_each(|(idx, chunk)| {
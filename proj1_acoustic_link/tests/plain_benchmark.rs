@@ -11,7 +11,7 @@ fn plain_benchmark() {
   use std::time::{Duration, Instant};
 
   // prepare data
-  const PACK_BYTES: usize = PHY::PACKET_BYTES;
+  const PACK_BYTES: usize = <PHY>::PACKET_BYTES;
   const SEND_PACKS: usize = 100;
   const TOTAL_BYTES: usize = PACK_BYTES * SEND_PACKS;
   let data: Vec<u8> = rand::thread_rng().sample_iter(Standard).take(TOTAL_BYTES).collect();
@@ -22,7 +22,7 @@ fn plain_benchmark() {
   let start = Instant::now();
 
   // transmission
-  let mut phy = PHY::default();
+  let mut phy = <PHY>::default();
   for (idx, packet) in data.chunks_exact(PACK_BYTES).enumerate() {
     let mut retry_before_succ: Option<usize> = None;
     for retry in 0..10 {
@@ -0,0 +1,95 @@
+use proj1_acoustic_link::{
+  helper::CrcSeq,
+  phy_packet::{
+    frame_detect::CorrelationFraming,
+    header::LengthHeader,
+    modem::PSK,
+    preambles::ChirpUpDown,
+    replay::{align, replay, ReplayFrame},
+    Modem, PreambleGen,
+  },
+  sample_stream::{HoundInStream, HoundOutStream},
+  traits::{OutStream, Sample, FP},
+  AudioConfig,
+};
+use rand::{distributions::Standard, Rng};
+
+/// every frame of a 16-bit capture is found at its offset and decoded, whatever the length of its packet
+#[test]
+fn replay_capture() {
+  const PACKETS: usize = 5;
+  const GAP: usize = 1000;
+  let path = std::env::temp_dir().join(format!("{}_replay_capture.wav", std::process::id()));
+  let spec = hound::WavSpec {
+    bits_per_sample: 16,
+    sample_format: hound::SampleFormat::Int,
+    ..(&AudioConfig::default()).into()
+  };
+  let mut wav_out = HoundOutStream::create_with_spec(&path, spec).unwrap();
+  let mut modem = PSK::default();
+  let preamble = ChirpUpDown::new().samples();
+  let packets: Vec<Vec<u8>> = (0..PACKETS)
+    .map(|_| {
+      rand::thread_rng()
+        .sample_iter(Standard)
+        .take(rand::thread_rng().gen_range(0..=modem.bytes_per_packet()))
+        .collect()
+    })
+    .collect();
+  let mut offsets = Vec::new();
+  let mut samples = 0;
+  for packet in &packets {
+    wav_out.write_exact(&[FP::ZERO; GAP]).unwrap();
+    offsets.push(samples + GAP);
+    let header = modem.modulate(&LengthHeader::encode(packet.len()));
+    let payload = modem.modulate(packet);
+    wav_out.write_exact(&preamble).unwrap();
+    wav_out.write_exact(&header).unwrap();
+    wav_out.write_exact(&payload).unwrap();
+    samples += GAP + preamble.len() + header.len() + payload.len();
+  }
+  wav_out.write_exact(&[FP::ZERO; GAP]).unwrap();
+  wav_out.finalize();
+
+  let detector = CorrelationFraming::with_modem(ChirpUpDown::new(), PSK::default());
+  let frames = replay(HoundInStream::open(&path).unwrap(), detector, PSK::default()).unwrap();
+  assert_eq!(frames.len(), PACKETS);
+  for ((frame, packet), offset) in frames.iter().zip(packets).zip(offsets) {
+    assert_eq!(frame.packet, packet);
+    assert_eq!(frame.offset, offset);
+    assert!(frame.peak > CorrelationFraming::<ChirpUpDown>::CORR_MIN);
+  }
+}
+
+/// the frames are paired with the packets sent by their sequence numbers, despite the lost and false frames
+#[test]
+fn align_by_seq() {
+  type CS = CrcSeq<8>;
+  let seq = |packet: &[u8]| CS::unpack(packet).map(|(_, seq)| seq);
+  let sent: Vec<Vec<u8>> = (0..8).map(|i| CS::pack(&[i; 4], i % 4)).collect();
+  let mut corrupted = sent[1].clone();
+  corrupted[0] ^= 1;
+  let frames: Vec<ReplayFrame> = [
+    sent[0].clone(),
+    corrupted,
+    // packets 2 and 3 are lost
+    sent[4].clone(),
+    // a false detection whose sequence number matches none of the next packets
+    CS::pack(&[0xff], 0),
+    // packets 5 and 6 are lost
+    sent[7].clone(),
+  ]
+  .into_iter()
+  .map(|packet| ReplayFrame {
+    offset: 0,
+    peak: 1.0,
+    packet,
+  })
+  .collect();
+  assert_eq!(align(&frames, &sent, seq), [Some(0), Some(1), Some(4), None, Some(7)]);
+  // without sequence numbers, the frames are paired in order
+  assert_eq!(
+    align(&frames, &sent, |_| None),
+    [Some(0), Some(1), Some(2), Some(3), Some(4)]
+  );
+}
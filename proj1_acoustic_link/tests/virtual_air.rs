@@ -0,0 +1,308 @@
+#[cfg(feature = "wired")]
+mod virtual_air {
+  use proj1_acoustic_link::{
+    phy_layer::{CrcPhy, CrcPhyRecvErr, PhyLayer, PlainPHY},
+    sample_stream::{CaptureDir, VirtualAir},
+    traits::{OutStream, PacketReceiver, PacketSender, Sample, FP},
+    AudioConfig,
+  };
+  use rand::{distributions::Standard, Rng};
+  use std::{sync::Barrier, thread, time::Duration};
+
+  const NODES: usize = 3;
+  const RECV_TIMEOUT: Duration = Duration::from_millis(500);
+
+  fn plain_nodes(air: &VirtualAir) -> Vec<PlainPHY> {
+    (0..air.nodes())
+      .map(|id| {
+        let (stream_in, stream_out, power_probe) = air.node(id);
+        <PlainPHY>::with_streams(stream_in, stream_out, power_probe)
+      })
+      .collect()
+  }
+  fn nodes(air: &VirtualAir) -> Vec<CrcPhy> {
+    plain_nodes(air).into_iter().map(CrcPhy::new).collect()
+  }
+  fn random_packet() -> Vec<u8> {
+    rand::thread_rng()
+      .sample_iter(Standard)
+      .take(<CrcPhy>::PACKET_BYTES)
+      .collect()
+  }
+
+  /// a packet sent by one node is heard by all the others
+  #[test]
+  fn broadcast() {
+    let air = VirtualAir::new(NODES);
+    let mut phys = nodes(&air);
+    let packet = random_packet();
+
+    phys[0].send(packet.clone()).unwrap();
+    for phy in phys.iter_mut().skip(1) {
+      assert_eq!(phy.recv_timeout(RECV_TIMEOUT).unwrap(), packet);
+    }
+    // a node does not hear itself
+    assert!(phys[0].recv_timeout(RECV_TIMEOUT).is_err());
+  }
+
+  /// packets shorter than the maximum are sent in shorter frames and received with their own length
+  #[test]
+  fn short_packets() {
+    let air = VirtualAir::new(2);
+    let phys = plain_nodes(&air);
+    assert!(phys[0].frame_samples(0) < phys[0].frame_samples(<PlainPHY>::PACKET_BYTES));
+    let mut phys: Vec<_> = phys.into_iter().map(CrcPhy::new).collect();
+
+    for len in [0, 1, <CrcPhy>::PACKET_BYTES / 2, <CrcPhy>::PACKET_BYTES] {
+      let packet = random_packet()[..len].to_vec();
+      phys[0].send(packet.clone()).unwrap();
+      assert_eq!(phys[1].recv_timeout(RECV_TIMEOUT).unwrap(), packet);
+    }
+  }
+
+  /// two nodes with the same non-default parameters talk to each other,
+  /// the parameters are checked against the audio config
+  #[test]
+  fn custom_params() {
+    use proj1_acoustic_link::{
+      phy_layer::PhyParams,
+      phy_packet::{
+        modem::LineCodeParams,
+        preambles::{ChirpParams, PreambleParams},
+      },
+    };
+    let air = VirtualAir::new(2);
+    let config = Default::default();
+    let params = PhyParams {
+      preamble: ChirpParams::AIR.into(),
+      modem: LineCodeParams {
+        samples_per_bit: 4,
+        bits_per_packet: 400,
+      },
+    };
+    let mut phys: Vec<_> = (0..2)
+      .map(|id| {
+        let (stream_in, stream_out, power_probe) = air.node(id);
+        <PlainPHY>::with_streams_params(stream_in, stream_out, power_probe, &config, &params).unwrap()
+      })
+      .collect();
+    assert_eq!(phys[0].packet_bytes(), 50);
+
+    let packet = random_packet()[..50].to_vec();
+    phys[0].send(packet.clone()).unwrap();
+    assert_eq!(phys[1].recv_timeout(RECV_TIMEOUT).unwrap(), packet);
+
+    let invalid = PhyParams {
+      preamble: PreambleParams::Chirp(ChirpParams {
+        fb: 1e6,
+        ..ChirpParams::AIR
+      }),
+      ..params
+    };
+    let (stream_in, stream_out, power_probe) = air.node(0);
+    assert!(<PlainPHY>::with_streams_params(stream_in, stream_out, power_probe, &config, &invalid).is_err());
+  }
+
+  /// the channel is busy while a node is transmitting
+  #[test]
+  fn carrier_sense() {
+    let air = VirtualAir::new(NODES);
+    let mut phys = nodes(&air);
+    assert!(phys.iter().all(|phy| phy.channel_free()));
+
+    let mut sender = phys.remove(0);
+    let handler = thread::spawn(move || {
+      for _ in 0..3 {
+        sender.send(random_packet()).unwrap();
+      }
+    });
+    let mut busy = false;
+    while !handler.is_finished() {
+      busy |= !phys[0].channel_free() && !phys[1].channel_free();
+      thread::sleep(Duration::from_millis(1));
+    }
+    handler.join().unwrap();
+    assert!(busy);
+
+    thread::sleep(Duration::from_millis(100));
+    assert!(phys.iter().all(|phy| phy.channel_free()));
+  }
+
+  // every packet received intact until the receiver hears nothing more, the corrupted ones are skipped
+  fn recv_all(phy: &mut CrcPhy) -> Vec<Vec<u8>> {
+    let mut packets = Vec::new();
+    loop {
+      match phy.recv_timeout(RECV_TIMEOUT) {
+        Ok(packet) => packets.push(packet),
+        Err(CrcPhyRecvErr::Corrupt) => continue,
+        Err(CrcPhyRecvErr::NoPacket) => return packets,
+      }
+    }
+  }
+
+  /// two nodes transmitting at the same time collide at the third one,
+  /// the same frames sent one after the other arrive intact
+  #[test]
+  fn collision() {
+    let air = VirtualAir::new(NODES);
+    let mut phys = nodes(&air);
+    let mut receiver = phys.pop().unwrap();
+    let packets = [random_packet(), random_packet()];
+
+    let start = Barrier::new(phys.len());
+    thread::scope(|scope| {
+      for (phy, packet) in phys.iter_mut().zip(&packets) {
+        let start = &start;
+        scope.spawn(move || {
+          start.wait();
+          phy.send(packet.clone()).unwrap();
+        });
+      }
+    });
+    let received = recv_all(&mut receiver);
+    assert!(packets.iter().all(|packet| !received.contains(packet)));
+
+    for (phy, packet) in phys.iter_mut().zip(&packets) {
+      phy.send(packet.clone()).unwrap();
+    }
+    assert_eq!(recv_all(&mut receiver), packets);
+  }
+
+  /// a capturing node records the samples and the packet boundaries on both directions
+  #[test]
+  fn capture() {
+    const PACKETS: usize = 3;
+    let dir = std::env::temp_dir().join(format!("{}_virtual_air_capture", std::process::id()));
+    let capture = CaptureDir::create(&dir).unwrap();
+    let air = VirtualAir::new(2);
+    let (stream_in, stream_out, power_probe) = air.node(0);
+    let config = Default::default();
+    let mut phy0 = <PlainPHY>::with_streams_capture(stream_in, stream_out, power_probe, &config, &capture).unwrap();
+    let (stream_in, stream_out, power_probe) = air.node(1);
+    let mut phy1 = <PlainPHY>::with_streams(stream_in, stream_out, power_probe);
+
+    let packet = vec![0x5a; <PlainPHY>::PACKET_BYTES];
+    for _ in 0..PACKETS {
+      phy0.send(packet.clone()).unwrap();
+      assert_eq!(phy1.recv_timeout(RECV_TIMEOUT).unwrap(), packet);
+    }
+    phy1.send(packet.clone()).unwrap();
+    assert_eq!(phy0.recv_timeout(RECV_TIMEOUT).unwrap(), packet);
+    let frame_samples = phy0.frame_samples(packet.len());
+    // the recordings are finished when the PHY layer is dropped
+    drop(phy0);
+
+    let tx_log = std::fs::read_to_string(dir.join("tx.log")).unwrap();
+    let frames: Vec<(u64, &str)> = tx_log
+      .lines()
+      .skip(1)
+      .map(|line| {
+        let fields: Vec<_> = line.split(',').collect();
+        (fields[0].parse().unwrap(), fields[2])
+      })
+      .collect();
+    assert_eq!(frames.len(), 2 * PACKETS);
+    for (i, pair) in frames.chunks(2).enumerate() {
+      assert_eq!(pair[0], ((i * frame_samples) as u64, "tx frame start"));
+      assert_eq!(pair[1], (((i + 1) * frame_samples) as u64, "tx frame end"));
+    }
+    let tx_wav = hound::WavReader::open(dir.join("tx.wav")).unwrap();
+    assert_eq!(tx_wav.len() as usize, PACKETS * frame_samples);
+
+    let rx_log = std::fs::read_to_string(dir.join("rx.log")).unwrap();
+    let detected: u64 = rx_log
+      .lines()
+      .nth(1)
+      .unwrap()
+      .split(',')
+      .next()
+      .unwrap()
+      .parse()
+      .unwrap();
+    let rx_wav = hound::WavReader::open(dir.join("rx.wav")).unwrap();
+    assert!(detected >= frame_samples as u64 && detected <= rx_wav.len() as u64);
+  }
+
+  /// Shift the frequencies of the frames written by `offset` cycles per sample, through their analytic signal,
+  /// as a sender moving towards the receiver: the PHY layers write a frame at once.
+  #[cfg(not(feature = "nofloat"))]
+  struct FrequencyShift<S>(S, f32);
+
+  #[cfg(not(feature = "nofloat"))]
+  impl<S: OutStream<FP, ()>> OutStream<FP, ()> for FrequencyShift<S> {
+    fn write(&mut self, buf: &[FP]) -> Result<usize, ()> {
+      self.write_exact(buf).map(|_| buf.len())
+    }
+
+    fn write_exact(&mut self, buf: &[FP]) -> Result<(), ()> {
+      use rustfft::{num_complex::Complex32, FftPlanner};
+      // silence around the frame, so that the spectrum of the frame does not wrap around
+      const PAD: usize = 256;
+      let n = buf.len() + 2 * PAD;
+      let mut spectrum = vec![Complex32::default(); n];
+      spectrum[PAD..PAD + buf.len()]
+        .iter_mut()
+        .zip(buf)
+        .for_each(|(x, y)| x.re = y.into_f32());
+      let mut planner = FftPlanner::new();
+      planner.plan_fft_forward(n).process(&mut spectrum);
+      spectrum.iter_mut().skip(1).take((n - 1) / 2).for_each(|x| *x *= 2.0);
+      spectrum
+        .iter_mut()
+        .skip(n / 2 + 1)
+        .for_each(|x| *x = Complex32::default());
+      planner.plan_fft_inverse(n).process(&mut spectrum);
+      let shifted: Vec<_> = spectrum
+        .iter()
+        .enumerate()
+        .map(|(t, x)| {
+          let turns = (self.1 * t as f32).rem_euclid(1.0);
+          FP::from_f32((x * Complex32::from_polar(1.0, std::f32::consts::TAU * turns)).re / n as f32)
+        })
+        .collect();
+      self.0.write_exact(&shifted)
+    }
+
+    fn wait(&mut self) {
+      self.0.wait()
+    }
+  }
+
+  /// OFDM frames sent 150Hz above their frequencies are received with a preamble of two identical halves,
+  /// the frequency offset estimated by the frame detector is corrected by the modem
+  #[test]
+  #[cfg(not(feature = "nofloat"))]
+  fn ofdm_frequency_offset() {
+    use proj1_acoustic_link::{
+      phy_layer::{HighBpsPHY, PhyParams},
+      phy_packet::preambles::PreambleParams,
+    };
+    const OFFSET: f32 = 150.0 / 48000.0;
+    let air = VirtualAir::new(2);
+    let config = AudioConfig::default();
+    let params = PhyParams {
+      preamble: PreambleParams::RepeatedHalves(Default::default()),
+      ..Default::default()
+    };
+    let (stream_in, stream_out, power_probe) = air.node(0);
+    let mut sender = HighBpsPHY::with_streams_params(
+      stream_in,
+      FrequencyShift(stream_out, OFFSET),
+      power_probe,
+      &config,
+      &params,
+    )
+    .unwrap();
+    let (stream_in, stream_out, power_probe) = air.node(1);
+    let mut receiver = HighBpsPHY::with_streams_params(stream_in, stream_out, power_probe, &config, &params).unwrap();
+
+    for _ in 0..3 {
+      let packet: Vec<u8> = rand::thread_rng()
+        .sample_iter(Standard)
+        .take(receiver.packet_bytes())
+        .collect();
+      sender.send(packet.clone()).unwrap();
+      assert_eq!(receiver.recv_timeout(RECV_TIMEOUT).unwrap(), packet);
+    }
+  }
+}
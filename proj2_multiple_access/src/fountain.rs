@@ -0,0 +1,445 @@
+use crc::{Crc, CRC_64_XZ};
+
+/// the hash of a file carried with its metadata
+const HASH: Crc<u64> = Crc::<u64>::new(&CRC_64_XZ);
+/// one packet out of [`META_INTERVAL`] carries the metadata of the file instead of a symbol
+const META_INTERVAL: u32 = 8;
+
+/// kind of a packet, its first byte
+const META: u8 = 0;
+const SYMBOL: u8 = 1;
+/// kind, tag and symbol id
+const SYMBOL_HEADER_BYTES: usize = 1 + 2 + 4;
+/// kind, tag, size, hash and symbol size
+const META_HEADER_BYTES: usize = 1 + 2 + 8 + 8 + 2;
+/// number of symbols received between two attempts to solve the pending symbols when the peeling is stuck
+const ELIMINATION_INTERVAL: usize = 16;
+
+/// parameters of the robust soliton distribution of the degrees
+const SOLITON_C: f64 = 0.03;
+const SOLITON_DELTA: f64 = 0.5;
+
+/// Metadata of a file broadcast, sent in-band every [`META_INTERVAL`] packets
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileMeta {
+  /// name of the file, without directory, cut to fit in a packet
+  pub name: String,
+  /// size of the file in bytes
+  pub size: usize,
+  /// CRC-64 of the content of the file
+  pub hash: u64,
+}
+
+impl FileMeta {
+  /// whether the content of a file matches the size and the hash of the metadata
+  pub fn check(&self, data: &[u8]) -> bool {
+    data.len() == self.size && HASH.checksum(data) == self.hash
+  }
+
+  /// tag of the packets of the file, to tell the transfers apart
+  fn tag(&self) -> [u8; 2] {
+    [self.hash as u8, (self.hash >> 8) as u8]
+  }
+}
+
+/// Rateless encoder of a file into packets of a broadcast channel without feedback, an LT code:
+/// each packet carries a symbol, the XOR of a random set of source symbols drawn from its id,
+/// and one packet out of [`META_INTERVAL`] carries the metadata of the file.
+///
+/// A receiver decodes the file from any set of symbols slightly larger than the number of source symbols,
+/// so the sender loops the packets of [`Iterator::next`] with no end,
+/// and the receivers may join late or lose packets, see [`FountainDecoder`].
+pub struct FountainEncoder {
+  meta: FileMeta,
+  source: Vec<Vec<u8>>,
+  soliton: Soliton,
+  symbol_bytes: usize,
+  // the id of the next packet
+  id: u32,
+}
+
+impl FountainEncoder {
+  /// encode the content of a file into packets of at most `packet_bytes` bytes
+  pub fn new(name: &str, data: &[u8], packet_bytes: usize) -> Self {
+    assert!(packet_bytes > META_HEADER_BYTES);
+    let symbol_bytes = packet_bytes - SYMBOL_HEADER_BYTES;
+    let mut name = name.to_string();
+    while name.len() > packet_bytes - META_HEADER_BYTES {
+      name.pop();
+    }
+    let source: Vec<_> = data
+      .chunks(symbol_bytes)
+      .map(|chunk| {
+        let mut symbol = chunk.to_vec();
+        symbol.resize(symbol_bytes, 0);
+        symbol
+      })
+      .collect();
+    Self {
+      meta: FileMeta {
+        name,
+        size: data.len(),
+        hash: HASH.checksum(data),
+      },
+      soliton: Soliton::new(source.len()),
+      source,
+      symbol_bytes,
+      id: 0,
+    }
+  }
+
+  pub fn meta(&self) -> &FileMeta {
+    &self.meta
+  }
+
+  /// number of source symbols, a receiver needs a few more encoded symbols to decode the file
+  pub fn symbols(&self) -> usize {
+    self.source.len()
+  }
+
+  fn meta_packet(&self) -> Vec<u8> {
+    let mut packet = vec![META];
+    packet.extend(self.meta.tag());
+    packet.extend((self.meta.size as u64).to_le_bytes());
+    packet.extend(self.meta.hash.to_le_bytes());
+    packet.extend((self.symbol_bytes as u16).to_le_bytes());
+    packet.extend(self.meta.name.as_bytes());
+    packet
+  }
+
+  fn symbol_packet(&self, id: u32) -> Vec<u8> {
+    let mut symbol = vec![0; self.symbol_bytes];
+    for i in self.soliton.neighbours(id) {
+      xor(&mut symbol, &self.source[i]);
+    }
+    let mut packet = vec![SYMBOL];
+    packet.extend(self.meta.tag());
+    packet.extend(id.to_le_bytes());
+    packet.extend(symbol);
+    packet
+  }
+}
+
+impl Iterator for FountainEncoder {
+  type Item = Vec<u8>;
+
+  /// the next packet to send, with no end
+  fn next(&mut self) -> Option<Vec<u8>> {
+    let id = self.id;
+    self.id = self.id.wrapping_add(1);
+    if id.is_multiple_of(META_INTERVAL) || self.source.is_empty() {
+      Some(self.meta_packet())
+    } else {
+      Some(self.symbol_packet(id))
+    }
+  }
+}
+
+/// Decoder of the packets of [`FountainEncoder`]: collects the symbols received, whichever they are,
+/// and recovers the source symbols by peeling: a symbol whose source symbols are all known but one reveals it.
+/// When the peeling is stuck, the symbols are solved as a linear system,
+/// so that a few more symbols than the source symbols are enough.
+///
+/// The decoder follows the file of the first well-formed packet received and ignores the packets of other files.
+/// The symbols received before the metadata are kept until it is received.
+#[derive(Default)]
+pub struct FountainDecoder {
+  tag: Option<[u8; 2]>,
+  meta: Option<FileMeta>,
+  // the symbols received before the metadata
+  early: Vec<(u32, Vec<u8>)>,
+  peeling: Option<Peeling>,
+  symbols: usize,
+}
+
+impl FountainDecoder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// the metadata of the file, once received
+  pub fn meta(&self) -> Option<&FileMeta> {
+    self.meta.as_ref()
+  }
+
+  /// number of symbols of the file received
+  pub fn symbols(&self) -> usize {
+    self.symbols
+  }
+
+  /// number of source symbols recovered, and the number of source symbols once the metadata is received
+  pub fn progress(&self) -> (usize, Option<usize>) {
+    match &self.peeling {
+      Some(peeling) => (peeling.recovered, Some(peeling.source.len())),
+      None => (0, None),
+    }
+  }
+
+  /// Push a packet received, the packets malformed or of other files are ignored.
+  /// Return whether all the source symbols are recovered.
+  pub fn push(&mut self, packet: &[u8]) -> bool {
+    // only a well-formed packet decides the file followed, not a stray or corrupted one
+    if !well_formed(packet) || *self.tag.get_or_insert([packet[1], packet[2]]) != packet[1..3] {
+      return self.is_complete();
+    }
+    match packet[0] {
+      META if self.meta.is_none() => self.on_meta(packet),
+      SYMBOL => {
+        let id = u32::from_le_bytes(packet[3..7].try_into().unwrap());
+        let symbol = packet[SYMBOL_HEADER_BYTES..].to_vec();
+        self.symbols += 1;
+        match &mut self.peeling {
+          Some(peeling) => peeling.push(id, symbol),
+          None => self.early.push((id, symbol)),
+        }
+      }
+      _ => {}
+    }
+    self.is_complete()
+  }
+
+  fn on_meta(&mut self, packet: &[u8]) {
+    // a file too large for the platform can not be decoded
+    let Ok(size) = usize::try_from(u64::from_le_bytes(packet[3..11].try_into().unwrap())) else {
+      return;
+    };
+    let hash = u64::from_le_bytes(packet[11..19].try_into().unwrap());
+    let symbol_bytes = u16::from_le_bytes(packet[19..21].try_into().unwrap()) as usize;
+    let name = String::from_utf8_lossy(&packet[META_HEADER_BYTES..]).into_owned();
+    self.meta = Some(FileMeta { name, size, hash });
+
+    let mut peeling = Peeling::new(size.div_ceil(symbol_bytes), symbol_bytes);
+    for (id, symbol) in self.early.drain(..) {
+      peeling.push(id, symbol);
+    }
+    self.peeling = Some(peeling);
+  }
+
+  /// whether all the source symbols are recovered
+  pub fn is_complete(&self) -> bool {
+    self.peeling.as_ref().is_some_and(Peeling::is_complete)
+  }
+
+  /// the content of the file once all the source symbols are recovered, see [`FileMeta::check`]
+  pub fn file(&self) -> Option<Vec<u8>> {
+    let (meta, peeling) = (self.meta.as_ref()?, self.peeling.as_ref()?);
+    if !peeling.is_complete() {
+      return None;
+    }
+    let mut data: Vec<_> = peeling.source.iter().flatten().flatten().copied().collect();
+    data.truncate(meta.size);
+    Some(data)
+  }
+}
+
+/// Whether a packet looks like a packet of [`FountainEncoder`]:
+/// a symbol with some bytes, or a metadata whose tag matches its hash and whose symbols have some bytes.
+fn well_formed(packet: &[u8]) -> bool {
+  match packet.first() {
+    Some(&SYMBOL) => packet.len() > SYMBOL_HEADER_BYTES,
+    Some(&META) if packet.len() >= META_HEADER_BYTES => {
+      let hash = u64::from_le_bytes(packet[11..19].try_into().unwrap());
+      let symbol_bytes = u16::from_le_bytes(packet[19..21].try_into().unwrap());
+      packet[1..3] == [hash as u8, (hash >> 8) as u8] && symbol_bytes > 0
+    }
+    _ => false,
+  }
+}
+
+/// The peeling decoder of an LT code
+struct Peeling {
+  soliton: Soliton,
+  symbol_bytes: usize,
+  source: Vec<Option<Vec<u8>>>,
+  recovered: usize,
+  // the symbols with more than one source symbol unknown: those source symbols, and the symbol XOR the known ones
+  pending: Vec<(Vec<usize>, Vec<u8>)>,
+  // number of symbols to receive before the next attempt to solve the pending symbols
+  elimination_countdown: usize,
+}
+
+impl Peeling {
+  fn new(symbols: usize, symbol_bytes: usize) -> Self {
+    Self {
+      soliton: Soliton::new(symbols),
+      symbol_bytes,
+      source: vec![None; symbols],
+      recovered: 0,
+      pending: vec![],
+      elimination_countdown: 0,
+    }
+  }
+
+  fn is_complete(&self) -> bool {
+    self.recovered == self.source.len()
+  }
+
+  fn push(&mut self, id: u32, mut symbol: Vec<u8>) {
+    if symbol.len() != self.symbol_bytes || self.is_complete() {
+      return;
+    }
+    let mut neighbours = self.soliton.neighbours(id);
+    neighbours.retain(|i| match &self.source[*i] {
+      Some(source) => {
+        xor(&mut symbol, source);
+        false
+      }
+      None => true,
+    });
+    match neighbours.len() {
+      0 => {}
+      1 => self.recover(neighbours[0], symbol),
+      _ => self.pending.push((neighbours, symbol)),
+    }
+    // the elimination costs much more than peeling, after a failure it is retried with more symbols only
+    self.elimination_countdown = self.elimination_countdown.saturating_sub(1);
+    if !self.is_complete()
+      && self.pending.len() >= self.source.len() - self.recovered
+      && self.elimination_countdown == 0
+      && !self.eliminate()
+    {
+      self.elimination_countdown = ELIMINATION_INTERVAL;
+    }
+  }
+
+  /// Once the peeling is stuck with enough pending symbols, solve them by Gauss-Jordan elimination:
+  /// the source symbols left are recovered if the pending symbols determine them all.
+  /// Return whether they are recovered.
+  fn eliminate(&mut self) -> bool {
+    let unknown: Vec<_> = (0..self.source.len()).filter(|i| self.source[*i].is_none()).collect();
+    let mut column = vec![0; self.source.len()];
+    unknown.iter().enumerate().for_each(|(c, i)| column[*i] = c);
+    // each pending symbol as a row: the bits of its unknown source symbols, and the symbol
+    let mut rows: Vec<_> = self
+      .pending
+      .iter()
+      .map(|(neighbours, symbol)| {
+        let mut bits = vec![0u64; unknown.len().div_ceil(64)];
+        neighbours
+          .iter()
+          .for_each(|i| bits[column[*i] / 64] |= 1 << (column[*i] % 64));
+        (bits, symbol.clone())
+      })
+      .collect();
+
+    for c in 0..unknown.len() {
+      let has = |bits: &[u64]| (bits[c / 64] >> (c % 64)) & 1 == 1;
+      let Some(pivot) = (c..rows.len()).find(|r| has(&rows[*r].0)) else {
+        return false;
+      };
+      rows.swap(c, pivot);
+      let (bits, symbol) = rows[c].clone();
+      for (r, (other, other_symbol)) in rows.iter_mut().enumerate() {
+        if r != c && has(other) {
+          other.iter_mut().zip(&bits).for_each(|(x, y)| *x ^= y);
+          xor(other_symbol, &symbol);
+        }
+      }
+    }
+    for (i, (_, symbol)) in unknown.into_iter().zip(rows) {
+      self.source[i] = Some(symbol);
+    }
+    self.recovered = self.source.len();
+    self.pending.clear();
+    true
+  }
+
+  /// recover a source symbol, and the ones it reveals in turn
+  fn recover(&mut self, i: usize, symbol: Vec<u8>) {
+    let mut revealed = vec![(i, symbol)];
+    while let Some((i, symbol)) = revealed.pop() {
+      if self.source[i].is_some() {
+        continue;
+      }
+      let mut pending = vec![];
+      for (mut neighbours, mut other) in self.pending.drain(..) {
+        if let Some(k) = neighbours.iter().position(|j| *j == i) {
+          neighbours.swap_remove(k);
+          xor(&mut other, &symbol);
+        }
+        if neighbours.len() == 1 {
+          revealed.push((neighbours[0], other));
+        } else {
+          pending.push((neighbours, other));
+        }
+      }
+      self.pending = pending;
+      self.source[i] = Some(symbol);
+      self.recovered += 1;
+    }
+  }
+}
+
+fn xor(symbol: &mut [u8], other: &[u8]) {
+  symbol.iter_mut().zip(other).for_each(|(x, y)| *x ^= y);
+}
+
+/// The robust soliton distribution of the degrees of the symbols, the number of source symbols XORed:
+/// mostly low degrees so that the peeling goes on, a few high ones so that every source symbol is covered.
+struct Soliton {
+  symbols: usize,
+  // the cumulative distribution of the degrees from 1
+  cdf: Vec<f64>,
+}
+
+impl Soliton {
+  fn new(symbols: usize) -> Self {
+    let k = symbols as f64;
+    let r = SOLITON_C * (k / SOLITON_DELTA).ln() * k.sqrt();
+    let spike = (k / r).floor() as usize;
+    let weights: Vec<f64> = (1..=symbols)
+      .map(|d| {
+        let ideal = if d == 1 { 1.0 / k } else { 1.0 / (d * (d - 1)) as f64 };
+        let robust = match d {
+          d if d < spike => r / (d as f64 * k),
+          d if d == spike => r * (r / SOLITON_DELTA).ln() / k,
+          _ => 0.0,
+        };
+        ideal + robust
+      })
+      .collect();
+    let total: f64 = weights.iter().sum();
+    let cdf = weights
+      .iter()
+      .scan(0.0, |sum, weight| {
+        *sum += weight / total;
+        Some(*sum)
+      })
+      .collect();
+    Self { symbols, cdf }
+  }
+
+  /// the source symbols of the symbol `id`, drawn from a generator seeded by the id on both sides
+  fn neighbours(&self, id: u32) -> Vec<usize> {
+    if self.symbols == 0 {
+      return vec![];
+    }
+    let mut rng = SplitMix64(id as u64);
+    let u = (rng.next() >> 11) as f64 / (1u64 << 53) as f64;
+    let degree = self.cdf.iter().position(|p| u < *p).unwrap_or(self.symbols - 1) + 1;
+    let mut neighbours = Vec::with_capacity(degree);
+    while neighbours.len() < degree {
+      let i = (rng.next() % self.symbols as u64) as usize;
+      if !neighbours.contains(&i) {
+        neighbours.push(i);
+      }
+    }
+    neighbours
+  }
+}
+
+/// A small generator with the same output on every platform, see <https://prng.di.unimi.it/splitmix64.c>
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+  fn next(&mut self) -> u64 {
+    self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = self.0;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+  }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,95 @@
+use super::{FountainDecoder, FountainEncoder};
+use rand::{distributions::Standard, Rng};
+
+const PACKET_BYTES: usize = 98;
+
+fn random_file(len: usize) -> Vec<u8> {
+  rand::thread_rng().sample_iter(Standard).take(len).collect()
+}
+
+/// receive the packets of an encoder until the file is decoded, return the number of packets received
+fn receive(encoder: &mut FountainEncoder, decoder: &mut FountainDecoder, loss: f64) -> usize {
+  let mut received = 0;
+  for packet in encoder.by_ref().take(100_000) {
+    if rand::thread_rng().gen_bool(loss) {
+      continue;
+    }
+    received += 1;
+    if decoder.push(&packet) {
+      return received;
+    }
+  }
+  panic!("file not decoded");
+}
+
+/// a receiver losing packets decodes the file from a few more symbols than the source symbols
+#[test]
+fn fountain_lossy() {
+  for len in [0, 1, 1000, 10000] {
+    let data = random_file(len);
+    let mut encoder = FountainEncoder::new("lossy.bin", &data, PACKET_BYTES);
+    let mut decoder = FountainDecoder::new();
+    let received = receive(&mut encoder, &mut decoder, 0.3);
+
+    assert!(
+      received <= encoder.symbols() * 2 + 100,
+      "{} packets for {} symbols",
+      received,
+      encoder.symbols()
+    );
+    assert_eq!(decoder.meta(), Some(encoder.meta()));
+    let file = decoder.file().unwrap();
+    assert!(decoder.meta().unwrap().check(&file));
+    assert_eq!(file, data);
+  }
+}
+
+/// a receiver joining late keeps the symbols received before the metadata
+/// and ignores the packets of another file
+#[test]
+fn fountain_late_join() {
+  let data = random_file(5000);
+  let mut encoder = FountainEncoder::new("late.bin", &data, PACKET_BYTES);
+  let mut other = FountainEncoder::new("other.bin", &random_file(5000), PACKET_BYTES);
+  encoder.by_ref().take(3).for_each(drop);
+
+  let mut decoder = FountainDecoder::new();
+  decoder.push(&encoder.next().unwrap());
+  assert!(decoder.meta().is_none());
+  assert_eq!(decoder.symbols(), 1);
+  other.by_ref().take(20).for_each(|packet| {
+    decoder.push(&packet);
+  });
+  assert_eq!(decoder.symbols(), 1);
+
+  receive(&mut encoder, &mut decoder, 0.0);
+  assert_eq!(decoder.meta().unwrap().name, "late.bin");
+  assert_eq!(decoder.file().unwrap(), data);
+}
+
+/// the name of the file is cut to fit in a packet
+#[test]
+fn fountain_long_name() {
+  let name = "é".repeat(PACKET_BYTES);
+  let encoder = FountainEncoder::new(&name, &random_file(10), PACKET_BYTES);
+  assert!(encoder.meta().name.len() <= PACKET_BYTES);
+  assert!(name.starts_with(&encoder.meta().name));
+}
+
+/// the malformed packets received before the file do not decide which file is followed
+#[test]
+fn fountain_malformed_first() {
+  let data = random_file(2000);
+  let mut encoder = FountainEncoder::new("first.bin", &data, PACKET_BYTES);
+  let mut decoder = FountainDecoder::new();
+  // a symbol without payload, a metadata whose tag does not match its hash, and an unknown kind
+  decoder.push(&[1, 0xab, 0xcd, 0, 0, 0, 0]);
+  let mut meta = encoder.next().unwrap();
+  meta[1] ^= 0xff;
+  decoder.push(&meta);
+  decoder.push(&[7; PACKET_BYTES]);
+  assert!(decoder.meta().is_none());
+
+  receive(&mut encoder, &mut decoder, 0.0);
+  assert_eq!(decoder.file().unwrap(), data);
+}
@@ -1,4 +1,3 @@
-use proj1_acoustic_link::phy_layer::DefaultPhy;
 /// Define the MAC layer packet:
 ///
 /// - MAC address
@@ -15,5 +14,9 @@ mod csma;
 /// Simple MAC protocol for peer-to-peer full duplex connection.
 mod p2p_full_duplex;
 
+/// Rateless broadcast of a file with no feedback channel: LT encoder/decoder.
+mod fountain;
+pub use fountain::{FileMeta, FountainDecoder, FountainEncoder};
+
 /// export the default MAC layer implementation.
-pub type MacLayer = mac::MacLayer<DefaultPhy, p2p_full_duplex::Simple>;
+pub type MacLayer = mac::MacLayer<p2p_full_duplex::Simple>;
@@ -5,11 +5,14 @@ use std::{
 };
 
 use crossbeam_channel::{unbounded as channel, Receiver, Sender};
-use proj1_acoustic_link::phy_layer::PhyLayer;
+use proj1_acoustic_link::{
+  phy_layer::{BoxedPhy, DefaultPhy},
+  phy_packet::params::ParamError,
+};
 
 use crate::{MacAddr, MacPacket, MacSeq};
 
-pub trait MacStateMachine<PHY: PhyLayer> {
+pub trait MacStateMachine {
   /// Prepare a MAC state machine:
   /// - `phy`: the PHY layer object upon which the MAC layer is built.
   /// - `self_addr`: the MAC address of this node.
@@ -17,10 +20,10 @@ pub trait MacStateMachine<PHY: PhyLayer> {
   /// - `packets_to_send`: packets that we received from MAC layer.
   /// - `terminate_signal`: receive a `()` when MAC layer about to drop.
   fn new(
-    phy: PHY,
+    phy: BoxedPhy,
     self_addr: MacAddr,
-    packets_to_send: Receiver<MacPacket<PHY>>,
-    packets_received: Sender<MacPacket<PHY>>,
+    packets_to_send: Receiver<MacPacket>,
+    packets_received: Sender<MacPacket>,
     terminate_signal: Receiver<()>,
   ) -> Self;
 
@@ -32,33 +35,39 @@ pub trait MacStateMachine<PHY: PhyLayer> {
 #[derive(Clone, Copy, Debug)]
 pub struct PingTimeout;
 
-/// MAC layer object which is built on a PHY layer object.
+/// MAC layer object which is built on a PHY layer object picked at runtime.
 /// - `tx_seq`: the number of total packets sent.
 /// - `rx_seq`: the nubmer of total packets received.
-pub struct MacLayer<PHY, MAC>
+/// - `mtu`: the maximum payload of a MAC packet on the PHY layer.
+pub struct MacLayer<MAC>
 where
-  PHY: PhyLayer + Send + 'static,
-  MAC: MacStateMachine<PHY>,
+  MAC: MacStateMachine,
 {
-  _phantom: PhantomData<(PHY, MAC)>,
+  _phantom: PhantomData<MAC>,
   addr: MacAddr,
+  mtu: usize,
   tx_seq: MacSeq,
   rx_seq: MacSeq,
   worker_handler: Option<JoinHandle<()>>,
-  pack_send: Sender<MacPacket<PHY>>,
-  pack_recv: Receiver<MacPacket<PHY>>,
+  pack_send: Sender<MacPacket>,
+  pack_recv: Receiver<MacPacket>,
   terminate_signal: Sender<()>,
 }
-impl<PHY, MAC> MacLayer<PHY, MAC>
+impl<MAC> MacLayer<MAC>
 where
-  PHY: PhyLayer + Send + 'static,
-  MAC: MacStateMachine<PHY>,
+  MAC: MacStateMachine,
 {
-  /// maximum transmission unit in bytes
-  pub const MTU: usize = MacPacket::<PHY>::PAYLOAD_SIZE;
-
-  /// crate a new MAC layer on a given PHY layer.
-  pub fn new(addr: MacAddr, phy: PHY) -> Self {
+  /// Crate a new MAC layer on a given PHY layer,
+  /// fail if the packets of the PHY layer can not hold the MAC header and some data.
+  pub fn new(addr: MacAddr, phy: BoxedPhy) -> Result<Self, ParamError> {
+    let mtu = phy
+      .packet_bytes()
+      .checked_sub(MacPacket::HEADER_SIZE)
+      .filter(|&mtu| mtu > 0)
+      .ok_or(ParamError::Invalid(
+        "PHY packet bytes",
+        "a packet must hold the MAC header and some data",
+      ))?;
     let (pack_send, packets_to_send) = channel();
     let (packets_received, pack_recv) = channel();
     let (terminate_signal, exit_recv) = channel();
@@ -67,22 +76,34 @@ where
       MAC::new(phy, addr, packets_to_send, packets_received, exit_recv).run();
     }));
 
-    Self {
+    Ok(Self {
       _phantom: Default::default(),
       addr,
+      mtu,
       tx_seq: MacSeq(0),
       rx_seq: MacSeq(0),
       worker_handler,
       pack_send,
       pack_recv,
       terminate_signal,
-    }
+    })
+  }
+
+  /// create a MAC layer object based on the default PHY layer.
+  pub fn new_with_default_phy(addr: MacAddr) -> Self {
+    Self::new(addr, Box::new(DefaultPhy::default())).expect("the default PHY layer holds the MAC header")
+  }
+
+  /// maximum transmission unit in bytes
+  pub fn mtu(&self) -> usize {
+    self.mtu
   }
 
   /// Send a data packet to peer,
-  /// `bytes` must be no more than `MacPacket<PHY>::PAYLOAD_BYTES` bytes.
+  /// `bytes` must be no more than [`MacLayer::mtu`] bytes.
   /// Return immediately
   pub fn send_to(&mut self, dest: MacAddr, bytes: Vec<u8>) {
+    assert!(bytes.len() <= self.mtu);
     let packet = MacPacket::new_data(self.addr, dest, self.tx_seq, &bytes);
     self.tx_seq.step();
     self.pack_send.send(packet).unwrap();
@@ -134,21 +155,9 @@ where
   }
 }
 
-impl<PHY, MAC> MacLayer<PHY, MAC>
-where
-  PHY: PhyLayer + Default + Send + 'static,
-  MAC: MacStateMachine<PHY>,
-{
-  /// create a MAC layer object based on the default PHY layer.
-  pub fn new_with_default_phy(addr: MacAddr) -> Self {
-    Self::new(addr, PHY::default())
-  }
-}
-
-impl<PHY, MAC> Drop for MacLayer<PHY, MAC>
+impl<MAC> Drop for MacLayer<MAC>
 where
-  PHY: PhyLayer + Send + 'static,
-  MAC: MacStateMachine<PHY>,
+  MAC: MacStateMachine,
 {
   /// wait for the state machine to stop
   fn drop(&mut self) {
@@ -6,25 +6,25 @@ use std::{
 use crate::{mac::MacStateMachine, MacAddr, MacPacket};
 use crossbeam_channel::{Receiver, Sender};
 use proj1_acoustic_link::{
-  phy_layer::{DefaultPhy, PhyLayer},
+  phy_layer::BoxedPhy,
   traits::{PacketReceiver, PacketSender},
 };
 
 struct PendingPacket {
-  packet: MacPacket<DefaultPhy>,
+  packet: MacPacket,
   send_time: Instant,
   retry_count: usize,
 }
 
 impl PendingPacket {
-  fn with_packet(packet: MacPacket<DefaultPhy>) -> Self {
+  fn with_packet(packet: MacPacket) -> Self {
     Self {
       packet,
       send_time: Instant::now(),
       retry_count: 0,
     }
   }
-  fn resend(&mut self, phy: &mut DefaultPhy) {
+  fn resend(&mut self, phy: &mut BoxedPhy) {
     phy.send(self.packet.into_phy()).unwrap();
     self.send_time = Instant::now();
     self.retry_count += 1;
@@ -34,18 +34,18 @@ impl PendingPacket {
 /// Simple MAC implementaion for peer to peer full duplex connection:
 /// stop-and-wait or sliding window.
 pub struct Simple {
-  phy: DefaultPhy,
+  phy: BoxedPhy,
   addr: MacAddr,
-  packets_to_send: Receiver<MacPacket<DefaultPhy>>,
-  packets_received: Sender<MacPacket<DefaultPhy>>,
+  packets_to_send: Receiver<MacPacket>,
+  packets_received: Sender<MacPacket>,
   terminate_signal: Receiver<()>,
   pending_packets: VecDeque<PendingPacket>,
 }
 
 impl Simple {
   const WINDOW_SIZE: usize = 3;
-  fn resent_interval() -> Duration {
-    DefaultPhy::ESTIMATED_RTT * 3 / 2
+  fn resent_interval(&self) -> Duration {
+    self.phy.estimated_rtt() * 3 / 2
   }
 
   fn can_send_packet(&self) -> bool {
@@ -61,17 +61,21 @@ impl Simple {
     }
   }
   fn check_and_resend_packet(&mut self) {
+    let resent_interval = self.resent_interval();
     for pending_pacekt in &mut self.pending_packets {
-      if pending_pacekt.send_time.elapsed() > Self::resent_interval() {
+      if pending_pacekt.send_time.elapsed() > resent_interval {
         println!("resend package {:?}", pending_pacekt.packet.seq);
         pending_pacekt.resend(&mut self.phy);
       }
     }
   }
   fn receive_packet(&mut self) {
-    let mut pending_ack: VecDeque<MacPacket<DefaultPhy>> = VecDeque::new();
+    let mut pending_ack: VecDeque<MacPacket> = VecDeque::new();
     while let Ok(packet) = self.phy.recv() {
-      let packet: MacPacket<DefaultPhy> = MacPacket::from_phy(&packet);
+      let Some(packet) = MacPacket::from_phy(&packet) else {
+        println!("Drop short packet");
+        continue;
+      };
       if packet.dest != self.addr {
         println!("Drop packet");
         continue;
@@ -95,12 +99,12 @@ impl Simple {
     }
   }
 }
-impl MacStateMachine<DefaultPhy> for Simple {
+impl MacStateMachine for Simple {
   fn new(
-    phy: DefaultPhy,
+    phy: BoxedPhy,
     addr: MacAddr,
-    packets_to_send: Receiver<MacPacket<DefaultPhy>>,
-    packets_received: Sender<MacPacket<DefaultPhy>>,
+    packets_to_send: Receiver<MacPacket>,
+    packets_received: Sender<MacPacket>,
     terminate_signal: Receiver<()>,
   ) -> Self {
     Self {
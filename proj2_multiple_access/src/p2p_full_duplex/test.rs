@@ -14,10 +14,10 @@ fn packet_send() {
   println!("Sending packet from 1 to 2");
   // read input data from file
   let mut data = fs::read(INPUT_FILES).unwrap();
-  add_padding(&mut data, 0, MacLayer::MTU);
-  // transmission
   let mut mac_layer = MacLayer::new_with_default_phy(MacAddr(1));
-  for bytes in data.chunks_exact(MacLayer::MTU) {
+  add_padding(&mut data, 0, mac_layer.mtu());
+  // transmission
+  for bytes in data.chunks_exact(mac_layer.mtu()) {
     mac_layer.send_to(MacAddr(2), bytes.to_vec())
   }
   // wait for transmission to finish
@@ -38,6 +38,6 @@ fn packet_recv() {
   // stop timing
   println!("finished in {}ms", start.elapsed().as_millis());
   // write result
-  remove_padding(&mut data, FILE_SIZE, MacLayer::MTU);
+  remove_padding(&mut data, FILE_SIZE, mac_layer.mtu());
   fs::write(OUTPUT_FILES, &data).unwrap();
 }
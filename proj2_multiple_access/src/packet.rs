@@ -1,7 +1,3 @@
-use std::marker::PhantomData;
-
-use proj1_acoustic_link::phy_layer::PhyLayer;
-
 /// MAC address to identify a peer
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct MacAddr(pub u8);
@@ -58,8 +54,7 @@ impl MacFlags {
 /// - `flags: packet metadata flags (have an ack field, contain any data, is ping packet)
 /// - `seq`: MAC packet sequence number.
 /// - `data`: the payload data section
-pub struct MacPacket<PHY: PhyLayer> {
-  _phantom: PhantomData<PHY>,
+pub struct MacPacket {
   pub(crate) src: MacAddr,
   pub(crate) dest: MacAddr,
   pub(crate) seq: MacSeq,
@@ -67,19 +62,18 @@ pub struct MacPacket<PHY: PhyLayer> {
   pub(crate) data: Vec<u8>,
 }
 
-impl<PHY: PhyLayer> MacPacket<PHY> {
-  /// The number of payload bytes in one MAC packet.
-  pub const PAYLOAD_SIZE: usize = PHY::PACKET_BYTES - 4;
+impl MacPacket {
+  /// The number of bytes of the MAC header in one PHY packet,
+  /// the rest of the PHY packet is the payload.
+  pub const HEADER_SIZE: usize = 4;
 
   /// Create a MAC packet from the specified arguments.
-  /// `data` should contain no more than `MacPacket::PAYLOAD_SIZE` bytes.
+  /// `data` should fit in the PHY packet after the header,
+  /// the PHY packet is only as long as the data needs.
   fn new(src: MacAddr, dest: MacAddr, seq: MacSeq, flags: MacFlags, data: &[u8]) -> Self {
-    assert!(data.len() <= Self::PAYLOAD_SIZE);
-    let mut data = Vec::from(data);
-    data.resize(Self::PAYLOAD_SIZE, 0);
+    let data = Vec::from(data);
 
     Self {
-      _phantom: Default::default(),
       src,
       dest,
       seq,
@@ -112,7 +106,6 @@ impl<PHY: PhyLayer> MacPacket<PHY> {
         ping_reply: self.flags.ping_request,
       };
       Some(MacPacket {
-        _phantom: Default::default(),
         flags,
         src: self.dest,
         dest: self.src,
@@ -124,14 +117,17 @@ impl<PHY: PhyLayer> MacPacket<PHY> {
     }
   }
 
-  /// Parse a MAC packet from a PHY packet.
-  pub fn from_phy(phy_packet: &[u8]) -> Self {
-    assert_eq!(phy_packet.len(), PHY::PACKET_BYTES);
+  /// Parse a MAC packet from a PHY packet,
+  /// return `Option::None` if the PHY packet is too short to hold the MAC header.
+  pub fn from_phy(phy_packet: &[u8]) -> Option<Self> {
+    if phy_packet.len() < Self::HEADER_SIZE {
+      return None;
+    }
     let src = MacAddr(phy_packet[0]);
     let dest = MacAddr(phy_packet[1]);
     let seq = MacSeq(phy_packet[2]);
     let flags = phy_packet[3];
-    let data = &phy_packet[4..];
+    let data = &phy_packet[Self::HEADER_SIZE..];
 
     let flags = MacFlags {
       ack: (flags & 0b0001) != 0,
@@ -139,12 +135,12 @@ impl<PHY: PhyLayer> MacPacket<PHY> {
       ping_request: (flags & 0b0100) != 0,
       ping_reply: (flags & 0b1000) != 0,
     };
-    Self::new(src, dest, seq, flags, data)
+    Some(Self::new(src, dest, seq, flags, data))
   }
 
   /// Dump a MAC packet into a PHY packet.
   pub fn into_phy(&self) -> Vec<u8> {
-    let mut pack = vec![0; PHY::PACKET_BYTES];
+    let mut pack = vec![0; Self::HEADER_SIZE + self.data.len()];
 
     pack[0] = self.src.0;
     pack[1] = self.dest.0;
@@ -156,7 +152,7 @@ impl<PHY: PhyLayer> MacPacket<PHY> {
     let ping_rpl = self.flags.ping_reply as u8;
     pack[3] = ack | (data << 1) | (ping_req << 2) | (ping_rpl << 3);
 
-    pack[4..].copy_from_slice(&self.data);
+    pack[Self::HEADER_SIZE..].copy_from_slice(&self.data);
 
     pack
   }
@@ -8,11 +8,12 @@ use pnet::packet::{
   icmp::{Icmp, IcmpCode, IcmpTypes},
   ipv4::Ipv4,
 };
+use proj1_acoustic_link::phy_layer::{BoxedPhy, DefaultPhy};
 use proj2_multiple_access::MacAddr;
 use socket2::{Domain, Socket, Type};
 use std::{
   collections::HashMap,
-  io::Result,
+  io::{Error, Result},
   net::{Ipv4Addr, SocketAddrV4},
 };
 
@@ -186,6 +187,12 @@ impl IpLayerInternal {
   /// - `self_addr`: the MAC address and IP address of current node
   /// - `peer_addr`: the MAC address and IP address of peer node
   pub fn new(self_addr: (MacAddr, Ipv4Addr), peer_addr: (MacAddr, Ipv4Addr)) -> Result<Self> {
+    Self::with_phy(self_addr, peer_addr, Box::new(DefaultPhy::default()))
+  }
+
+  /// Same as [`Self::new`], but the MAC layer is built on a given PHY layer object,
+  /// e.g. a PHY layer on the selected audio devices.
+  pub fn with_phy(self_addr: (MacAddr, Ipv4Addr), peer_addr: (MacAddr, Ipv4Addr), phy: BoxedPhy) -> Result<Self> {
     log::debug!(
       "starting IP layer for internal@{:?}, gateway@{:?}",
       self_addr,
@@ -202,7 +209,7 @@ impl IpLayerInternal {
     Ok(Self {
       self_ip: self_addr.1,
       _peer_ip: peer_addr.1,
-      ip_txrx: IpOverMac::new(self_addr.0, peer_addr.0),
+      ip_txrx: IpOverMac::new(self_addr.0, peer_addr.0, phy).map_err(Error::other)?,
       ipc,
       socks_in_use: Default::default(),
       icmp_binds: Default::default(),
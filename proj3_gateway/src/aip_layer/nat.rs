@@ -8,12 +8,13 @@ use pnet::packet::{
   ipv4::{Ipv4, Ipv4Packet, MutableIpv4Packet},
   FromPacket, Packet,
 };
+use proj1_acoustic_link::phy_layer::{BoxedPhy, DefaultPhy};
 use proj2_multiple_access::MacAddr;
 use rand::Rng;
 use socket2::{Domain, Socket, Type};
 use std::{
   collections::HashMap,
-  io::{ErrorKind, Result},
+  io::{Error, ErrorKind, Result},
   mem::{transmute, MaybeUninit},
   net::{Ipv4Addr, SocketAddrV4},
 };
@@ -395,6 +396,17 @@ impl IpLayerGateway {
   /// - `peer_addr`: the MAC address and IP address of peer node
   /// - `inet_addr`: address in the Internet
   pub fn new(self_addr: (MacAddr, Ipv4Addr), peer_addr: (MacAddr, Ipv4Addr), inet_addr: Ipv4Addr) -> Result<Self> {
+    Self::with_phy(self_addr, peer_addr, inet_addr, Box::new(DefaultPhy::default()))
+  }
+
+  /// Same as [`Self::new`], but the MAC layer is built on a given PHY layer object,
+  /// e.g. a PHY layer on the selected audio devices.
+  pub fn with_phy(
+    self_addr: (MacAddr, Ipv4Addr),
+    peer_addr: (MacAddr, Ipv4Addr),
+    inet_addr: Ipv4Addr,
+    phy: BoxedPhy,
+  ) -> Result<Self> {
     log::debug!(
       "starting IP layer for gateway@{:?}, internal@{:?}",
       self_addr,
@@ -404,7 +416,7 @@ impl IpLayerGateway {
     Ok(Self {
       anet_self_ip: self_addr.1,
       anet_peer_ip: peer_addr.1,
-      ip_txrx: IpOverMac::new(self_addr.0, peer_addr.0),
+      ip_txrx: IpOverMac::new(self_addr.0, peer_addr.0, phy).map_err(Error::other)?,
       rawsock: WrapRawSock::new(inet_addr)?,
       inet_self_ip: inet_addr,
       nat: NatTable::new(),
@@ -0,0 +1,65 @@
+use clap::Parser;
+use proj1_acoustic_link::traits::PacketReceiver;
+use proj2_multiple_access::FountainDecoder;
+use proj3_gateway::AudioDeviceCli;
+use std::{
+  io::{Error, ErrorKind, Result},
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+/// Receive a file broadcast by `broadcast_send`, from whichever packets are heard:
+/// exit as soon as the file is decoded and saved
+#[derive(Parser)]
+struct BroadcastRecvCli {
+  /// the directory to save the file into, under the name of the broadcast
+  #[arg(long, default_value = ".")]
+  output_dir: PathBuf,
+
+  #[command(flatten)]
+  audio: AudioDeviceCli,
+}
+
+fn main() -> Result<()> {
+  env_logger::init();
+
+  let BroadcastRecvCli { output_dir, audio } = BroadcastRecvCli::parse();
+  if audio.list_devices {
+    return audio.print_devices();
+  }
+  let mut phy = audio.build_phy()?;
+  let mut decoder = FountainDecoder::new();
+  let mut announced = false;
+  while !decoder.is_complete() {
+    let Ok(packet) = phy.recv_timeout(Duration::from_secs(1)) else {
+      continue;
+    };
+    decoder.push(&packet);
+    if let (Some(meta), false) = (decoder.meta(), announced) {
+      println!("receiving {} ({} bytes)", meta.name, meta.size);
+      announced = true;
+    }
+    if let (recovered, Some(symbols)) = decoder.progress() {
+      println!(
+        "{} symbols received, {}/{} source symbols",
+        decoder.symbols(),
+        recovered,
+        symbols
+      );
+    }
+  }
+
+  let (meta, data) = (decoder.meta().unwrap(), decoder.file().unwrap());
+  if !meta.check(&data) {
+    return Err(Error::new(
+      ErrorKind::InvalidData,
+      "the file decoded does not match its hash",
+    ));
+  }
+  // only the last component of the name, the file stays in the output directory
+  let name = Path::new(&meta.name).file_name().ok_or(ErrorKind::InvalidInput)?;
+  let path = output_dir.join(name);
+  std::fs::write(&path, data)?;
+  println!("saved {}", path.display());
+  Ok(())
+}
@@ -0,0 +1,51 @@
+use clap::Parser;
+use proj1_acoustic_link::traits::PacketSender;
+use proj2_multiple_access::FountainEncoder;
+use proj3_gateway::AudioDeviceCli;
+use std::{
+  io::{Error, Result},
+  path::PathBuf,
+};
+
+/// Broadcast a file with no feedback channel: loop the packets of a fountain code,
+/// until interrupted or the number of packets is sent
+#[derive(Parser)]
+struct BroadcastSendCli {
+  /// the file to broadcast
+  file: PathBuf,
+  /// stop after sending this number of packets, loop forever if not given
+  #[arg(long)]
+  packets: Option<usize>,
+
+  #[command(flatten)]
+  audio: AudioDeviceCli,
+}
+
+fn main() -> Result<()> {
+  env_logger::init();
+
+  let BroadcastSendCli { file, packets, audio } = BroadcastSendCli::parse();
+  if audio.list_devices {
+    return audio.print_devices();
+  }
+  let data = std::fs::read(&file)?;
+  let name = file.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+
+  let mut phy = audio.build_phy()?;
+  let encoder = FountainEncoder::new(&name, &data, phy.packet_bytes());
+  println!(
+    "broadcast {} ({} bytes) in {} source symbols",
+    encoder.meta().name,
+    encoder.meta().size,
+    encoder.symbols()
+  );
+  for (i, packet) in encoder.take(packets.unwrap_or(usize::MAX)).enumerate() {
+    phy
+      .send(packet)
+      .map_err(|e| Error::other(format!("failed to send packet #{}: {:?}", i, e)))?;
+    if (i + 1) % 100 == 0 {
+      println!("{} packets sent", i + 1);
+    }
+  }
+  Ok(())
+}
@@ -1,6 +1,6 @@
 use clap::Parser;
 use proj2_multiple_access::MacAddr;
-use proj3_gateway::IpLayerInternal;
+use proj3_gateway::{AudioDeviceCli, IpLayerInternal};
 use std::{io::Result, net::Ipv4Addr};
 
 /// IP layer server running on the internal node of Athernet (node1)
@@ -18,6 +18,9 @@ struct AnetIpCli {
   /// Athernet MAC address of internal node
   #[arg(long, default_value_t = 2)]
   internal_mac: u8,
+
+  #[command(flatten)]
+  audio: AudioDeviceCli,
 }
 
 fn main() -> Result<()> {
@@ -28,13 +31,17 @@ fn main() -> Result<()> {
     gateway_mac,
     internal_ip,
     internal_mac,
+    audio,
   } = AnetIpCli::parse();
+  if audio.list_devices {
+    return audio.print_devices();
+  }
   let gateway_mac = MacAddr(gateway_mac);
   let internal_mac = MacAddr(internal_mac);
 
   let gateway_addr = (gateway_mac, gateway_ip);
   let internal_addr = (internal_mac, internal_ip);
-  let mut ip_layer = IpLayerInternal::new(internal_addr, gateway_addr)?;
+  let mut ip_layer = IpLayerInternal::with_phy(internal_addr, gateway_addr, audio.build_phy()?)?;
   ip_layer.run();
 
   Ok(())
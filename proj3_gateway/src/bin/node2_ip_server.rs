@@ -1,5 +1,5 @@
 use proj2_multiple_access::MacAddr;
-use proj3_gateway::IpLayerGateway;
+use proj3_gateway::{AudioDeviceCli, IpLayerGateway};
 use std::{io::Result, net::Ipv4Addr};
 
 use clap::Parser;
@@ -22,6 +22,9 @@ struct NatCli {
 
   /// Internet IP address of the gateway/NAT
   nat_ip: Ipv4Addr,
+
+  #[command(flatten)]
+  audio: AudioDeviceCli,
 }
 
 fn main() -> Result<()> {
@@ -33,13 +36,17 @@ fn main() -> Result<()> {
     internal_ip,
     internal_mac,
     nat_ip,
+    audio,
   } = NatCli::parse();
+  if audio.list_devices {
+    return audio.print_devices();
+  }
   let gateway_mac = MacAddr(gateway_mac);
   let internal_mac = MacAddr(internal_mac);
 
   let gateway_addr = (gateway_mac, gateway_ip);
   let internal_addr = (internal_mac, internal_ip);
-  let mut ip_layer = IpLayerGateway::new(gateway_addr, internal_addr, nat_ip)?;
+  let mut ip_layer = IpLayerGateway::with_phy(gateway_addr, internal_addr, nat_ip, audio.build_phy()?)?;
   ip_layer.run();
 
   Ok(())
@@ -0,0 +1,54 @@
+use clap::Args;
+use proj1_acoustic_link::{
+  phy_layer::{BoxedPhy, PhyBuilder, PhyKind},
+  sample_stream::device::{host, hosts, input_devices, output_devices, DeviceSelector},
+};
+use std::io::{Error, ErrorKind, Result};
+
+/// Command line options to select the audio devices and the kind of the Athernet PHY layer
+#[derive(Args)]
+pub struct AudioDeviceCli {
+  /// PHY layer: plain, crc, atomic, fec, fsk or ofdm
+  #[arg(long, default_value_t = PhyKind::default())]
+  pub phy: PhyKind,
+  /// List the available audio hosts and devices, then exit
+  #[arg(long)]
+  pub list_devices: bool,
+  /// Audio host (e.g. ALSA, JACK), use the default host if not given
+  #[arg(long)]
+  pub audio_host: Option<String>,
+  /// Input device: "default", a device index or a device name
+  #[arg(long, default_value = "default")]
+  pub input_device: DeviceSelector,
+  /// Output device: "default", a device index or a device name
+  #[arg(long, default_value = "default")]
+  pub output_device: DeviceSelector,
+}
+
+impl AudioDeviceCli {
+  /// Print the available audio hosts, and the devices of the selected host with their indices
+  pub fn print_devices(&self) -> Result<()> {
+    println!("audio hosts: {}", hosts().join(", "));
+    let host = host(self.audio_host.as_deref()).map_err(|e| Error::new(ErrorKind::NotFound, e))?;
+    println!("devices of host {}:", host.id().name());
+    let inputs = input_devices(&host).map_err(Error::other)?;
+    inputs
+      .iter()
+      .enumerate()
+      .for_each(|(i, name)| println!("  input  #{}: {}", i, name));
+    let outputs = output_devices(&host).map_err(Error::other)?;
+    outputs
+      .iter()
+      .enumerate()
+      .for_each(|(i, name)| println!("  output #{}: {}", i, name));
+    Ok(())
+  }
+
+  /// Build the selected PHY layer on the selected audio devices
+  pub fn build_phy(&self) -> Result<BoxedPhy> {
+    let host = host(self.audio_host.as_deref()).map_err(|e| Error::new(ErrorKind::NotFound, e))?;
+    PhyBuilder::new(self.phy)
+      .build_on_devices(&host, &self.input_device, &self.output_device)
+      .map_err(|e| Error::new(ErrorKind::NotFound, e))
+  }
+}
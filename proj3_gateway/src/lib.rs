@@ -9,6 +9,10 @@ pub use aip_layer::{IpAccessor, IpLayerGateway, IpLayerInternal};
 mod socket;
 pub use socket::{ASockProtocol, IcmpSocket, TcpListener, TcpStream, UdpSocket};
 
+/// Command line options shared by the binaries: audio device selection
+mod cli;
+pub use cli::AudioDeviceCli;
+
 /// Define common constant values: timeout length, maximum packet size ...
 mod common;
 
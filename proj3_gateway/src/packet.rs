@@ -6,6 +6,7 @@ use pnet::packet::{
   udp::{ipv4_checksum as udp_checksum, *},
   FromPacket, Packet,
 };
+use proj1_acoustic_link::{phy_layer::BoxedPhy, phy_packet::params::ParamError};
 use proj2_multiple_access::{MacAddr, MacLayer};
 use std::{collections::VecDeque, net::Ipv4Addr};
 
@@ -83,7 +84,7 @@ pub(crate) fn compose_tcp(tcp: &Tcp, src: Ipv4Addr, dest: Ipv4Addr) -> Ipv4 {
 /// - [0..2]: (1 bit) last fragment flag + (15 bit) data length
 /// - [2..N]: data chunk
 pub(crate) struct IpPackFrag {
-  data: Vec<u8>,
+  pub data: Vec<u8>,
   pub last: bool,
 }
 
@@ -100,18 +101,22 @@ fn decompose_u16(two_bytes: u16) -> (u8, u8) {
 }
 
 impl IpPackFrag {
-  /// maximum data size per fragment: 1 bytes for is last,
-  const FRAG_SIZE: usize = MacLayer::MTU - 2;
-  /// parse a fragment from a MAC packet payload
-  pub(crate) fn from_mac_payload(mac_payload: &[u8]) -> Self {
-    let (head, data) = mac_payload.split_at(2);
+  /// number of bytes of the fragment header
+  const HEADER_SIZE: usize = 2;
+  /// Parse a fragment from a MAC packet payload,
+  /// return `Option::None` if the payload is shorter than the header or the length in the header.
+  pub(crate) fn from_mac_payload(mac_payload: &[u8]) -> Option<Self> {
+    if mac_payload.len() < Self::HEADER_SIZE {
+      return None;
+    }
+    let (head, data) = mac_payload.split_at(Self::HEADER_SIZE);
 
     let head = combine_u16(head[0], head[1]);
     let last = (head & 0b_1000_0000_0000_0000) != 0;
     let len = (head & 0b_0111_1111_1111_1111) as usize;
 
-    let data = data[..len].to_vec();
-    Self { data, last }
+    let data = data.get(..len)?.to_vec();
+    Some(Self { data, last })
   }
   /// populate a MAC packet payload with a fragment
   pub(crate) fn into_mac_payload(self) -> Vec<u8> {
@@ -130,14 +135,15 @@ impl IpPackFrag {
   }
 }
 
-/// Split a IPv4 packet into chunks so that the packet can be send over MAC layer
-fn fragment_ipv4(ipv4: &Ipv4) -> Vec<IpPackFrag> {
+/// Split a IPv4 packet into chunks so that the packet can be send over MAC layer,
+/// each fragment fits in `mtu` bytes with its header.
+fn fragment_ipv4(ipv4: &Ipv4, mtu: usize) -> Vec<IpPackFrag> {
   let mut buf = vec![0; ipv4.total_length as usize];
   let mut pack = MutableIpv4Packet::new(&mut buf).unwrap();
   pack.populate(ipv4);
 
   let mut fragments: Vec<_> = buf
-    .chunks(IpPackFrag::FRAG_SIZE)
+    .chunks(mtu - IpPackFrag::HEADER_SIZE)
     .map(|chunk| IpPackFrag {
       data: Vec::from(chunk),
       last: false,
@@ -148,12 +154,13 @@ fn fragment_ipv4(ipv4: &Ipv4) -> Vec<IpPackFrag> {
   fragments
 }
 
-/// Reassemble an IPv4 packet from bytes
-fn reassemble_ipv4(bytes: impl Iterator<Item = u8>) -> Ipv4 {
+/// Reassemble an IPv4 packet from bytes,
+/// return `Option::None` if the bytes are too short for an IPv4 packet
+fn reassemble_ipv4(bytes: impl Iterator<Item = u8>) -> Option<Ipv4> {
   let buf: Vec<_> = bytes.collect();
-  let packet = Ipv4Packet::new(&buf).unwrap();
+  let packet = Ipv4Packet::new(&buf)?;
 
-  packet.from_packet()
+  Some(packet.from_packet())
 }
 
 /// A wrapper of MAC layer object: sending/receiving IP packets via MAC.
@@ -168,19 +175,28 @@ pub(crate) struct IpOverMac {
 }
 
 impl IpOverMac {
-  pub fn new(self_addr: MacAddr, peer_addr: MacAddr) -> Self {
-    Self {
-      mac: MacLayer::new_with_default_phy(self_addr),
+  /// Build the MAC layer on a given PHY layer object,
+  /// fail if the MAC packets can not hold the fragment header and some data.
+  pub fn new(self_addr: MacAddr, peer_addr: MacAddr, phy: BoxedPhy) -> Result<Self, ParamError> {
+    let mac = MacLayer::new(self_addr, phy)?;
+    if mac.mtu() <= IpPackFrag::HEADER_SIZE {
+      return Err(ParamError::Invalid(
+        "MAC transmission unit",
+        "a MAC packet must hold the fragment header and some data",
+      ));
+    }
+    Ok(Self {
+      mac,
       _self_addr: self_addr,
       peer_addr,
       recv_frags: Default::default(),
       send_frags: Default::default(),
-    }
+    })
   }
   /// schedule to send a packet
   pub fn send(&mut self, ipv4: &Ipv4) {
     log::debug!("send ipv4 via MAC: {:?} -> {:?}", ipv4.source, ipv4.destination);
-    self.send_frags.extend(fragment_ipv4(ipv4));
+    self.send_frags.extend(fragment_ipv4(ipv4, self.mac.mtu()));
   }
   /// Called every iteration.
   /// Send a fragment to peer
@@ -200,7 +216,12 @@ impl IpOverMac {
   pub fn recv_poll(&mut self) -> Option<Ipv4> {
     log::trace!("try to receive a fragment from MAC");
     if let Some(frag) = self.mac.try_recv() {
-      let frag = IpPackFrag::from_mac_payload(&frag);
+      let Some(frag) = IpPackFrag::from_mac_payload(&frag) else {
+        // the PHY layer may not check the integrity of the packets
+        log::warn!("drop a malformed fragment from MAC");
+        self.recv_frags.clear();
+        return None;
+      };
       log::trace!(
         "have a fragment to received from MAC: len={}, last={}",
         frag.data.len(),
@@ -209,7 +230,10 @@ impl IpOverMac {
       let last = frag.last;
       self.recv_frags.extend(frag.data);
       if last {
-        let ipv4 = reassemble_ipv4(self.recv_frags.drain(..));
+        let Some(ipv4) = reassemble_ipv4(self.recv_frags.drain(..)) else {
+          log::warn!("drop a malformed ipv4 packet from MAC");
+          return None;
+        };
         log::debug!("receive ipv4 via MAC: {:?} -> {:?}", ipv4.source, ipv4.destination);
         return Some(ipv4);
       }
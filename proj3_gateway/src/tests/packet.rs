@@ -1,6 +1,8 @@
 use std::net::Ipv4Addr;
 
-use crate::packet::{compose_icmp, compose_ipv4, compose_tcp, compose_udp, parse_icmp, parse_tcp, parse_udp};
+use crate::packet::{
+  compose_icmp, compose_ipv4, compose_tcp, compose_udp, parse_icmp, parse_tcp, parse_udp, IpPackFrag,
+};
 use pnet::packet::{
   icmp::{Icmp, IcmpCode, IcmpTypes},
   tcp::Tcp,
@@ -126,3 +128,17 @@ fn icmp_pack_unpack() {
     icmp_once();
   }
 }
+#[test]
+fn frag_malformed() {
+  let frag = IpPackFrag {
+    data: vec![1, 2, 3],
+    last: true,
+  };
+  let payload = frag.into_mac_payload();
+  let frag = IpPackFrag::from_mac_payload(&payload).unwrap();
+  assert_eq!(frag.data, [1, 2, 3]);
+  assert!(frag.last);
+  // shorter than the header, or than the length in the header
+  assert!(IpPackFrag::from_mac_payload(&payload[..1]).is_none());
+  assert!(IpPackFrag::from_mac_payload(&payload[..4]).is_none());
+}